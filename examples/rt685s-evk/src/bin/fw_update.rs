@@ -15,8 +15,8 @@ use embassy_time::Delay;
 use mimxrt600_fcb::FlexSPIFlashConfigurationBlock;
 use static_cell::StaticCell;
 use tps6699x::asynchronous::embassy as pd_controller;
-use tps6699x::asynchronous::fw_update::perform_fw_update_borrowed;
-use tps6699x::fw_update::UpdateConfig;
+use tps6699x::asynchronous::fw_update::perform_fw_update_borrowed_with_progress;
+use tps6699x::fw_update::{FwImage, UpdateConfig};
 use tps6699x::ADDR0;
 use {defmt_rtt as _, panic_probe as _};
 
@@ -70,9 +70,16 @@ async fn main(spawner: Spawner) {
 
     let mut controllers = [&mut pd];
     let mut guards = [const { None }; 2];
-    perform_fw_update_borrowed(&mut controllers, &mut guards, &mut delay, config, pd_fw_bytes)
-        .await
-        .unwrap();
+    perform_fw_update_borrowed_with_progress(
+        &mut controllers,
+        &mut guards,
+        &mut delay,
+        config,
+        &FwImage::new(pd_fw_bytes).unwrap(),
+        |progress| info!("FW update progress: {:?}", progress),
+    )
+    .await
+    .unwrap();
 
     let mut controllers = [&mut pd];
     for (i, controller) in controllers.iter_mut().enumerate() {
@@ -15,8 +15,7 @@ use embedded_usb_pd::LocalPortId;
 use mimxrt600_fcb::FlexSPIFlashConfigurationBlock;
 use static_cell::StaticCell;
 use tps6699x::asynchronous::embassy as pd_controller;
-use tps6699x::registers::field_sets::IntEventBus1;
-use tps6699x::{ADDR0, MAX_SUPPORTED_PORTS};
+use tps6699x::ADDR0;
 use {defmt_rtt as _, panic_probe as _};
 
 bind_interrupts!(struct Irqs {
@@ -53,37 +52,22 @@ async fn main(spawner: Spawner) {
     spawner.spawn(interrupt_task(int_in, interrupt_processor).unwrap());
 
     loop {
-        let mut plug_event_mask = IntEventBus1::new_zero();
-        plug_event_mask.set_plug_event(true);
-        let flags = interrupt_receiver
-            .wait_any_masked(false, [plug_event_mask; MAX_SUPPORTED_PORTS])
-            .await;
-
-        for (i, flag) in flags.iter().enumerate().take(pd.num_ports()) {
-            if *flag == IntEventBus1::new_zero() {
-                continue;
-            }
-
+        let changed = interrupt_receiver.wait_connection_change(false).await;
+
+        for (i, _) in changed
+            .iter()
+            .enumerate()
+            .take(pd.num_ports())
+            .filter(|(_, changed)| **changed)
+        {
             let port = LocalPortId(i as u8);
-            info!("{:?}: plug event", port);
-            let result = pd.get_port_status(port).await;
-            if let Err(e) = result {
-                info!("{:?}: error getting port status: {:?}", port, e);
-                continue;
-            }
-
-            let status = result.unwrap();
-            if status.plug_present() {
-                info!("{:?}: plug present", port);
-                if status.plug_orientation() {
-                    info!("{:?}: flipped ", port);
-                } else {
-                    info!("{:?}: not flipped", port);
-                }
-
-                info!("{:?}: connection state {:?}", port, status.connection_state());
-            } else {
-                info!("{:?}: plug removed", port);
+            match pd.get_connection_state(port).await {
+                Ok(state) if state.connected => info!(
+                    "{:?}: connected, orientation {:?}, {:?}, {:?}",
+                    port, state.orientation, state.power_role, state.data_role
+                ),
+                Ok(_) => info!("{:?}: disconnected", port),
+                Err(e) => info!("{:?}: error getting connection state: {:?}", port, e),
             }
         }
     }
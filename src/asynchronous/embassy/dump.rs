@@ -0,0 +1,40 @@
+//! Register snapshots, see [`super::Tps6699x::dump_port_registers`], [`super::Tps6699x::dump_controller_registers`]
+//! and [`super::Tps6699x::get_connection_snapshot`].
+
+use crate::Mode;
+use crate::registers::boot_flags::BootFlags;
+use crate::registers::field_sets::{
+    ActivePdoContract, ActiveRdoContract, IntEventBus1, PdStatus, PortControl, PowerPathStatus, Status,
+};
+
+/// Snapshot of a port's status registers
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortRegisterDump {
+    pub status: Status,
+    pub pd_status: PdStatus,
+    pub port_control: PortControl,
+    pub power_path_status: PowerPathStatus,
+    pub active_pdo_contract: ActivePdoContract,
+    pub active_rdo_contract: ActiveRdoContract,
+    /// Asserted interrupts, these are not cleared by the dump
+    pub int_event_bus_1: IntEventBus1,
+}
+
+/// Snapshot of the controller-wide registers
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ControllerRegisterDump {
+    pub mode: Mode,
+    pub fw_version: u32,
+    pub boot_flags: BootFlags,
+}
+
+/// Snapshot of the registers describing a port's connection, see [`super::Tps6699x::get_connection_snapshot`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConnectionSnapshot {
+    pub status: Status,
+    pub pd_status: PdStatus,
+    pub active_pdo_contract: ActivePdoContract,
+}
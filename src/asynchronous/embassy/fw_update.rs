@@ -11,6 +11,40 @@ use crate::command::*;
 use crate::fw_update::*;
 use crate::{PORT0, error, info, warn};
 
+impl<M: RawMutex, B: I2c> Tps6699x<'_, M, B> {
+    /// Query the status of the FW update with the TFUq command
+    ///
+    /// This can be used to check whether an update is in progress and which blocks have been accepted without driving
+    /// the update itself.
+    pub async fn query_fw_update_status(&mut self) -> Result<TfuqReturnValue, Error<B::Error>> {
+        let args = TfuqArgs {
+            command: TfuqCommandType::QueryTfuStatus,
+            status_query: TfuqStatusQuery::StatusInProgress,
+        };
+
+        let mut arg_bytes = [0u8; TFUQ_ARGS_LEN];
+        let mut return_bytes = [0u8; TFUQ_RETURN_LEN];
+
+        bincode::encode_into_slice(args, &mut arg_bytes, config::standard().with_fixed_int_encoding())
+            .map_err(|_| PdError::Serialize)?;
+
+        let result = self
+            .execute_command(PORT0, Command::Tfuq, Some(&arg_bytes), Some(&mut return_bytes))
+            .await?;
+
+        if result != ReturnValue::Success {
+            error!("FW update status query failed {:?}", result);
+            return PdError::Failed.into();
+        }
+
+        let (ret, _): (TfuqReturnValue, _) =
+            bincode::decode_from_slice(&return_bytes, config::standard().with_fixed_int_encoding())
+                .map_err(|_| PdError::Serialize)?;
+
+        Ok(ret)
+    }
+}
+
 impl<M: RawMutex, B: I2c> UpdateTarget for Tps6699x<'_, M, B> {
     /// Enter firmware update mode with the TFUs command
     async fn fw_update_mode_enter(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<Self::BusError>> {
@@ -62,43 +96,29 @@ impl<M: RawMutex, B: I2c> UpdateTarget for Tps6699x<'_, M, B> {
     /// Validate the most recent block of data with a TFUq command
     async fn fw_update_validate_stream(
         &mut self,
-        _delay: &mut impl DelayNs,
+        delay: &mut impl DelayNs,
         block_index: usize,
     ) -> Result<TfuqBlockStatus, Error<Self::BusError>> {
         if block_index > TFUQ_RETURN_BLOCK_STATUS_LEN {
             return PdError::InvalidParams.into();
         }
 
-        let args = TfuqArgs {
-            command: TfuqCommandType::QueryTfuStatus,
-            status_query: TfuqStatusQuery::StatusInProgress,
-        };
-
-        let mut arg_bytes = [0u8; 2];
-        let mut return_bytes = [0u8; TFUQ_RETURN_LEN];
-
-        bincode::encode_into_slice(args, &mut arg_bytes, config::standard().with_fixed_int_encoding())
-            .map_err(|_| PdError::Serialize)?;
-
-        let result = self
-            .execute_command(PORT0, Command::Tfuq, Some(&arg_bytes), Some(&mut return_bytes))
-            .await?;
-
-        if result != ReturnValue::Success {
-            error!("Validate stream failed {:?}", result);
-            return PdError::Failed.into();
-        }
-
-        let (ret, _): (TfuqReturnValue, _) =
-            bincode::decode_from_slice(&return_bytes, config::standard().with_fixed_int_encoding())
-                .map_err(|_| PdError::Serialize)?;
-
-        ret.block_status
+        self.fw_update_query_status(delay)
+            .await?
+            .block_status
             .get(block_index)
             .cloned()
             .ok_or(Error::Pd(PdError::InvalidParams))
     }
 
+    /// Query the status of the FW update in progress with a TFUq command
+    async fn fw_update_query_status(
+        &mut self,
+        _delay: &mut impl DelayNs,
+    ) -> Result<TfuqReturnValue, Error<Self::BusError>> {
+        self.query_fw_update_status().await
+    }
+
     async fn fw_update_stream_data(
         &mut self,
         _delay: &mut impl DelayNs,
@@ -137,6 +157,10 @@ impl<M: RawMutex, B: I2c> UpdateTarget for Tps6699x<'_, M, B> {
         }
     }
 
+    /// Write data to the burst write address
+    ///
+    /// The inner lock is only held for the duration of this write so other tasks can access the controller
+    /// between writes.
     async fn fw_update_burst_write(&mut self, address: u8, data: &[u8]) -> Result<(), Error<Self::BusError>> {
         let mut inner = self.controller.inner.lock().await;
 
@@ -1,8 +1,6 @@
 //! Interrupt related code.
-use core::array::from_fn;
-
 use embassy_sync::blocking_mutex::raw::RawMutex;
-use embassy_time::{Duration, with_timeout};
+use embassy_time::{Duration, Timer, with_timeout};
 use embedded_hal::digital::InputPin;
 use embedded_hal_async::i2c::I2c;
 use embedded_usb_pd::{Error, LocalPortId, PdError};
@@ -16,12 +14,18 @@ use crate::{MAX_SUPPORTED_PORTS, error, trace, warn};
 #[non_exhaustive]
 pub struct Config {
     pub interrupt_timeout: Duration,
+    /// Delay before clearing interrupts once processing starts
+    ///
+    /// The controller latches events until they're cleared, so events arriving within this window are coalesced
+    /// into a single read instead of each being processed separately. Zero disables debouncing.
+    pub interrupt_debounce: Duration,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             interrupt_timeout: Duration::from_millis(100),
+            interrupt_debounce: Duration::from_ticks(0),
         }
     }
 }
@@ -38,11 +42,13 @@ impl<'a, M: RawMutex, B: I2c> InterruptProcessor<'a, M, B> {
         int: &mut impl InputPin,
     ) -> Result<[IntEventBus1; MAX_SUPPORTED_PORTS], Error<B::Error>> {
         let timeout = self.controller.config.interrupt_processor_config.interrupt_timeout;
-        let mut flags = self
-            .controller
-            .interrupt_waker
-            .try_take()
-            .unwrap_or([IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS]);
+        let debounce = self.controller.config.interrupt_processor_config.interrupt_debounce;
+        if debounce.as_ticks() > 0 {
+            trace!("Debouncing interrupts for {:?}", debounce);
+            Timer::after(debounce).await;
+        }
+
+        let mut flags = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
 
         let interrupts_enabled = self.controller.interrupts_enabled();
         let mut inner = self.controller.inner.lock().await;
@@ -67,7 +73,8 @@ impl<'a, M: RawMutex, B: I2c> InterruptProcessor<'a, M, B> {
 
             match int.is_high() {
                 Ok(true) => {
-                    // Early exit if checking the last port cleared the interrupt
+                    // The interrupt line is shared and active low, so once it's de-asserted no remaining port has
+                    // pending interrupts. Stop instead of checking the line again for each port.
                     trace!("Interrupt line is high, exiting");
                     break;
                 }
@@ -82,6 +89,8 @@ impl<'a, M: RawMutex, B: I2c> InterruptProcessor<'a, M, B> {
                 Ok(res) => match res {
                     Ok(event) => {
                         *flag |= event;
+                        self.controller.invalidate_rx_src_caps_cache(port_id, &event);
+                        self.controller.clear_hpd(port_id, &event);
                         if event.cmd_1_completed() {
                             command_complete.signal(());
                         }
@@ -98,7 +107,7 @@ impl<'a, M: RawMutex, B: I2c> InterruptProcessor<'a, M, B> {
             }
         }
 
-        self.controller.interrupt_waker.signal(flags);
+        self.controller.signal_interrupts(flags);
         Ok(flags)
     }
 }
@@ -128,69 +137,41 @@ impl<M: RawMutex, B: I2c> Drop for InterruptGuard<'_, M, B> {
 
 impl<M: RawMutex, B: I2c> crate::asynchronous::interrupt::InterruptGuard for InterruptGuard<'_, M, B> {}
 
-/// Struct to ensure drop-safety of [`InterruptReceiver::wait_any_masked`]
+/// Stream of interrupt events, see [`InterruptReceiver::event_stream`]
 ///
-/// This struct re-signals any unhandled interrupts on drop.
-struct AccumulatedFlagsAny<'a, M: RawMutex, B: I2c> {
-    controller: &'a Controller<M, B>,
-    accumulated_flags: [IntEventBus1; MAX_SUPPORTED_PORTS],
-    masks: [IntEventBus1; MAX_SUPPORTED_PORTS],
+/// Drop safety: Safe, events that haven't been returned by [`Self::next_event`] are returned to the pending
+/// interrupts on drop.
+pub struct EventStream<'r, 'a, M: RawMutex, B: I2c> {
+    receiver: &'r mut InterruptReceiver<'a, M, B>,
+    pending: [IntEventBus1; MAX_SUPPORTED_PORTS],
 }
 
-impl<'a, M: RawMutex, B: I2c> AccumulatedFlagsAny<'a, M, B> {
-    fn new(controller: &'a Controller<M, B>, masks: [IntEventBus1; MAX_SUPPORTED_PORTS]) -> Self {
-        AccumulatedFlagsAny {
-            controller,
-            accumulated_flags: [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS],
-            masks,
-        }
-    }
-
-    fn accumulate(
-        &mut self,
-        flags: [IntEventBus1; MAX_SUPPORTED_PORTS],
-    ) -> Option<[IntEventBus1; MAX_SUPPORTED_PORTS]> {
-        let mut done = false;
-        for (&flags, &mask, accumulated) in izip!(flags.iter(), self.masks.iter(), self.accumulated_flags.iter_mut(),) {
-            *accumulated |= flags;
-            let consumed_flags = flags & mask;
-            if consumed_flags != IntEventBus1::new_zero() {
-                done = true;
+impl<M: RawMutex, B: I2c> EventStream<'_, '_, M, B> {
+    /// Wait for the next port with asserted events, returns the port and its events
+    ///
+    /// Ports are returned in order when multiple ports have events from the same interrupt.
+    pub async fn next_event(&mut self) -> (LocalPortId, IntEventBus1) {
+        loop {
+            if let Some((port, flags)) = self
+                .pending
+                .iter_mut()
+                .enumerate()
+                .find(|(_, flags)| **flags != IntEventBus1::new_zero())
+            {
+                let flags = core::mem::replace(flags, IntEventBus1::new_zero());
+                return (LocalPortId(port as u8), flags);
             }
-        }
 
-        if done {
-            // Panic safety: the return type, `accumulated_flags`, and `mask` are all of size MAX_SUPPORTED_PORTS
-            // so this will never index out of bounds
-            #[allow(clippy::indexing_slicing)]
-            let handled = from_fn(|i| self.accumulated_flags[i] & self.masks[i]);
-            // Put unhandled flags back for signaling in `drop()`
-            self.accumulated_flags = from_fn(|i| self.accumulated_flags[i] & !self.masks[i]);
-            Some(handled)
-        } else {
-            None
+            self.pending = self.receiver.wait_any(false).await;
         }
     }
 }
 
-impl<M: RawMutex, B: I2c> Drop for AccumulatedFlagsAny<'_, M, B> {
+impl<M: RawMutex, B: I2c> Drop for EventStream<'_, '_, M, B> {
     fn drop(&mut self) {
-        // Catch any flags that may have happened since the last accumulate.
-        let new = self
-            .controller
-            .interrupt_waker
-            .try_take()
-            .unwrap_or([IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS]);
-        // Panic safety: `unhandled`, `accumulated_flags`, and `mask` are all of size MAX_SUPPORTED_PORTS
-        // so this will never index out of bounds
-        #[allow(clippy::indexing_slicing)]
-        let unhandled = from_fn(|i| self.accumulated_flags[i] | new[i]);
-
-        // Put back any unhandled interrupt flags for future processing
-        if unhandled.iter().any(|&f| f != IntEventBus1::new_zero()) {
-            // If there are unhandled flags, signal them for future processing
-            trace!("Signaling unhandled interrupt flags: {:?}", unhandled);
-            self.controller.interrupt_waker.signal(unhandled);
+        if self.pending.iter().any(|&f| f != IntEventBus1::new_zero()) {
+            trace!("Signaling unhandled interrupt flags: {:?}", self.pending);
+            self.receiver.controller.signal_interrupts(self.pending);
         }
     }
 }
@@ -205,13 +186,19 @@ pub struct InterruptReceiver<'a, M: RawMutex, B: I2c> {
 impl<'a, M: RawMutex, B: I2c> InterruptReceiver<'a, M, B> {
     /// Wait for an interrupt to occur.
     ///
-    /// Drop safety: Safe, unhandled interrupts will be re-signaled.
+    /// Drop safety: Safe, interrupts are only acknowledged once they are returned.
     pub async fn wait_any(&mut self, clean_current: bool) -> [IntEventBus1; MAX_SUPPORTED_PORTS] {
         self.wait_any_masked(clean_current, [IntEventBus1::all(); MAX_SUPPORTED_PORTS])
             .await
     }
 
     /// Wait for an interrupt to occur that matches any bits in the given mask.
+    ///
+    /// Asserted flags stay pending until a receiver takes them, so flags from several interrupts that occur before
+    /// this is polled are all returned together. Only flags in `mask` are returned and acknowledged, others are left
+    /// pending for other receivers. If `clear_current` is set, pending flags in `mask` are discarded before waiting.
+    ///
+    /// Drop safety: Safe, interrupts are only acknowledged once they are returned.
     pub async fn wait_any_masked(
         &mut self,
         clear_current: bool,
@@ -225,29 +212,269 @@ impl<'a, M: RawMutex, B: I2c> InterruptReceiver<'a, M, B> {
         }
 
         if clear_current {
-            self.controller.interrupt_waker.reset();
+            self.controller.take_interrupts(mask);
         }
 
-        let mut accumulated_flags = AccumulatedFlagsAny::new(self.controller, mask);
         loop {
-            let flags = self.controller.interrupt_waker.wait().await;
-            if let Some(flags) = accumulated_flags.accumulate(flags) {
+            let flags = self.controller.take_interrupts(mask);
+            if flags.iter().any(|&f| f != IntEventBus1::new_zero()) {
                 return flags;
             }
+
+            self.controller.interrupt_waker.wait().await;
         }
     }
+
+    /// Returns a stream of interrupt events for all ports, see [`EventStream::next_event`]
+    ///
+    /// The interrupt processor never waits on the stream, so a consumer that lags behind won't stall interrupt
+    /// processing. Instead, events that arrive before the previous ones have been taken are OR'd together, so a
+    /// lagging consumer sees the union of everything asserted since its last call rather than each interrupt
+    /// separately. Repeated occurrences of the same event are therefore reported once.
+    pub fn event_stream(&mut self) -> EventStream<'_, 'a, M, B> {
+        EventStream {
+            receiver: self,
+            pending: [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS],
+        }
+    }
+
+    /// Wait for a change in the connection state of any port, see [`super::Tps6699x::get_connection_state`]
+    ///
+    /// Returns which ports had a plug event or completed a power, data or fast role swap. Drop safety: Safe, other
+    /// interrupts are left pending.
+    pub async fn wait_connection_change(&mut self, clear_current: bool) -> [bool; MAX_SUPPORTED_PORTS] {
+        let mut connection_mask = IntEventBus1::new_zero();
+        connection_mask.set_plug_event(true);
+        connection_mask.set_power_swap_completed(true);
+        connection_mask.set_data_swap_completed(true);
+        connection_mask.set_fast_role_swap_completed(true);
+
+        let mut mask = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+        for mask in mask.iter_mut().take(self.controller.num_ports) {
+            *mask = connection_mask;
+        }
+
+        self.wait_any_masked(clear_current, mask)
+            .await
+            .map(|flags| flags != IntEventBus1::new_zero())
+    }
+
+    /// Wait for an overcurrent event on any port, see [`super::Tps6699x::get_faults`]
+    ///
+    /// Returns which ports had an overcurrent event. Drop safety: Safe, other interrupts are left pending.
+    pub async fn wait_fault(&mut self, clear_current: bool) -> [bool; MAX_SUPPORTED_PORTS] {
+        let mut fault_mask = IntEventBus1::new_zero();
+        fault_mask.set_overcurrent(true);
+
+        let mut mask = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+        for mask in mask.iter_mut().take(self.controller.num_ports) {
+            *mask = fault_mask;
+        }
+
+        self.wait_any_masked(clear_current, mask)
+            .await
+            .map(|flags| flags != IntEventBus1::new_zero())
+    }
+
+    /// Wait for a fast role swap event on the given port
+    ///
+    /// Returns the fast role swap flags that were set, either [`IntEventBus1::frs_signal_received`] or
+    /// [`IntEventBus1::fast_role_swap_completed`]. Drop safety: Safe, other interrupts are left pending.
+    pub async fn wait_fast_role_swap(
+        &mut self,
+        clear_current: bool,
+        port: LocalPortId,
+    ) -> Result<IntEventBus1, PdError> {
+        let mut frs_mask = IntEventBus1::new_zero();
+        frs_mask.set_frs_signal_received(true);
+        frs_mask.set_fast_role_swap_completed(true);
+
+        let mut mask = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+        *mask.get_mut(port.0 as usize).ok_or(PdError::InvalidPort)? = frs_mask;
+
+        let flags = self.wait_any_masked(clear_current, mask).await;
+        flags.get(port.0 as usize).copied().ok_or(PdError::InvalidPort)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use embassy_sync::blocking_mutex::raw::NoopRawMutex;
     use embassy_time::{Duration, TimeoutError, with_timeout};
+    use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction as PinTransaction};
     use embedded_hal_mock::eh1::i2c::Mock;
     use static_cell::StaticCell;
 
     use super::*;
-    use crate::ADDR0;
     use crate::asynchronous::embassy::controller::Controller;
+    use crate::asynchronous::embassy::rx_caps::RxCaps;
+    use crate::test::{PORT0_ADDR0, create_register_read, create_register_write};
+    use crate::{ADDR0, PORT0, PORT1};
+
+    /// Tests that processing stops as soon as the interrupt line is de-asserted
+    #[tokio::test]
+    async fn test_process_interrupt_line_high() {
+        let mut event = IntEventBus1::new_zero();
+        event.set_plug_event(true);
+
+        // Only port 0 is read, clearing it de-asserts the line
+        let transactions = [
+            create_register_read(PORT0_ADDR0, 0x14, event),
+            create_register_write(PORT0_ADDR0, 0x18, event),
+        ];
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller =
+            CONTROLLER.init(Controller::new_tps66994(Mock::new(&transactions), Default::default(), ADDR0).unwrap());
+        let (mut pd, mut processor, _receiver) = controller.make_parts();
+
+        let mut int = PinMock::new(&[PinTransaction::get(State::Low), PinTransaction::get(State::High)]);
+        assert_eq!(
+            processor.process_interrupt(&mut int).await,
+            Ok([event, IntEventBus1::new_zero()])
+        );
+        int.done();
+
+        // Line already de-asserted, no port is read
+        let mut int = PinMock::new(&[PinTransaction::get(State::High)]);
+        assert_eq!(
+            processor.process_interrupt(&mut int).await,
+            Ok([IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS])
+        );
+        int.done();
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    /// Tests that flags from interrupts processed before a receiver runs are all returned together
+    #[tokio::test]
+    async fn test_process_interrupt_coalesce() {
+        let mut port0 = IntEventBus1::new_zero();
+        port0.set_plug_event(true);
+        let mut port1 = IntEventBus1::new_zero();
+        port1.set_sink_ready(true);
+
+        let transactions = [
+            // First interrupt, port 0 only
+            create_register_read(PORT0_ADDR0, 0x14, port0),
+            create_register_write(PORT0_ADDR0, 0x18, port0),
+            // Second interrupt, port 1 only
+            create_register_read(PORT0_ADDR0, 0x14, IntEventBus1::new_zero()),
+            create_register_read(ADDR0[1], 0x14, port1),
+            create_register_write(ADDR0[1], 0x18, port1),
+        ];
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller =
+            CONTROLLER.init(Controller::new_tps66994(Mock::new(&transactions), Default::default(), ADDR0).unwrap());
+        let (mut pd, mut processor, mut receiver) = controller.make_parts();
+
+        let mut int = PinMock::new(&[
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::High),
+            PinTransaction::get(State::Low),
+            PinTransaction::get(State::Low),
+        ]);
+        assert_eq!(
+            processor.process_interrupt(&mut int).await,
+            Ok([port0, IntEventBus1::new_zero()])
+        );
+        assert_eq!(
+            processor.process_interrupt(&mut int).await,
+            Ok([IntEventBus1::new_zero(), port1])
+        );
+        int.done();
+
+        assert_eq!(
+            with_timeout(Duration::from_millis(10), receiver.wait_any(false)).await,
+            Ok([port0, port1])
+        );
+
+        // Clearing current flags only discards the flags this receiver is interested in
+        pd.controller.signal_interrupts([port0, port1]);
+        let mut sink_ready = IntEventBus1::new_zero();
+        sink_ready.set_sink_ready(true);
+        assert_eq!(
+            with_timeout(
+                Duration::from_millis(10),
+                receiver.wait_any_masked(true, [IntEventBus1::new_zero(), sink_ready])
+            )
+            .await,
+            Err(TimeoutError)
+        );
+
+        assert_eq!(
+            with_timeout(Duration::from_millis(10), receiver.wait_any(false)).await,
+            Ok([port0, IntEventBus1::new_zero()])
+        );
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    /// Tests that a single port controller only reads and clears port 0's interrupts
+    #[tokio::test]
+    async fn test_process_interrupt_single_port() {
+        let mut event = IntEventBus1::new_zero();
+        event.set_plug_event(true);
+
+        let transactions = [
+            create_register_read(PORT0_ADDR0, 0x14, event),
+            create_register_write(PORT0_ADDR0, 0x18, event),
+        ];
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER
+            .init(Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap());
+        let (mut pd, mut processor, _receiver) = controller.make_parts();
+
+        // The interrupt line is only checked before port 0
+        let mut int = PinMock::new(&[PinTransaction::get(State::Low)]);
+        assert_eq!(
+            processor.process_interrupt(&mut int).await,
+            Ok([event, IntEventBus1::new_zero()])
+        );
+
+        int.done();
+        pd.lock_inner().await.bus.done();
+    }
+
+    /// Tests that `wait_connection_change` only returns on connection events and leaves others pending
+    #[tokio::test]
+    async fn test_wait_connection_change() {
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(Controller::new_tps66994(Mock::new(&[]), Default::default(), ADDR0).unwrap());
+        let (pd, _processor, mut receiver) = controller.make_parts();
+
+        let mut port0 = IntEventBus1::new_zero();
+        port0.set_sink_ready(true);
+        pd.controller.signal_interrupts([port0, IntEventBus1::new_zero()]);
+        assert_eq!(
+            with_timeout(Duration::from_millis(10), receiver.wait_connection_change(false)).await,
+            Err(TimeoutError)
+        );
+
+        let mut port1 = IntEventBus1::new_zero();
+        port1.set_data_swap_completed(true);
+        pd.controller.signal_interrupts([IntEventBus1::new_zero(), port1]);
+        assert_eq!(receiver.wait_connection_change(false).await, [false, true]);
+
+        // Unrelated events are still pending
+        assert_eq!(pd.controller.pending_interrupts(), [port0, IntEventBus1::new_zero()]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_fault() {
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(Controller::new_tps66994(Mock::new(&[]), Default::default(), ADDR0).unwrap());
+        let (pd, _processor, mut receiver) = controller.make_parts();
+
+        let mut port0 = IntEventBus1::new_zero();
+        port0.set_plug_event(true);
+        let mut port1 = IntEventBus1::new_zero();
+        port1.set_overcurrent(true);
+        pd.controller.signal_interrupts([port0, port1]);
+        assert_eq!(receiver.wait_fault(false).await, [false, true]);
+
+        // Unrelated events are still pending
+        assert_eq!(pd.controller.pending_interrupts(), [port0, IntEventBus1::new_zero()]);
+    }
 
     /// Tests `wait_any_masked` with a mask for both ports.
     #[tokio::test]
@@ -265,7 +492,7 @@ mod test {
         port1.set_plug_event(true);
         port1.set_alert_message_received(true);
 
-        pd.controller.interrupt_waker.signal([port0, port1]);
+        pd.controller.signal_interrupts([port0, port1]);
 
         let mut mask0 = IntEventBus1::new_zero();
         mask0.set_cmd_1_completed(true);
@@ -284,10 +511,7 @@ mod test {
         let unhandled1 = IntEventBus1::new_zero();
 
         // Should already be signaled
-        assert_eq!(
-            pd.controller.interrupt_waker.try_take().unwrap(),
-            [unhandled0, unhandled1]
-        );
+        assert_eq!(pd.controller.pending_interrupts(), [unhandled0, unhandled1]);
     }
 
     /// Tests `wait_any_masked` with a mask for a single port.
@@ -306,7 +530,7 @@ mod test {
         port1.set_plug_event(true);
         port1.set_alert_message_received(true);
 
-        pd.controller.interrupt_waker.signal([port0, port1]);
+        pd.controller.signal_interrupts([port0, port1]);
 
         let mut mask0 = IntEventBus1::new_zero();
         mask0.set_cmd_1_completed(true);
@@ -323,10 +547,7 @@ mod test {
         let unhandled1 = port1;
 
         // Should already be signaled
-        assert_eq!(
-            pd.controller.interrupt_waker.try_take().unwrap(),
-            [unhandled0, unhandled1]
-        );
+        assert_eq!(pd.controller.pending_interrupts(), [unhandled0, unhandled1]);
     }
 
     /// Tests `wait_any_masked` with both masks set to zero.
@@ -345,7 +566,7 @@ mod test {
         port1.set_plug_event(true);
         port1.set_alert_message_received(true);
 
-        pd.controller.interrupt_waker.signal([port0, port1]);
+        pd.controller.signal_interrupts([port0, port1]);
 
         let mask0 = IntEventBus1::new_zero();
         let mask1 = IntEventBus1::new_zero();
@@ -353,7 +574,7 @@ mod test {
         assert_eq!(flags, [mask0, mask1]);
 
         // Should already be signaled with nothing changed
-        assert_eq!(pd.controller.interrupt_waker.try_take().unwrap(), [port0, port1]);
+        assert_eq!(pd.controller.pending_interrupts(), [port0, port1]);
     }
 
     #[tokio::test]
@@ -370,7 +591,7 @@ mod test {
         let controller = CONTROLLER.init(Controller::new_tps66994(Mock::new(&[]), Default::default(), ADDR0).unwrap());
         let (pd, _processor, mut receiver) = controller.make_parts();
 
-        pd.controller.interrupt_waker.signal([port0, port1]);
+        pd.controller.signal_interrupts([port0, port1]);
 
         // The mask doesn't match the pending interrupts, so we should get a timeout
         let mut mask0 = IntEventBus1::new_zero();
@@ -405,6 +626,89 @@ mod test {
         assert_eq!(leftover_flags[1], leftover1);
     }
 
+    #[tokio::test]
+    async fn test_event_stream() {
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(Controller::new_tps66994(Mock::new(&[]), Default::default(), ADDR0).unwrap());
+        let (pd, _processor, mut receiver) = controller.make_parts();
+
+        let mut port0 = IntEventBus1::new_zero();
+        port0.set_plug_event(true);
+
+        let mut port1 = IntEventBus1::new_zero();
+        port1.set_cmd_1_completed(true);
+
+        pd.controller.signal_interrupts([port0, port1]);
+
+        let mut stream = receiver.event_stream();
+        assert_eq!(stream.next_event().await, (PORT0, port0));
+        assert_eq!(stream.next_event().await, (PORT1, port1));
+
+        // Nothing pending
+        assert_eq!(
+            with_timeout(Duration::from_millis(10), stream.next_event()).await,
+            Err(TimeoutError)
+        );
+
+        pd.controller.signal_interrupts([IntEventBus1::new_zero(), port0]);
+        assert_eq!(stream.next_event().await, (PORT1, port0));
+    }
+
+    /// Tests that events not yet returned by the stream are returned to the pending interrupts on drop
+    #[tokio::test]
+    async fn test_event_stream_drop() {
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(Controller::new_tps66994(Mock::new(&[]), Default::default(), ADDR0).unwrap());
+        let (pd, _processor, mut receiver) = controller.make_parts();
+
+        let mut port0 = IntEventBus1::new_zero();
+        port0.set_plug_event(true);
+
+        let mut port1 = IntEventBus1::new_zero();
+        port1.set_cmd_1_completed(true);
+
+        pd.controller.signal_interrupts([port0, port1]);
+
+        {
+            let mut stream = receiver.event_stream();
+            assert_eq!(stream.next_event().await, (PORT0, port0));
+        }
+
+        assert_eq!(pd.controller.pending_interrupts(), [IntEventBus1::new_zero(), port1]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_fast_role_swap() {
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(Controller::new_tps66994(Mock::new(&[]), Default::default(), ADDR0).unwrap());
+        let (pd, _processor, mut receiver) = controller.make_parts();
+
+        let mut port0 = IntEventBus1::new_zero();
+        port0.set_plug_event(true);
+        port0.set_fast_role_swap_completed(true);
+
+        let mut port1 = IntEventBus1::new_zero();
+        port1.set_frs_signal_received(true);
+
+        pd.controller.signal_interrupts([port0, port1]);
+
+        let mut expected = IntEventBus1::new_zero();
+        expected.set_fast_role_swap_completed(true);
+        assert_eq!(receiver.wait_fast_role_swap(false, PORT0).await, Ok(expected));
+
+        // Other flags are left for other receivers
+        let mut unhandled0 = IntEventBus1::new_zero();
+        unhandled0.set_plug_event(true);
+        assert_eq!(pd.controller.pending_interrupts(), [unhandled0, port1]);
+
+        assert_eq!(
+            receiver
+                .wait_fast_role_swap(false, LocalPortId(MAX_SUPPORTED_PORTS as u8))
+                .await,
+            Err(PdError::InvalidPort)
+        );
+    }
+
     /// Tests `wait_any`.
     #[tokio::test]
     async fn test_wait_any() {
@@ -421,7 +725,7 @@ mod test {
         port1.set_plug_event(true);
         port1.set_alert_message_received(true);
 
-        pd.controller.interrupt_waker.signal([port0, port1]);
+        pd.controller.signal_interrupts([port0, port1]);
 
         let mut flags0 = IntEventBus1::new_zero();
         flags0.set_new_consumer_contract(true);
@@ -443,4 +747,98 @@ mod test {
         .await;
         assert_eq!(leftover_flags, Err(TimeoutError));
     }
+
+    /// Tests that only contract-related events invalidate the cached Rx source caps.
+    #[tokio::test]
+    async fn test_invalidate_rx_src_caps_cache() {
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(Controller::new_tps66994(Mock::new(&[]), Default::default(), ADDR0).unwrap());
+
+        let caps = RxCaps {
+            spr: heapless::Vec::new(),
+            epr: heapless::Vec::new(),
+        };
+        let fill_cache = |controller: &Controller<NoopRawMutex, Mock>| {
+            for port in [PORT0, PORT1] {
+                let (generation, _) = controller.cached_rx_src_caps(port).unwrap();
+                controller.store_rx_src_caps(port, generation, &caps);
+            }
+        };
+        let is_cached = |controller: &Controller<NoopRawMutex, Mock>, port: LocalPortId| {
+            controller.cached_rx_src_caps(port).unwrap().1.is_some()
+        };
+
+        // Unrelated events leave the cache intact
+        fill_cache(controller);
+        let mut event = IntEventBus1::new_zero();
+        event.set_cmd_1_completed(true);
+        event.set_sink_ready(true);
+        controller.invalidate_rx_src_caps_cache(PORT0, &event);
+        assert!(is_cached(controller, PORT0));
+        assert!(is_cached(controller, PORT1));
+
+        let invalidating: [fn(&mut IntEventBus1); 7] = [
+            |e| e.set_plug_event(true),
+            |e| e.set_hard_reset(true),
+            |e| e.set_source_caps_received(true),
+            |e| e.set_new_consumer_contract(true),
+            |e| e.set_new_provider_contract(true),
+            |e| e.set_power_swap_completed(true),
+            |e| e.set_fast_role_swap_completed(true),
+        ];
+        for set in invalidating {
+            fill_cache(controller);
+            let mut event = IntEventBus1::new_zero();
+            set(&mut event);
+            controller.invalidate_rx_src_caps_cache(PORT1, &event);
+            assert!(is_cached(controller, PORT0));
+            assert!(!is_cached(controller, PORT1));
+        }
+
+        // Caps read before an invalidation must not be cached after it
+        let (generation, _) = controller.cached_rx_src_caps(PORT0).unwrap();
+        let mut event = IntEventBus1::new_zero();
+        event.set_source_caps_received(true);
+        controller.invalidate_rx_src_caps_cache(PORT0, &event);
+        controller.store_rx_src_caps(PORT0, generation, &caps);
+        assert!(!is_cached(controller, PORT0));
+    }
+
+    /// Tests that detach, hard reset and alt mode exit clear the HPD state.
+    #[tokio::test]
+    async fn test_clear_hpd() {
+        use core::sync::atomic::Ordering;
+
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(Controller::new_tps66994(Mock::new(&[]), Default::default(), ADDR0).unwrap());
+
+        let set_hpd_high = |controller: &Controller<NoopRawMutex, Mock>| {
+            for hpd_high in &controller.hpd_high {
+                hpd_high.store(true, Ordering::SeqCst);
+            }
+        };
+
+        // Unrelated events leave HPD intact
+        set_hpd_high(controller);
+        let mut event = IntEventBus1::new_zero();
+        event.set_cmd_1_completed(true);
+        event.set_am_entered(true);
+        controller.clear_hpd(PORT0, &event);
+        assert!(controller.hpd_high[0].load(Ordering::SeqCst));
+        assert!(controller.hpd_high[1].load(Ordering::SeqCst));
+
+        let clearing: [fn(&mut IntEventBus1); 3] = [
+            |e| e.set_plug_event(true),
+            |e| e.set_hard_reset(true),
+            |e| e.set_exit_mode_completed(true),
+        ];
+        for set in clearing {
+            set_hpd_high(controller);
+            let mut event = IntEventBus1::new_zero();
+            set(&mut event);
+            controller.clear_hpd(PORT1, &event);
+            assert!(controller.hpd_high[0].load(Ordering::SeqCst));
+            assert!(!controller.hpd_high[1].load(Ordering::SeqCst));
+        }
+    }
 }
@@ -1,34 +1,45 @@
 //! This module contains a high-level API uses embassy synchronization types
+use core::cell::RefCell;
 use core::future::Future;
 use core::iter::zip;
 use core::sync::atomic::AtomicBool;
 
 use bincode::config;
+use embassy_sync::blocking_mutex;
 use embassy_sync::blocking_mutex::raw::RawMutex;
 use embassy_sync::mutex::{Mutex, MutexGuard};
 use embassy_sync::signal::Signal;
-use embassy_time::{Timer, with_timeout};
+use embassy_time::{Delay, Duration, Timer, with_timeout};
 use embedded_hal_async::delay::DelayNs;
 use embedded_hal_async::i2c::I2c;
 use embedded_usb_pd::ado::{self, Ado};
 use embedded_usb_pd::pdinfo::AltMode;
-use embedded_usb_pd::{Error, LocalPortId, PdError, pdo};
+use embedded_usb_pd::vdm::structured::Svid;
+use embedded_usb_pd::vdm::structured::command::discover_identity::sop_prime;
+use embedded_usb_pd::vdm::structured::header::CommandType;
+use embedded_usb_pd::{DataRole, Error, LocalPortId, PdError, PowerRole, pdo};
 
 use crate::asynchronous::embassy::interrupt::InterruptReceiver;
 use crate::asynchronous::internal;
 use crate::asynchronous::interrupt::InterruptController;
-use crate::command::{Command, ReturnValue, SrdySwitch, gcdm, muxr, trig, vdms};
+use crate::command::{Command, ResetConfig, ReturnValue, SrdySwitch, SrdySwitchKind, gcdm, muxr, trig, vdms};
 use crate::registers::autonegotiate_sink::AutoComputeSinkMaxVoltage;
 use crate::registers::field_sets::IntEventBus1;
 use crate::{DeviceError, MAX_SUPPORTED_PORTS, Mode, error, registers, trace};
 
+pub mod dump;
 pub mod fw_update;
 pub mod interrupt;
+#[cfg(feature = "serde")]
+pub mod remote;
 pub mod rx_caps;
+pub mod summary;
 pub mod task;
 pub mod ucsi;
 
 pub mod controller {
+    use itertools::izip;
+
     use super::*;
     use crate::asynchronous::embassy::interrupt::InterruptProcessor;
     use crate::{TPS66993_NUM_PORTS, TPS66994_NUM_PORTS};
@@ -38,6 +49,20 @@ pub mod controller {
     #[non_exhaustive]
     pub struct Config {
         pub interrupt_processor_config: crate::asynchronous::embassy::interrupt::Config,
+        /// Time to wait after forcing retimer power on or off before continuing, see
+        /// [`Tps6699x::retimer_force_pwr`]
+        ///
+        /// The controller doesn't report when the retimer supply is good, so this gives the retimer time to power up
+        /// before it's accessed. Defaults to zero, i.e. no wait.
+        pub retimer_power_settle: Duration,
+        /// Abort commands that time out with [`Command::Abrt`], see [`Tps6699x::abort_command`]
+        ///
+        /// Otherwise a stalled command is left pending on the controller. Defaults to false.
+        pub abort_on_timeout: bool,
+        /// Retry policy for transient bus errors, see [`internal::RetryConfig`]
+        ///
+        /// Retries wait on [`embassy_time::Delay`]. Defaults to no retries.
+        pub bus_retry: internal::RetryConfig,
     }
 
     /// Controller struct. This struct is meant to be created and then immediately broken into its parts
@@ -45,33 +70,69 @@ pub mod controller {
         /// Config
         pub(super) config: Config,
         /// Low-level TPS6699x driver
-        pub(super) inner: Mutex<M, internal::Tps6699x<B>>,
+        pub(super) inner: Mutex<M, internal::Tps6699x<B, Delay>>,
         /// Command completion signals
         pub(super) command_complete: [Signal<M, ()>; MAX_SUPPORTED_PORTS],
-        /// Signal for awaiting an interrupt
-        pub(super) interrupt_waker: Signal<M, [IntEventBus1; MAX_SUPPORTED_PORTS]>,
+        /// Interrupt flags that haven't been taken by a receiver yet, see [`Self::take_interrupts`]
+        pub(super) pending_interrupts: blocking_mutex::Mutex<M, RefCell<[IntEventBus1; MAX_SUPPORTED_PORTS]>>,
+        /// Signaled when flags are added to `pending_interrupts`
+        pub(super) interrupt_waker: Signal<M, ()>,
         /// Current interrupt state
         pub(super) interrupts_enabled: [AtomicBool; MAX_SUPPORTED_PORTS],
         /// Number of active ports
         pub(super) num_ports: usize,
+        /// Last HPD state sent to the DP partner, see [`Tps6699x::set_hpd`]
+        pub(super) hpd_high: [AtomicBool; MAX_SUPPORTED_PORTS],
+        /// Cached Rx source caps, see [`Tps6699x::get_rx_src_caps_cached`]
+        pub(super) rx_src_caps_cache: blocking_mutex::Mutex<M, RefCell<[RxSrcCapsCacheEntry; MAX_SUPPORTED_PORTS]>>,
+    }
+
+    /// Per-port entry of [`Controller::rx_src_caps_cache`]
+    pub(super) struct RxSrcCapsCacheEntry {
+        /// Incremented on every invalidation so that a read that raced with one doesn't store stale caps
+        pub(super) generation: u32,
+        /// Cached caps, `None` if they haven't been read since the last invalidation
+        pub(super) caps: Option<rx_caps::RxSrcCaps>,
     }
 
     impl<M: RawMutex, B: I2c> Controller<M, B> {
         /// Private constructor
+        ///
+        /// Returns [`PdError::InvalidParams`] if `num_ports` is zero or greater than [`MAX_SUPPORTED_PORTS`],
+        /// or if any active port has an address of zero
         fn new(
             bus: B,
             config: Config,
             addr: [u8; MAX_SUPPORTED_PORTS],
             num_ports: usize,
         ) -> Result<Self, Error<B::Error>> {
-            Ok(Self {
+            validate_ports(&addr, num_ports).map_err(Error::Pd)?;
+            let inner = internal::Tps6699x::new(bus, addr, num_ports).with_retry(config.bus_retry, Delay);
+            Ok(Self::from_inner(inner, config, num_ports))
+        }
+
+        /// Create a new controller from an already configured low-level driver
+        fn from_inner(inner: internal::Tps6699x<B, Delay>, config: Config, num_ports: usize) -> Self {
+            Self {
                 config,
-                inner: Mutex::new(internal::Tps6699x::new(bus, addr, num_ports)),
+                inner: Mutex::new(inner),
+                pending_interrupts: blocking_mutex::Mutex::new(RefCell::new(
+                    [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS],
+                )),
                 interrupt_waker: Signal::new(),
                 command_complete: [const { Signal::new() }; MAX_SUPPORTED_PORTS],
                 interrupts_enabled: [const { AtomicBool::new(true) }; MAX_SUPPORTED_PORTS],
                 num_ports,
-            })
+                hpd_high: [const { AtomicBool::new(false) }; MAX_SUPPORTED_PORTS],
+                rx_src_caps_cache: blocking_mutex::Mutex::new(RefCell::new(
+                    [const {
+                        RxSrcCapsCacheEntry {
+                            generation: 0,
+                            caps: None,
+                        }
+                    }; MAX_SUPPORTED_PORTS],
+                )),
+            }
         }
 
         /// Create a new controller for the TPS66993
@@ -84,6 +145,22 @@ pub mod controller {
             Self::new(bus, config, addr, TPS66994_NUM_PORTS)
         }
 
+        /// Create a new controller for either a TPS66993 or TPS66994, detecting the number of ports from the device
+        ///
+        /// `addr` holds the addresses the ports would use on a TPS66994, the second address may be zero if only a
+        /// TPS66993 can be populated. See [`internal::Tps6699x::detect_num_ports`] for how the part is detected.
+        pub async fn new_detect(
+            bus: B,
+            config: Config,
+            addr: [u8; MAX_SUPPORTED_PORTS],
+        ) -> Result<Self, Error<B::Error>> {
+            // Only the first port is required
+            validate_ports(&addr, TPS66993_NUM_PORTS).map_err(Error::Pd)?;
+            let mut inner = internal::Tps6699x::new(bus, addr, MAX_SUPPORTED_PORTS).with_retry(config.bus_retry, Delay);
+            let num_ports = inner.detect_num_ports().await?;
+            Ok(Self::from_inner(inner, config, num_ports))
+        }
+
         /// Breaks the controller into its parts
         pub fn make_parts(
             &mut self,
@@ -105,6 +182,90 @@ pub mod controller {
             }
         }
 
+        /// Add `flags` to the pending interrupts and wake any waiting receiver
+        pub(super) fn signal_interrupts(&self, flags: [IntEventBus1; MAX_SUPPORTED_PORTS]) {
+            self.pending_interrupts.lock(|pending| {
+                for (pending, flags) in zip(pending.borrow_mut().iter_mut(), flags.iter()) {
+                    *pending |= *flags;
+                }
+            });
+            self.interrupt_waker.signal(());
+        }
+
+        /// Take the pending interrupt flags that match `mask`, acknowledging them
+        ///
+        /// Flags outside of `mask` stay pending.
+        pub(super) fn take_interrupts(
+            &self,
+            mask: [IntEventBus1; MAX_SUPPORTED_PORTS],
+        ) -> [IntEventBus1; MAX_SUPPORTED_PORTS] {
+            let mut taken = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+            self.pending_interrupts.lock(|pending| {
+                for (pending, mask, taken) in izip!(pending.borrow_mut().iter_mut(), mask.iter(), taken.iter_mut()) {
+                    *taken = *pending & *mask;
+                    *pending = *pending & !*mask;
+                }
+            });
+            taken
+        }
+
+        /// Returns the pending interrupt flags without acknowledging them
+        #[cfg(test)]
+        pub(super) fn pending_interrupts(&self) -> [IntEventBus1; MAX_SUPPORTED_PORTS] {
+            self.pending_interrupts.lock(|pending| *pending.borrow())
+        }
+
+        /// Invalidate the cached Rx source caps for the given port if the event indicates they may have changed
+        pub(super) fn invalidate_rx_src_caps_cache(&self, port: LocalPortId, event: &IntEventBus1) {
+            if event.plug_event()
+                || event.hard_reset()
+                || event.source_caps_received()
+                || event.new_consumer_contract()
+                || event.new_provider_contract()
+                || event.power_swap_completed()
+                || event.fast_role_swap_completed()
+            {
+                self.rx_src_caps_cache.lock(|cache| {
+                    if let Some(entry) = cache.borrow_mut().get_mut(port.0 as usize) {
+                        entry.generation = entry.generation.wrapping_add(1);
+                        entry.caps = None;
+                    }
+                });
+            }
+        }
+
+        /// Clear the HPD state of the given port if the event indicates the DP partner may have gone away
+        pub(super) fn clear_hpd(&self, port: LocalPortId, event: &IntEventBus1) {
+            if (event.plug_event() || event.hard_reset() || event.exit_mode_completed())
+                && let Some(hpd_high) = self.hpd_high.get(port.0 as usize)
+            {
+                hpd_high.store(false, core::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        /// Returns the cache generation and cached Rx source caps of the given port
+        pub(super) fn cached_rx_src_caps(&self, port: LocalPortId) -> Option<(u32, Option<rx_caps::RxSrcCaps>)> {
+            self.rx_src_caps_cache.lock(|cache| {
+                cache
+                    .borrow()
+                    .get(port.0 as usize)
+                    .map(|entry| (entry.generation, entry.caps.clone()))
+            })
+        }
+
+        /// Cache Rx source caps read at `generation`, they're dropped if the cache was invalidated since
+        pub(super) fn store_rx_src_caps(&self, port: LocalPortId, generation: u32, caps: &rx_caps::RxSrcCaps) {
+            self.rx_src_caps_cache.lock(|cache| {
+                if let Some(entry) = cache
+                    .borrow_mut()
+                    .get_mut(port.0 as usize)
+                    .filter(|entry| entry.generation == generation)
+                {
+                    entry.caps = Some(caps.clone());
+                }
+            });
+        }
+
         /// Returns current interrupt state
         pub(super) fn interrupts_enabled(&self) -> [bool; MAX_SUPPORTED_PORTS] {
             let mut interrupts_enabled = [false; MAX_SUPPORTED_PORTS];
@@ -117,6 +278,220 @@ pub mod controller {
     }
 }
 
+/// Validate the port count and the addresses of the active ports
+fn validate_ports(addr: &[u8; MAX_SUPPORTED_PORTS], num_ports: usize) -> Result<(), PdError> {
+    if num_ports == 0 || num_ports > MAX_SUPPORTED_PORTS {
+        error!("Invalid number of ports: {}", num_ports);
+        return Err(PdError::InvalidParams);
+    }
+
+    if addr.iter().take(num_ports).any(|&addr| addr == 0) {
+        error!("Invalid address for an active port");
+        return Err(PdError::InvalidParams);
+    }
+
+    Ok(())
+}
+
+/// Decode a raw active PDO, returning the fixed-supply data if there is an explicit fixed-supply contract
+fn fixed_contract(pdo_raw: u32) -> Option<pdo::source::FixedData> {
+    if pdo_raw == 0 {
+        // No explicit contract
+        return None;
+    }
+
+    match pdo::source::Pdo::try_from(pdo_raw) {
+        Ok(pdo::source::Pdo::Fixed(data)) => Some(data),
+        _ => None,
+    }
+}
+
+/// Programmable power supply contract status, see [`Tps6699x::get_pps_status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PpsStatus {
+    /// Requested output voltage in mV
+    pub output_voltage_mv: u32,
+    /// Requested operating current in mA
+    pub output_current_ma: u32,
+}
+
+/// Decode a raw active PDO and RDO, returning the PPS status if there is an SPR PPS contract
+fn pps_contract(pdo_raw: u32, rdo_raw: u32) -> Option<PpsStatus> {
+    // Augmented PDO (bits 31:30 = 0b11) with the SPR PPS subtype (bits 29:28 = 0b00)
+    if pdo_raw >> 28 != 0b1100 {
+        return None;
+    }
+
+    Some(PpsStatus {
+        // Bits 20:9, 20 mV units
+        output_voltage_mv: ((rdo_raw >> 9) & 0xfff) * 20,
+        // Bits 6:0, 50 mA units
+        output_current_ma: (rdo_raw & 0x7f) * 50,
+    })
+}
+
+/// Negotiated power contract of a port, see [`Tps6699x::get_contract_power`]
+///
+/// These are the values agreed in the active PDO/RDO, not measured values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ContractPower {
+    /// VBUS state reported by the controller
+    pub vbus: registers::VbusMode,
+    /// Contract VBUS voltage in mV, `None` without an explicit fixed or PPS contract
+    pub voltage_mv: Option<u32>,
+    /// Contract port current in mA, `None` without an explicit fixed or PPS contract
+    pub current_ma: Option<u32>,
+    /// VCONN switch state
+    pub vconn: registers::PpVconnSw,
+}
+
+/// Thermal state of a port, see [`Tps6699x::get_thermal_status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ThermalStatus {
+    /// The port went through error recovery due to an over-temperature shutdown
+    pub over_temperature: bool,
+}
+
+/// VBUS power path, see [`Tps6699x::wait_power_path_stable`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerPath {
+    /// Internal VBUS switch, used when sourcing
+    Source,
+    /// External VBUS switch, used when sinking
+    Sink,
+}
+
+/// Forced CC line termination, see [`Tps6699x::set_cc_termination`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CcConfig {
+    /// Rp advertising default USB current
+    RpDefault,
+    /// Rp advertising 1.5 A
+    Rp1A5,
+    /// Rp advertising 3.0 A
+    Rp3A0,
+    /// Rd, sink only
+    Rd,
+    /// No termination, the Type-C state machine is disabled
+    Open,
+}
+
+impl CcConfig {
+    /// Type-C state machine and Rp current advertisement that produce this termination
+    ///
+    /// The Rp current is `None` when the termination doesn't present Rp.
+    fn encode(
+        self,
+    ) -> (
+        registers::port_config::TypeCStateMachine,
+        Option<registers::TypecCurrent>,
+    ) {
+        use registers::TypecCurrent;
+        use registers::port_config::TypeCStateMachine;
+
+        match self {
+            CcConfig::RpDefault => (TypeCStateMachine::Source, Some(TypecCurrent::UsbDefault)),
+            CcConfig::Rp1A5 => (TypeCStateMachine::Source, Some(TypecCurrent::Current1A5)),
+            CcConfig::Rp3A0 => (TypeCStateMachine::Source, Some(TypecCurrent::Current3A0)),
+            CcConfig::Rd => (TypeCStateMachine::Sink, None),
+            CcConfig::Open => (TypeCStateMachine::Disabled, None),
+        }
+    }
+}
+
+/// Time allowed for the sink path to turn on when recovering from dead battery mode
+const DEAD_BATTERY_SINK_PATH_TIMEOUT_MS: u64 = 250;
+
+/// Time allowed for the hard reset event after [`Tps6699x::send_hard_reset`] and the plug event after
+/// [`Tps6699x::trigger_error_recovery`]
+const PORT_RESET_EVENT_TIMEOUT_MS: u64 = 1000;
+
+/// Time the port stays disconnected during [`Tps6699x::trigger_error_recovery`]
+const ERROR_RECOVERY_DISCONNECT_TIME_S: u8 = 1;
+
+/// Read the status registers that make up a [`summary::ModeSummary`]
+async fn read_mode_summary<B: I2c, D: DelayNs>(
+    inner: &mut internal::Tps6699x<B, D>,
+    port: LocalPortId,
+) -> Result<summary::ModeSummary, Error<B::Error>> {
+    let dp_status = inner.get_dp_status(port).await?;
+    let intel_vid_status = inner.get_intel_vid_status(port).await?;
+    let usb_status = inner.get_usb_status(port).await?;
+    Ok(summary::ModeSummary::from_status(
+        &dp_status,
+        &intel_vid_status,
+        &usb_status,
+    ))
+}
+
+/// Interval between power path status polls
+const POWER_PATH_POLL_INTERVAL_MS: u64 = 1;
+
+/// Check if the given power path has finished turning on
+///
+/// Returns `Ok(false)` while the switch is still off or current-limiting through soft-start and
+/// [`PdError::Failed`] if the switch has been disabled due to a fault.
+fn power_path_stable(
+    status: &registers::field_sets::PowerPathStatus,
+    port: LocalPortId,
+    path: PowerPath,
+) -> Result<bool, PdError> {
+    use registers::{PpExtVbusSw, PpIntVbusSw};
+
+    match (port.0, path) {
+        (0, PowerPath::Source) => match status.pa_int_vbus_sw() {
+            PpIntVbusSw::EnabledOutput => Ok(!status.pa_int_vbus_oc()),
+            PpIntVbusSw::DisabledFault => Err(PdError::Failed),
+            _ => Ok(false),
+        },
+        (1, PowerPath::Source) => match status.pb_int_vbus_sw() {
+            PpIntVbusSw::EnabledOutput => Ok(!status.pb_int_vbus_oc()),
+            PpIntVbusSw::DisabledFault => Err(PdError::Failed),
+            _ => Ok(false),
+        },
+        (0, PowerPath::Sink) => match status.pa_ext_vbus_sw() {
+            PpExtVbusSw::EnabledInput => Ok(true),
+            PpExtVbusSw::DisabledFault => Err(PdError::Failed),
+            _ => Ok(false),
+        },
+        (1, PowerPath::Sink) => match status.pb_ext_vbus_sw() {
+            PpExtVbusSw::EnabledInput => Ok(true),
+            PpExtVbusSw::DisabledFault => Err(PdError::Failed),
+            _ => Ok(false),
+        },
+        _ => Err(PdError::InvalidPort),
+    }
+}
+
+/// Check if the source path of `port` is off, either disabled normally or due to a fault
+fn source_path_off(status: &registers::field_sets::PowerPathStatus, port: LocalPortId) -> Result<bool, PdError> {
+    use registers::PpIntVbusSw;
+
+    let switch = match port.0 {
+        0 => status.pa_int_vbus_sw(),
+        1 => status.pb_int_vbus_sw(),
+        _ => return Err(PdError::InvalidPort),
+    };
+    Ok(matches!(switch, PpIntVbusSw::Disabled | PpIntVbusSw::DisabledFault))
+}
+
+/// Status of the VCONN switch for `port`, each port has its own switch in the power path status register
+fn vconn_switch(
+    status: &registers::field_sets::PowerPathStatus,
+    port: LocalPortId,
+) -> Result<registers::PpVconnSw, PdError> {
+    match port.0 {
+        0 => Ok(status.pa_vconn_sw()),
+        1 => Ok(status.pb_vconn_sw()),
+        _ => Err(PdError::InvalidPort),
+    }
+}
+
 /// Struct for controlling a TP6699x device
 pub struct Tps6699x<'a, M: RawMutex, B: I2c> {
     controller: &'a controller::Controller<M, B>,
@@ -124,7 +499,7 @@ pub struct Tps6699x<'a, M: RawMutex, B: I2c> {
 
 impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
     /// Locks the inner device
-    pub fn lock_inner(&mut self) -> impl Future<Output = MutexGuard<'_, M, internal::Tps6699x<B>>> {
+    pub fn lock_inner(&mut self) -> impl Future<Output = MutexGuard<'_, M, internal::Tps6699x<B, Delay>>> {
         self.controller.inner.lock()
     }
 
@@ -134,6 +509,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         f: impl FnOnce(&mut registers::field_sets::IntEventBus1) -> registers::field_sets::IntEventBus1,
     ) -> Result<registers::field_sets::IntEventBus1, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.modify_interrupt_mask(port, f).await
     }
 
@@ -150,6 +526,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::Status, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_port_status(port).await
     }
 
@@ -158,6 +535,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::ActivePdoContract, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_active_pdo_contract(port).await
     }
 
@@ -166,6 +544,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::ActiveRdoContract, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_active_rdo_contract(port).await
     }
 
@@ -174,6 +553,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::autonegotiate_sink::AutonegotiateSink, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_autonegotiate_sink(port).await
     }
 
@@ -183,6 +563,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         value: registers::autonegotiate_sink::AutonegotiateSink,
     ) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.set_autonegotiate_sink(port, value).await
     }
 
@@ -194,29 +575,106 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
             &mut registers::autonegotiate_sink::AutonegotiateSink,
         ) -> registers::autonegotiate_sink::AutonegotiateSink,
     ) -> Result<registers::autonegotiate_sink::AutonegotiateSink, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.modify_autonegotiate_sink(port, f).await
     }
 
+    /// Change the I2C address used to access `port`
+    ///
+    /// The controller's I2C1 target address is selected by its ADDR strap at boot and is reported in the boot flags,
+    /// there is no command or register to change it at runtime. This only changes the address the driver uses,
+    /// e.g. to follow a controller that came up on the other address set because a strap was misread. Subsequent
+    /// accesses to `port` use `new_addr`, so this must not be called while a command on `port` is in progress or its
+    /// completion would be read back from the wrong address.
+    pub async fn set_i2c_address(&mut self, port: LocalPortId, new_addr: u8) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
+        self.lock_inner().await.set_port_addr(port, new_addr)
+    }
+
     /// Wrapper for `get_mode`
     pub async fn get_mode(&mut self) -> Result<Mode, Error<B::Error>> {
         self.lock_inner().await.get_mode().await
     }
 
+    /// Wrapper for `get_mode_raw`
+    pub async fn get_mode_raw(&mut self) -> Result<u32, Error<B::Error>> {
+        self.lock_inner().await.get_mode_raw().await
+    }
+
     /// Wrapper for `get_fw_version`
     pub async fn get_fw_version(&mut self) -> Result<u32, Error<B::Error>> {
         self.lock_inner().await.get_fw_version().await
     }
 
+    /// Read a snapshot of `port`'s status registers for diagnostics
+    ///
+    /// All registers are read while holding the driver lock so no other access is interleaved. This is read-only,
+    /// asserted interrupts are reported in [`dump::PortRegisterDump::int_event_bus_1`] but are not cleared.
+    pub async fn dump_port_registers(&mut self, port: LocalPortId) -> Result<dump::PortRegisterDump, Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        Ok(dump::PortRegisterDump {
+            status: inner.get_port_status(port).await?,
+            pd_status: inner.get_pd_status(port).await?,
+            port_control: inner.get_port_control(port).await?,
+            power_path_status: inner.get_power_path_status(port).await?,
+            active_pdo_contract: inner.get_active_pdo_contract(port).await?,
+            active_rdo_contract: inner.get_active_rdo_contract(port).await?,
+            int_event_bus_1: inner.get_interrupt(port).await?,
+        })
+    }
+
+    /// Read the status, PD status and active PDO of `port` together, e.g. when handling a plug event
+    ///
+    /// Each register is selected by its own address and returns its own length-prefixed data, so the controller
+    /// can't return several registers in a single transfer. The registers are read individually, but while holding
+    /// the driver lock so the snapshot isn't interleaved with other accesses.
+    pub async fn get_connection_snapshot(
+        &mut self,
+        port: LocalPortId,
+    ) -> Result<dump::ConnectionSnapshot, Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        Ok(dump::ConnectionSnapshot {
+            status: inner.get_port_status(port).await?,
+            pd_status: inner.get_pd_status(port).await?,
+            active_pdo_contract: inner.get_active_pdo_contract(port).await?,
+        })
+    }
+
+    /// Read a snapshot of the controller-wide registers for diagnostics
+    ///
+    /// All registers are read while holding the driver lock so no other access is interleaved.
+    pub async fn dump_controller_registers(&mut self) -> Result<dump::ControllerRegisterDump, Error<B::Error>> {
+        let mut inner = self.lock_inner().await;
+        Ok(dump::ControllerRegisterDump {
+            mode: inner.get_mode().await?,
+            fw_version: inner.get_fw_version().await?,
+            boot_flags: inner.get_boot_flags().await?,
+        })
+    }
+
     /// Wrapper for `get_customer_use`
     pub async fn get_customer_use(&mut self) -> Result<u64, Error<B::Error>> {
         self.lock_inner().await.get_customer_use().await
     }
 
+    /// Wrapper for `set_customer_use`
+    pub async fn set_customer_use(&mut self, value: u64) -> Result<(), Error<B::Error>> {
+        self.lock_inner().await.set_customer_use(value).await
+    }
+
+    /// Wrapper for `get_device_info`
+    pub async fn get_device_info(&mut self) -> Result<registers::device_info::DeviceInfo, Error<B::Error>> {
+        self.lock_inner().await.get_device_info().await
+    }
+
     /// Wrapper for `get_power_path_status`
     pub async fn get_power_path_status(
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::PowerPathStatus, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_power_path_status(port).await
     }
 
@@ -225,6 +683,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::PdStatus, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_pd_status(port).await
     }
 
@@ -233,6 +692,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::PortControl, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_port_control(port).await
     }
 
@@ -242,9 +702,20 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         control: registers::field_sets::PortControl,
     ) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.set_port_control(port, control).await
     }
 
+    /// Wrapper for `modify_port_control`
+    pub async fn modify_port_control(
+        &mut self,
+        port: LocalPortId,
+        f: impl FnOnce(&mut registers::field_sets::PortControl) -> registers::field_sets::PortControl,
+    ) -> Result<registers::field_sets::PortControl, Error<B::Error>> {
+        self.validate_port(port)?;
+        self.lock_inner().await.modify_port_control(port, f).await
+    }
+
     /// Wrapper for `get_system_config`
     pub async fn get_system_config(&mut self) -> Result<registers::field_sets::SystemConfig, Error<B::Error>> {
         self.lock_inner().await.get_system_config().await
@@ -258,30 +729,119 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.lock_inner().await.set_system_config(config).await
     }
 
+    /// Wrapper for `modify_system_config`
+    pub async fn modify_system_config(
+        &mut self,
+        f: impl FnOnce(&mut registers::field_sets::SystemConfig) -> registers::field_sets::SystemConfig,
+    ) -> Result<registers::field_sets::SystemConfig, Error<B::Error>> {
+        self.lock_inner().await.modify_system_config(f).await
+    }
+
     /// Wrapper for `enable_source`
     pub async fn enable_source(&mut self, port: LocalPortId, enable: bool) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.enable_source(port, enable).await
     }
 
+    /// Wait for a power path to finish turning on
+    ///
+    /// Polls the power path status until the switch reports fully on and is no longer current-limiting. Returns
+    /// [`PdError::Timeout`] if the switch doesn't settle within `timeout`, which usually indicates a short or
+    /// overload, and [`PdError::Failed`] if the controller disabled the switch due to a fault.
+    pub async fn wait_power_path_stable(
+        &mut self,
+        port: LocalPortId,
+        path: PowerPath,
+        timeout: Duration,
+    ) -> Result<(), Error<B::Error>> {
+        let result = with_timeout(timeout, async {
+            loop {
+                let status = self.get_power_path_status(port).await?;
+                if power_path_stable(&status, port, path).map_err(Error::Pd)? {
+                    return Ok::<(), Error<B::Error>>(());
+                }
+
+                Timer::after_millis(POWER_PATH_POLL_INTERVAL_MS).await;
+            }
+        })
+        .await;
+
+        result.unwrap_or_else(|_| {
+            error!("{:?}: {:?} power path did not stabilize", port, path);
+            PdError::Timeout.into()
+        })
+    }
+
+    /// Enable or disable the source path, then confirm the switch actually followed
+    ///
+    /// [`Self::enable_source`] only updates the system configuration, the switch can still fail to turn on, e.g. due
+    /// to overcurrent. This polls the power path status until the source switch for `port` is stable in the requested
+    /// state and returns [`PdError::Failed`] if that doesn't happen within `timeout` or the controller disabled the
+    /// switch due to a fault.
+    pub async fn enable_source_checked(
+        &mut self,
+        port: LocalPortId,
+        enable: bool,
+        timeout: Duration,
+    ) -> Result<(), Error<B::Error>> {
+        self.enable_source(port, enable).await?;
+
+        if enable {
+            return match self.wait_power_path_stable(port, PowerPath::Source, timeout).await {
+                Err(Error::Pd(PdError::Timeout)) => PdError::Failed.into(),
+                result => result,
+            };
+        }
+
+        let result = with_timeout(timeout, async {
+            loop {
+                let status = self.get_power_path_status(port).await?;
+                if source_path_off(&status, port).map_err(Error::Pd)? {
+                    return Ok::<(), Error<B::Error>>(());
+                }
+
+                Timer::after_millis(POWER_PATH_POLL_INTERVAL_MS).await;
+            }
+        })
+        .await;
+
+        result.unwrap_or_else(|_| {
+            error!("{:?}: source path did not turn off", port);
+            PdError::Failed.into()
+        })
+    }
+
     /// Returns the number of ports
     pub fn num_ports(&self) -> usize {
         self.controller.num_ports
     }
 
-    /// Execute the given command with no timeout
-    async fn execute_command_no_timeout(
+    /// Check that the given port exists on this controller
+    pub fn validate_port(&self, port: LocalPortId) -> Result<(), Error<B::Error>> {
+        if port.0 as usize >= self.controller.num_ports {
+            error!(
+                "{:?}: Invalid port, controller has {} ports",
+                port, self.controller.num_ports
+            );
+            return Err(Error::Pd(PdError::InvalidPort));
+        }
+
+        Ok(())
+    }
+
+    /// Execute the given raw command with no timeout
+    async fn execute_raw_command_no_timeout(
         &mut self,
         port: LocalPortId,
-        cmd: Command,
+        cmd: u32,
+        has_return_value: bool,
         indata: Option<&[u8]>,
         outdata: Option<&mut [u8]>,
     ) -> Result<ReturnValue, Error<B::Error>> {
         // Size of the command_complete array is MAX_SUPPORTED_PORTS so the `get`` call below doesn't guarentee
         // that the port is valid because it wouldn't catch trying to access a second port on a controller with
         // only one port.
-        if port.0 as usize >= self.controller.num_ports {
-            return Err(Error::Pd(PdError::InvalidPort));
-        }
+        self.validate_port(port)?;
 
         let command_complete = self
             .controller
@@ -291,33 +851,50 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         command_complete.reset();
         {
             let mut inner = self.lock_inner().await;
-            inner.send_command(port, cmd, indata).await?;
+            inner.send_raw_command(port, cmd, indata).await?;
         }
 
         command_complete.wait().await;
         {
             let mut inner = self.lock_inner().await;
-            inner.read_command_result(port, outdata, cmd.has_return_value()).await
+            inner.read_command_result(port, outdata, has_return_value).await
         }
     }
 
-    /// Execute the given command with a timeout determined by [`Command::timeout`].
-    async fn execute_command(
+    /// Execute the given raw command, returning [`PdError::Rejected`] or [`PdError::Timeout`] if it doesn't complete
+    /// within `timeout`
+    async fn execute_raw_command_with_timeout(
         &mut self,
         port: LocalPortId,
-        cmd: Command,
+        cmd: u32,
+        has_return_value: bool,
         indata: Option<&[u8]>,
         outdata: Option<&mut [u8]>,
+        timeout: Duration,
     ) -> Result<ReturnValue, Error<B::Error>> {
-        let timeout = cmd.timeout();
-        let result = with_timeout(timeout, self.execute_command_no_timeout(port, cmd, indata, outdata)).await;
+        let result = with_timeout(
+            timeout,
+            self.execute_raw_command_no_timeout(port, cmd, has_return_value, indata, outdata),
+        )
+        .await;
         if let Ok(result) = result {
             result
         } else {
-            error!("Command {:#?} timed out", cmd);
+            match Command::try_from(cmd) {
+                Ok(cmd) => error!("Command {:#?} timed out", cmd),
+                Err(_) => error!("Command {:#x} timed out", cmd),
+            }
+
+            if self.controller.config.abort_on_timeout {
+                let complete = self.lock_inner().await.check_command_complete(port).await?;
+                if !complete {
+                    self.abort_command(port).await?;
+                }
+            }
+
             // See if there's a definite error we can read
             let mut inner = self.lock_inner().await;
-            match inner.read_command_result(port, None, cmd.has_return_value()).await? {
+            match inner.read_command_result(port, None, has_return_value).await? {
                 ReturnValue::Rejected => PdError::Rejected,
                 _ => PdError::Timeout,
             }
@@ -325,27 +902,105 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         }
     }
 
+    /// Abort the command currently executing on `port` with [`Command::Abrt`]
+    ///
+    /// The aborted command completes with [`ReturnValue::Abort`]. This cancels a stalled command, e.g. an `ANeg` that
+    /// never finishes, without having to reset the controller. Returns [`PdError::Timeout`] if the abort itself doesn't
+    /// complete.
+    pub async fn abort_command(&mut self, port: LocalPortId) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
+
+        let command_complete = self
+            .controller
+            .command_complete
+            .get(port.0 as usize)
+            .ok_or(Error::Pd(PdError::InvalidPort))?;
+        command_complete.reset();
+        self.lock_inner().await.send_command(port, Command::Abrt, None).await?;
+
+        with_timeout(Command::Abrt.timeout(), command_complete.wait())
+            .await
+            .map_err(|_| {
+                error!("{:?}: abort timed out", port);
+                Error::Pd(PdError::Timeout)
+            })
+    }
+
+    /// Execute the given command with a timeout determined by [`Command::timeout`].
+    async fn execute_command(
+        &mut self,
+        port: LocalPortId,
+        cmd: Command,
+        indata: Option<&[u8]>,
+        outdata: Option<&mut [u8]>,
+    ) -> Result<ReturnValue, Error<B::Error>> {
+        self.execute_command_with_timeout(port, cmd, indata, outdata, cmd.timeout())
+            .await
+    }
+
+    /// Execute the given command, overriding the default [`Command::timeout`]
+    ///
+    /// Useful when a command legitimately takes longer than usual, e.g. `ANeg` during a slow renegotiation. If the
+    /// command doesn't complete within `timeout` this returns [`PdError::Rejected`] if the controller has rejected it,
+    /// [`PdError::Timeout`] otherwise.
+    pub async fn execute_command_with_timeout(
+        &mut self,
+        port: LocalPortId,
+        cmd: Command,
+        indata: Option<&[u8]>,
+        outdata: Option<&mut [u8]>,
+        timeout: Duration,
+    ) -> Result<ReturnValue, Error<B::Error>> {
+        self.execute_raw_command_with_timeout(port, cmd as u32, cmd.has_return_value(), indata, outdata, timeout)
+            .await
+    }
+
+    /// Execute a 4CC command that isn't covered by [`Command`], e.g. one added in newer firmware
+    ///
+    /// `fourcc` is the command name as sent, e.g. `*b"ANeg"`. The command must return a standard [`ReturnValue`] in
+    /// the first output byte, any remaining output is copied into `outdata`. If the command doesn't complete within
+    /// `timeout` this returns [`PdError::Rejected`] if the controller has rejected it, [`PdError::Timeout`] otherwise.
+    pub async fn execute_raw_command(
+        &mut self,
+        port: LocalPortId,
+        fourcc: [u8; 4],
+        indata: Option<&[u8]>,
+        outdata: Option<&mut [u8]>,
+        timeout: Duration,
+    ) -> Result<ReturnValue, Error<B::Error>> {
+        self.execute_raw_command_with_timeout(port, u32::from_le_bytes(fourcc), true, indata, outdata, timeout)
+            .await
+    }
+
+    /// Execute the given command, mapping generic failures to errors, see [`ReturnValue::checked`]
+    async fn execute_command_checked(
+        &mut self,
+        port: LocalPortId,
+        cmd: Command,
+        indata: Option<&[u8]>,
+        outdata: Option<&mut [u8]>,
+    ) -> Result<ReturnValue, Error<B::Error>> {
+        self.execute_command(port, cmd, indata, outdata)
+            .await?
+            .checked()
+            .map_err(Error::Pd)
+    }
+
     async fn execute_srdy(&mut self, port: LocalPortId, switch: SrdySwitch) -> Result<ReturnValue, Error<B::Error>> {
         let arg_bytes = [switch.into()];
-        self.execute_command(port, Command::Srdy, Some(&arg_bytes), None).await
+        self.execute_command_checked(port, Command::Srdy, Some(&arg_bytes), None)
+            .await
     }
 
     async fn execute_sryr(&mut self, port: LocalPortId) -> Result<ReturnValue, Error<B::Error>> {
-        self.execute_command(port, Command::Sryr, None, None).await
+        self.execute_command_checked(port, Command::Sryr, None, None).await
     }
 
     /// Enable or disable the given power path
     pub async fn enable_sink_path(&mut self, port: LocalPortId, enable: bool) -> Result<(), Error<B::Error>> {
         if enable {
-            self.execute_srdy(
-                port,
-                match port.0 {
-                    0 => Ok(SrdySwitch::PpExt1),
-                    1 => Ok(SrdySwitch::PpExt2),
-                    _ => PdError::InvalidPort.into(),
-                }?,
-            )
-            .await?;
+            let switch = SrdySwitch::for_port(port, SrdySwitchKind::PpExt).map_err(Error::Pd)?;
+            self.execute_srdy(port, switch).await?;
         } else {
             self.execute_sryr(port).await?;
         }
@@ -355,11 +1010,127 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
 
     /// Trigger an `ANeg` command to autonegotiate the sink contract.
     pub async fn autonegotiate_sink(&mut self, port: LocalPortId) -> Result<(), Error<B::Error>> {
-        match self.execute_command(port, Command::Aneg, None, None).await? {
-            ReturnValue::Success => Ok(()),
-            ReturnValue::Rejected => PdError::Rejected.into(),
-            _ => PdError::Failed.into(),
+        self.execute_command_checked(port, Command::Aneg, None, None)
+            .await?
+            .success_or(PdError::Failed)
+            .map_err(Error::Pd)
+    }
+
+    /// Initiate a data role swap to `role` with [`Command::SwapToDfp`] or [`Command::SwapToUfp`]
+    ///
+    /// Returns [`PdError::Rejected`] if the port partner rejects the swap.
+    pub async fn swap_data_role(&mut self, port: LocalPortId, role: DataRole) -> Result<(), Error<B::Error>> {
+        let cmd = match role {
+            DataRole::Dfp => Command::SwapToDfp,
+            DataRole::Ufp => Command::SwapToUfp,
+        };
+
+        self.execute_command_checked(port, cmd, None, None)
+            .await?
+            .success_or(PdError::Failed)
+            .map_err(Error::Pd)
+    }
+
+    /// Initiate a power role swap to `role` with [`Command::SwapToSource`] or [`Command::SwapToSink`]
+    ///
+    /// Returns the power role reported by the port status register once the swap has completed. Returns
+    /// [`PdError::Rejected`] if the port partner rejects the swap and [`PdError::Timeout`] if the swap was aborted.
+    pub async fn swap_power_role(&mut self, port: LocalPortId, role: PowerRole) -> Result<PowerRole, Error<B::Error>> {
+        let cmd = match role {
+            PowerRole::Source => Command::SwapToSource,
+            PowerRole::Sink => Command::SwapToSink,
+        };
+
+        self.execute_command_checked(port, cmd, None, None)
+            .await?
+            .success_or(PdError::Failed)
+            .map_err(Error::Pd)?;
+
+        let status = self.get_port_status(port).await?;
+        Ok(if status.port_role() {
+            PowerRole::Source
+        } else {
+            PowerRole::Sink
+        })
+    }
+
+    /// Send a VDM to `sop_target`, `vdos` holds the VDM header followed by its VDOs
+    ///
+    /// Returns [`PdError::InvalidParams`] if `vdos` is empty or holds more than [`vdms::MAX_NUM_DATA_OBJECTS`]
+    /// objects. The command completes once the VDM has been sent. Responses aren't returned through the command
+    /// output, they are reported through the VDM received interrupt and the matching receive register, e.g.
+    /// [`Self::get_received_sop_prime_identity_data`] for a Discover Identity sent to SOP'.
+    pub async fn send_vdm(
+        &mut self,
+        port: LocalPortId,
+        sop_target: vdms::SopTarget,
+        vdos: &[u32],
+    ) -> Result<(), Error<B::Error>> {
+        let input = vdms::Input::from_vdos(sop_target, vdos).map_err(Error::Pd)?;
+        self.execute_command_checked(port, Command::VDMs, Some(input.as_bytes()), None)
+            .await?
+            .success_or(PdError::Failed)
+            .map_err(Error::Pd)
+    }
+
+    /// Set the HPD state reported to the DisplayPort partner
+    ///
+    /// HPD is sent to the DFP_D as a DisplayPort Attention VDM addressed to the object position of the discovered DP
+    /// mode. Returns [`PdError::InvalidMode`] if no DP mode was discovered and [`PdError::Rejected`] if the controller
+    /// rejects the VDM. The HPD state is forgotten on detach, hard reset and alt mode exit.
+    pub async fn set_hpd(&mut self, port: LocalPortId, state: vdms::HpdState) -> Result<(), Error<B::Error>> {
+        let hpd_high = self.hpd_flag(port)?;
+        self.send_hpd_attention(port, state, false).await?;
+        hpd_high.store(state == vdms::HpdState::High, core::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Send an HPD IRQ to the DisplayPort partner
+    ///
+    /// The IRQ is sent as an Attention VDM with IRQ_HPD set, the DFP_D then generates the IRQ_HPD pulse with the
+    /// timing required by the DisplayPort specification. An IRQ is only valid while HPD is high, so this returns
+    /// [`PdError::InvalidMode`] without sending anything if HPD was last set low.
+    pub async fn pulse_hpd_irq(&mut self, port: LocalPortId) -> Result<(), Error<B::Error>> {
+        if !self.hpd_flag(port)?.load(core::sync::atomic::Ordering::SeqCst) {
+            return PdError::InvalidMode.into();
         }
+
+        self.send_hpd_attention(port, vdms::HpdState::High, true).await
+    }
+
+    /// Returns the HPD state flag for the given port
+    fn hpd_flag(&self, port: LocalPortId) -> Result<&'a AtomicBool, Error<B::Error>> {
+        self.validate_port(port)?;
+        self.controller
+            .hpd_high
+            .get(port.0 as usize)
+            .ok_or(Error::Pd(PdError::InvalidPort))
+    }
+
+    /// Send a DisplayPort Attention VDM with the given HPD state
+    async fn send_hpd_attention(
+        &mut self,
+        port: LocalPortId,
+        state: vdms::HpdState,
+        irq: bool,
+    ) -> Result<(), Error<B::Error>> {
+        let object_position = self.dp_object_position(port).await?;
+        let input = vdms::Input::dp_hpd_attention(object_position, state, irq);
+        self.execute_command_checked(port, Command::VDMs, Some(input.as_bytes()), None)
+            .await?
+            .success_or(PdError::Failed)
+            .map_err(Error::Pd)
+    }
+
+    /// Returns the object position of the DP mode discovered on the port partner
+    async fn dp_object_position(&mut self, port: LocalPortId) -> Result<u8, Error<B::Error>> {
+        self.execute_gcdm(port, Svid(vdms::DP_SVID).into())
+            .await?
+            .alt_modes
+            .iter()
+            .find(|mode| mode.position != 0)
+            .map(|mode| mode.position)
+            .ok_or(Error::Pd(PdError::InvalidMode))
     }
 
     /// Trigger virtual gpios
@@ -378,8 +1149,22 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.execute_command(port, Command::Trig, Some(&args_buf), None).await
     }
 
-    /// Force retimer power on or off
+    /// Force retimer power on or off, then wait for the configured [`controller::Config::retimer_power_settle`]
     pub async fn retimer_force_pwr(&mut self, port: LocalPortId, enable: bool) -> Result<(), Error<B::Error>> {
+        let settle = self.controller.config.retimer_power_settle;
+        self.retimer_force_pwr_with_settle(port, enable, settle).await
+    }
+
+    /// Force retimer power on or off, then wait for `settle` before returning
+    ///
+    /// The controller doesn't expose a retimer power-good status so there's nothing to poll, `settle` should cover
+    /// the retimer's power-up time on the board in question. Zero skips the wait.
+    pub async fn retimer_force_pwr_with_settle(
+        &mut self,
+        port: LocalPortId,
+        enable: bool,
+        settle: Duration,
+    ) -> Result<(), Error<B::Error>> {
         trace!("retimer_force_pwr: {}", enable);
 
         let edge = if enable {
@@ -391,11 +1176,16 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.virtual_gpio_trigger(port, edge, trig::Cmd::RetimerForcePwr)
             .await?;
 
+        if settle > Duration::from_ticks(0) {
+            Timer::after(settle).await;
+        }
+
         Ok(())
     }
 
     /// Get retimer fw update state
     pub async fn get_rt_fw_update_status(&mut self, port: LocalPortId) -> Result<bool, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         let rt_fw_update_mode = inner.get_intel_vid_status(port).await?.forced_tbt_mode();
         trace!("rt_fw_update_mode: {}", rt_fw_update_mode);
@@ -404,24 +1194,51 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
 
     /// set retimer fw update state
     pub async fn set_rt_fw_update_state(&mut self, port: LocalPortId) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         // Force RT Pwr On
+        //
+        // The inner lock can't be held across this, command completion is signaled by the interrupt processor which
+        // needs the lock. That's fine, interrupt processing only reads and clears event flags and never touches the
+        // forced retimer power state, so it's still on when the port control update below runs.
         self.retimer_force_pwr(port, true).await?;
 
-        let mut inner = self.lock_inner().await;
-        let mut port_control = inner.get_port_control(port).await?;
-        port_control.set_retimer_fw_update(true);
-        inner.set_port_control(port, port_control).await?;
+        self.modify_port_control(port, |port_control| {
+            port_control.set_retimer_fw_update(true);
+            *port_control
+        })
+        .await?;
         Ok(())
     }
 
+    /// Enable or disable fast role swap on the given port
+    ///
+    /// Only the fast role swap bit in the port control register is modified.
+    pub async fn set_fast_role_swap_enabled(
+        &mut self,
+        port: LocalPortId,
+        enabled: bool,
+    ) -> Result<(), Error<B::Error>> {
+        self.modify_port_control(port, |port_control| {
+            port_control.set_fr_swap_enabled(enabled);
+            *port_control
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Get the state of the VCONN switch on the given port from the power path status register
+    pub async fn get_vconn_status(&mut self, port: LocalPortId) -> Result<registers::PpVconnSw, Error<B::Error>> {
+        let power_path = self.get_power_path_status(port).await?;
+        vconn_switch(&power_path, port).map_err(Error::Pd)
+    }
+
     /// clear retimer fw update state
     pub async fn clear_rt_fw_update_state(&mut self, port: LocalPortId) -> Result<(), Error<B::Error>> {
-        {
-            let mut inner = self.lock_inner().await;
-            let mut port_control = inner.get_port_control(port).await?;
+        self.modify_port_control(port, |port_control| {
             port_control.set_retimer_fw_update(false);
-            inner.set_port_control(port, port_control).await?;
-        }
+            *port_control
+        })
+        .await?;
 
         // Force RT Pwr Off
         self.retimer_force_pwr(port, false).await?;
@@ -431,6 +1248,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
 
     /// set retimer compliance
     pub async fn set_rt_compliance(&mut self, port: LocalPortId) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         {
             // Force RT Pwr On
             self.retimer_force_pwr(port, true).await?;
@@ -472,6 +1290,49 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         inner.reset(delay, &Default::default()).await
     }
 
+    /// Reset the device, waiting for it to restart as described by `config`
+    pub async fn reset_with_config(
+        &mut self,
+        delay: &mut impl DelayNs,
+        config: &ResetConfig,
+    ) -> Result<(), Error<B::Error>> {
+        let _guard = self.disable_all_interrupts_guarded().await?;
+        let mut inner = self.lock_inner().await;
+        inner.reset_with_config(delay, &Default::default(), config).await
+    }
+
+    /// Reset the device and boot from the given firmware bank, see [`internal::Tps6699x::reset_to_bank`]
+    pub async fn reset_to_bank(&mut self, delay: &mut impl DelayNs, bank: u8) -> Result<(), Error<B::Error>> {
+        let _guard = self.disable_all_interrupts_guarded().await?;
+        let mut inner = self.lock_inner().await;
+        inner.reset_to_bank(delay, bank).await
+    }
+
+    /// Trigger a reset without waiting for the controller to come back up
+    ///
+    /// Interrupts stay disabled until the returned guard is dropped, which should be after [`Self::wait_ready`]
+    /// returns.
+    pub async fn reset_nowait(&mut self) -> Result<interrupt::InterruptGuard<'a, M, B>, Error<B::Error>> {
+        let guard = self.disable_all_interrupts_guarded().await?;
+        self.lock_inner().await.reset_nowait(&Default::default()).await?;
+        Ok(guard)
+    }
+
+    /// Wait for the controller to be ready after [`Self::reset_nowait`], returns the mode the controller booted into
+    pub async fn wait_ready(&mut self, delay: &mut impl DelayNs, timeout_ms: u32) -> Result<Mode, Error<B::Error>> {
+        self.lock_inner().await.wait_ready(delay, timeout_ms).await
+    }
+
+    /// Wait for the controller to enter one of the `targets` modes, see [`internal::Tps6699x::wait_for_mode`]
+    pub async fn wait_for_mode(
+        &mut self,
+        targets: &[Mode],
+        delay: &mut impl DelayNs,
+        timeout_ms: u32,
+    ) -> Result<Mode, Error<B::Error>> {
+        self.lock_inner().await.wait_for_mode(targets, delay, timeout_ms).await
+    }
+
     /// Execute the [`Command::DISC`] command to disconnect a port for a specified amount of time (in seconds).
     pub async fn execute_disc(
         &mut self,
@@ -488,11 +1349,60 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         inner.get_boot_flags().await
     }
 
+    /// Wrapper for `get_bank_versions`
+    pub async fn get_bank_versions(
+        &mut self,
+    ) -> Result<[registers::boot_flags::BankInfo; registers::boot_flags::NUM_BANKS], Error<B::Error>> {
+        self.lock_inner().await.get_bank_versions().await
+    }
+
+    /// Wrapper for `get_bank_status`
+    pub async fn get_bank_status(&mut self) -> Result<registers::boot_flags::BankStatus, Error<B::Error>> {
+        self.lock_inner().await.get_bank_status().await
+    }
+
+    /// Check if the controller booted in dead battery mode, powered through `port`
+    pub async fn is_dead_battery(&mut self, port: LocalPortId) -> Result<bool, Error<B::Error>> {
+        self.validate_port(port)?;
+        Ok(self.get_boot_flags().await?.is_dead_battery(port))
+    }
+
+    /// Leave dead battery mode and negotiate a normal sink contract on `port`
+    ///
+    /// Does nothing if the controller isn't in dead battery mode through `port`. Otherwise the following sequence is
+    /// run, each step waiting for the previous one to complete:
+    /// 1. Enable the sink path with [`Self::enable_sink_path`]
+    /// 2. Wait for the sink path to be stable with [`Self::wait_power_path_stable`]
+    /// 3. Clear the dead battery flag with [`Command::Dbfg`]
+    /// 4. Negotiate a new contract with [`Self::autonegotiate_sink`]
+    ///
+    /// Negotiating before the sink path is on can drop VBUS while the system is still running from it, which is why
+    /// the path must be stable first. Returns [`PdError::Timeout`] if the sink path doesn't turn on in time.
+    pub async fn recover_from_dead_battery(&mut self, port: LocalPortId) -> Result<(), Error<B::Error>> {
+        if !self.is_dead_battery(port).await? {
+            return Ok(());
+        }
+
+        self.enable_sink_path(port, true).await?;
+        self.wait_power_path_stable(
+            port,
+            PowerPath::Sink,
+            Duration::from_millis(DEAD_BATTERY_SINK_PATH_TIMEOUT_MS),
+        )
+        .await?;
+        self.execute_command_checked(port, Command::Dbfg, None, None)
+            .await?
+            .success_or(PdError::Failed)
+            .map_err(Error::Pd)?;
+        self.autonegotiate_sink(port).await
+    }
+
     /// Get DP status
     pub async fn get_dp_status(
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::dp_status::DpStatus, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_dp_status(port).await
     }
@@ -502,6 +1412,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::IntelVidStatus, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_intel_vid_status(port).await
     }
@@ -511,6 +1422,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::UsbStatus, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_usb_status(port).await
     }
@@ -520,18 +1432,130 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::UserVidStatus, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_user_vid_status(port).await
     }
 
     /// Get complete alt-mode status
     pub async fn get_alt_mode_status(&mut self, port: LocalPortId) -> Result<AltMode, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_alt_mode_status(port).await
     }
 
+    /// Get the thermal state of a port
+    ///
+    /// Intended to be called when [`IntEventBus1::thermal_event`] or `pd_status_updated` is set so that the advertised
+    /// current can be reduced while the controller is under thermal stress. The register map doesn't expose the die
+    /// temperature, so this only reports whether the port went through an over-temperature shutdown.
+    pub async fn get_thermal_status(&mut self, port: LocalPortId) -> Result<ThermalStatus, Error<B::Error>> {
+        let pd_status = self.get_pd_status(port).await?;
+        Ok(ThermalStatus {
+            over_temperature: pd_status.error_recovery_details()
+                == registers::PdErrorRecoveryDetails::OverTemperatureShutdown,
+        })
+    }
+
+    /// Summarize the role, contract and alt-mode state of a port
+    pub async fn summarize_port(&mut self, port: LocalPortId) -> Result<summary::PortSummary, Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        let status = inner.get_port_status(port).await?;
+        if !status.plug_present() {
+            return Ok(summary::PortSummary::default());
+        }
+
+        let pd_status = inner.get_pd_status(port).await?;
+        let pdo_raw = inner.get_active_pdo_contract(port).await?.active_pdo();
+        let contract = fixed_contract(pdo_raw).map(|data| summary::ContractSummary {
+            voltage_mv: data.voltage_mv.into(),
+            current_ma: data.current_ma.into(),
+        });
+
+        let modes = read_mode_summary(&mut inner, port).await?;
+
+        Ok(summary::PortSummary {
+            connected: true,
+            is_source: pd_status.is_source(),
+            is_dfp: status.data_role(),
+            contract,
+            dp: modes.dp_active,
+            tbt: modes.tbt_active,
+            usb4: modes.usb4_active,
+        })
+    }
+
+    /// Get whether a plug is present on a port, along with its orientation and roles
+    ///
+    /// See [`interrupt::InterruptReceiver::wait_connection_change`] to wait for this to change.
+    pub async fn get_connection_state(
+        &mut self,
+        port: LocalPortId,
+    ) -> Result<summary::ConnectionState, Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        let status = inner.get_port_status(port).await?;
+        let pd_status = inner.get_pd_status(port).await?;
+        Ok(summary::ConnectionState::from_status(&status, &pd_status))
+    }
+
+    /// Get the power path faults on a port
+    ///
+    /// See [`interrupt::InterruptReceiver::wait_fault`] to wait for an overcurrent event. Faults are read from the
+    /// power path status so they clear once the condition is gone, a switch disabled by a fault must be re-enabled,
+    /// e.g. with [`Self::enable_source`]. The controller doesn't report overvoltage or overtemperature through this
+    /// register.
+    pub async fn get_faults(&mut self, port: LocalPortId) -> Result<summary::FaultStatus, Error<B::Error>> {
+        let status = self.get_power_path_status(port).await?;
+        summary::FaultStatus::from_power_path(&status, port).map_err(Error::Pd)
+    }
+
+    /// Get the active DP, TBT and USB4 modes on a port
+    pub async fn get_mode_summary(&mut self, port: LocalPortId) -> Result<summary::ModeSummary, Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        read_mode_summary(&mut inner, port).await
+    }
+
+    /// Handle a remote request, see [`remote`]
+    #[cfg(feature = "serde")]
+    pub async fn handle_request(&mut self, request: remote::Request) -> remote::Response {
+        use remote::{Request, Response};
+
+        let result = match request {
+            Request::GetMode => self.get_mode().await.map(Response::Mode),
+            Request::GetFwVersion => self.get_fw_version().await.map(Response::FwVersion),
+            Request::GetCustomerUse => self.get_customer_use().await.map(Response::CustomerUse),
+            Request::GetPortStatus { port } => self
+                .get_port_status(LocalPortId(port))
+                .await
+                .map(|r| Response::PortStatus(r.into())),
+            Request::GetPdStatus { port } => self
+                .get_pd_status(LocalPortId(port))
+                .await
+                .map(|r| Response::PdStatus(r.into())),
+            Request::GetPowerPathStatus { port } => self
+                .get_power_path_status(LocalPortId(port))
+                .await
+                .map(|r| Response::PowerPathStatus(r.into())),
+            Request::GetActivePdoContract { port } => self
+                .get_active_pdo_contract(LocalPortId(port))
+                .await
+                .map(|r| Response::ActivePdoContract(r.into())),
+            Request::GetActiveRdoContract { port } => self
+                .get_active_rdo_contract(LocalPortId(port))
+                .await
+                .map(|r| Response::ActiveRdoContract(r.into())),
+            Request::GetPortSummary { port } => self.summarize_port(LocalPortId(port)).await.map(Response::PortSummary),
+        };
+
+        result.unwrap_or_else(|e| Response::Error(e.into()))
+    }
+
     /// Set unconstrained power on a port
     pub async fn set_unconstrained_power(&mut self, port: LocalPortId, enable: bool) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.set_unconstrained_power(port, enable).await
     }
@@ -541,6 +1565,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::port_config::PortConfig, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_port_config(port).await
     }
@@ -551,7 +1576,101 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         config: registers::port_config::PortConfig,
     ) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        inner.set_port_config(port, config).await
+    }
+
+    /// Get the VBUS over-voltage protection threshold, as a percentage of the contract voltage
+    pub async fn get_vbus_ovp_threshold_pct(&mut self, port: LocalPortId) -> Result<u8, Error<B::Error>> {
+        Ok(self.get_port_config(port).await?.vbus_ovp_usage().percent())
+    }
+
+    /// Set the VBUS over-voltage protection threshold, as a percentage of the contract voltage
+    ///
+    /// Returns [`PdError::InvalidParams`] if the value isn't one of
+    /// [`registers::port_config::VbusOvpUsage::SUPPORTED_PERCENT`].
+    pub async fn set_vbus_ovp_threshold_pct(&mut self, port: LocalPortId, percent: u8) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
+        let usage = registers::port_config::VbusOvpUsage::from_percent(percent).ok_or(PdError::InvalidParams)?;
+        let mut inner = self.lock_inner().await;
+        let mut config = inner.get_port_config(port).await?;
+        config.set_vbus_ovp_usage(usage);
+        inner.set_port_config(port, config).await
+    }
+
+    /// Get the VBUS sink under-voltage protection threshold, as a percentage below the contract voltage
+    ///
+    /// Returns [`PdError::InvalidParams`] if the register contains a reserved value.
+    pub async fn get_vbus_uvp_threshold_pct(&mut self, port: LocalPortId) -> Result<u8, Error<B::Error>> {
+        Ok(self
+            .get_port_config(port)
+            .await?
+            .vbus_sink_uvp_trip_hv()
+            .percent()
+            .ok_or(PdError::InvalidParams)?)
+    }
+
+    /// Set the VBUS sink under-voltage protection threshold, as a percentage below the contract voltage
+    ///
+    /// Returns [`PdError::InvalidParams`] if the value isn't one of
+    /// [`registers::port_config::VbusSinkUvpTripHv::SUPPORTED_PERCENT`].
+    pub async fn set_vbus_uvp_threshold_pct(&mut self, port: LocalPortId, percent: u8) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
+        let trip = registers::port_config::VbusSinkUvpTripHv::from_percent(percent).ok_or(PdError::InvalidParams)?;
         let mut inner = self.lock_inner().await;
+        let mut config = inner.get_port_config(port).await?;
+        config.set_vbus_sink_uvp_trip_hv(trip);
+        inner.set_port_config(port, config).await
+    }
+
+    /// Force the CC line termination, e.g. for compliance testing
+    ///
+    /// Rp terminations configure the port as source only and set the Type-C current advertised in the port control
+    /// register, Rd configures it as sink only and [`CcConfig::Open`] disables the Type-C state machine. Both registers
+    /// are read-modify-written so other settings are kept. Changing the termination detaches the port partner, this
+    /// ends any active contract and may remove power if the system is running from the port.
+    pub async fn set_cc_termination(&mut self, port: LocalPortId, config: CcConfig) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
+        let (state_machine, current) = config.encode();
+        let mut inner = self.lock_inner().await;
+
+        if let Some(current) = current {
+            inner
+                .modify_port_control(port, |port_control| {
+                    port_control.set_typec_current(current);
+                    *port_control
+                })
+                .await?;
+        }
+
+        let mut port_config = inner.get_port_config(port).await?;
+        port_config.set_typec_state_machine(state_machine);
+        inner.set_port_config(port, port_config).await
+    }
+
+    /// Get the role preference used when attaching as a DRP
+    ///
+    /// Returns [`PdError::InvalidParams`] if the register contains a reserved value.
+    pub async fn get_drp_preference(
+        &mut self,
+        port: LocalPortId,
+    ) -> Result<registers::port_config::DrpPreference, Error<B::Error>> {
+        Ok(self.get_port_config(port).await?.drp_preference()?)
+    }
+
+    /// Set the role preference used when attaching as a DRP
+    ///
+    /// The preference only applies while the Type-C state machine is configured as a DRP.
+    pub async fn set_drp_preference(
+        &mut self,
+        port: LocalPortId,
+        pref: registers::port_config::DrpPreference,
+    ) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        let mut config = inner.get_port_config(port).await?;
+        config.set_drp_preference(pref);
         inner.set_port_config(port, config).await
     }
 
@@ -562,6 +1681,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::SxAppConfig, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_sx_app_config(port).await
     }
@@ -575,15 +1695,39 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         state: registers::SystemPowerState,
     ) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.set_sx_app_config(port, state).await
     }
 
+    /// Report the host system power state to every port
+    ///
+    /// Writes `state` to the Sx App Config register (`0x20`) of every port under a single lock, the controller then
+    /// applies the application configuration for that system power state. This is the power state of the host
+    /// system, it doesn't put the PD controller itself to sleep.
+    pub async fn set_system_power_state(&mut self, state: registers::SystemPowerState) -> Result<(), Error<B::Error>> {
+        let num_ports = self.num_ports();
+        let mut inner = self.lock_inner().await;
+        for port in 0..num_ports {
+            inner.set_sx_app_config(LocalPortId(port as u8), state).await?;
+        }
+        Ok(())
+    }
+
+    /// Get the host system power state last reported through [`Self::set_system_power_state`]
+    ///
+    /// The state is the same for all ports when set through [`Self::set_system_power_state`], only port 0 is read.
+    pub async fn get_system_power_state(&mut self) -> Result<registers::SystemPowerState, Error<B::Error>> {
+        let config = self.lock_inner().await.get_sx_app_config(crate::PORT0).await?;
+        Ok(config.sleep_state())
+    }
+
     /// Get the discovered SVIDs on a port returned from `Discover SVIDs REQ` messages.
     pub async fn get_discovered_svids(
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::discovered_svids::DiscoveredSvids, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_discovered_svids(port).await
     }
 
@@ -592,6 +1736,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<Option<Ado>, DeviceError<B::Error, ado::InvalidType>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         let ado_raw = inner.get_rx_ado(port).await.map_err(DeviceError::from)?;
 
@@ -608,6 +1753,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::RxAttnVdm, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_rx_attn_vdm(port).await
     }
@@ -617,6 +1763,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::rx_other_vdm::RxOtherVdm, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_rx_other_vdm(port).await
     }
@@ -648,6 +1795,73 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         }
     }
 
+    /// Verify that the active contract matches the voltage requested through
+    /// [`Self::set_autonegotiate_sink_max_voltage`].
+    ///
+    /// Returns `true` if there is an explicit fixed-supply contract at `expected_voltage_mv`. Returns `false` if
+    /// there is no explicit contract or the source could not provide the requested voltage.
+    pub async fn verify_sink_negotiation(
+        &mut self,
+        port: LocalPortId,
+        expected_voltage_mv: u16,
+    ) -> Result<bool, Error<B::Error>> {
+        let pdo_raw = self.get_active_pdo_contract(port).await?.active_pdo();
+        let verified =
+            fixed_contract(pdo_raw).is_some_and(|data| u32::from(data.voltage_mv) == expected_voltage_mv.into());
+        if !verified {
+            trace!(
+                "{:?}: Contract doesn't match {}mV, raw PDO {:#x}",
+                port, expected_voltage_mv, pdo_raw
+            );
+        }
+        Ok(verified)
+    }
+
+    /// Get the status of an SPR PPS contract
+    ///
+    /// Returns `None` if the active contract isn't a PPS contract. The voltage and current are the values in the
+    /// active request, the source's PPS status isn't exposed in the register map so current limiting isn't reported.
+    pub async fn get_pps_status(&mut self, port: LocalPortId) -> Result<Option<PpsStatus>, Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        let pdo_raw = inner.get_active_pdo_contract(port).await?.active_pdo();
+        let rdo_raw = inner.get_active_rdo_contract(port).await?.active_rdo();
+        Ok(pps_contract(pdo_raw, rdo_raw))
+    }
+
+    /// Get the negotiated power contract of a port
+    ///
+    /// The voltage and current come from the active PDO/RDO contract registers, normalized to mV and mA, and are
+    /// not measurements: the register map doesn't expose ADC samples of VBUS or the port current. For a fixed
+    /// contract these are the PDO voltage (50 mV units) and maximum current (10 mA units), for a PPS contract they
+    /// are the requested output voltage (20 mV units) and operating current (50 mA units).
+    /// [`ContractPower::vbus`] reports whether VBUS is actually at the contract voltage.
+    pub async fn get_contract_power(&mut self, port: LocalPortId) -> Result<ContractPower, Error<B::Error>> {
+        self.validate_port(port)?;
+        let mut inner = self.lock_inner().await;
+        let status = inner.get_port_status(port).await?;
+        let power_path = inner.get_power_path_status(port).await?;
+        let pdo_raw = inner.get_active_pdo_contract(port).await?.active_pdo();
+        let rdo_raw = inner.get_active_rdo_contract(port).await?.active_rdo();
+
+        let vconn = vconn_switch(&power_path, port).map_err(Error::Pd)?;
+
+        let (voltage_mv, current_ma) = if let Some(data) = fixed_contract(pdo_raw) {
+            (Some(data.voltage_mv.into()), Some(data.current_ma.into()))
+        } else if let Some(pps) = pps_contract(pdo_raw, rdo_raw) {
+            (Some(pps.output_voltage_mv), Some(pps.output_current_ma))
+        } else {
+            (None, None)
+        };
+
+        Ok(ContractPower {
+            vbus: status.vbus_status(),
+            voltage_mv,
+            current_ma,
+            vconn,
+        })
+    }
+
     /// Get Rx source/sink Caps
     ///
     /// Returns (num_standard_pdos, num_epr_pdos).
@@ -656,6 +1870,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         register: u8,
     ) -> Result<rx_caps::RxCaps<T>, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         let mut out_spr_pdos = [T::default(); crate::registers::rx_caps::NUM_SPR_PDOS];
         let mut out_epr_pdos = [T::default(); crate::registers::rx_caps::NUM_EPR_PDOS];
@@ -684,11 +1899,33 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.get_rx_caps(port, registers::rx_caps::RX_SRC_ADDR).await
     }
 
+    /// Get Rx source Caps, using a cached value if available
+    ///
+    /// The cache is invalidated by the interrupt processor when new source caps are received, a new consumer or
+    /// provider contract is negotiated, a power role swap completes, or on a plug event or hard reset. The cached value
+    /// is therefore only valid while the interrupt task is running and these interrupts are unmasked. Caps read while
+    /// an invalidation arrives are returned but not cached.
+    pub async fn get_rx_src_caps_cached(&mut self, port: LocalPortId) -> Result<rx_caps::RxSrcCaps, Error<B::Error>> {
+        self.validate_port(port)?;
+        let (generation, cached) = self
+            .controller
+            .cached_rx_src_caps(port)
+            .ok_or(Error::Pd(PdError::InvalidPort))?;
+        if let Some(caps) = cached {
+            return Ok(caps);
+        }
+
+        let caps = self.get_rx_src_caps(port).await?;
+        self.controller.store_rx_src_caps(port, generation, &caps);
+        Ok(caps)
+    }
+
     /// Get Tx Identity
     pub async fn get_tx_identity(
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::tx_identity::TxIdentity, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_tx_identity(port).await
     }
 
@@ -698,6 +1935,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         value: registers::tx_identity::TxIdentity,
     ) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.set_tx_identity(port, value).await
     }
 
@@ -707,6 +1945,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         f: impl FnOnce(&mut registers::tx_identity::TxIdentity) -> registers::tx_identity::TxIdentity,
     ) -> Result<registers::tx_identity::TxIdentity, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.modify_tx_identity(port, f).await
     }
 
@@ -715,6 +1954,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::received_sop_identity_data::ReceivedSopIdentityData, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_received_sop_identity_data(port).await
     }
 
@@ -723,14 +1963,38 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::received_sop_prime_identity_data::ReceivedSopPrimeIdentityData, Error<B::Error>> {
+        self.validate_port(port)?;
         self.lock_inner().await.get_received_sop_prime_identity_data(port).await
     }
 
+    /// Get the identity VDOs of the attached cable, from the latest SOP' Discover Identity response
+    ///
+    /// Returns `None` if the cable plug didn't ACK a Discover Identity, e.g. if no e-marked cable is attached or
+    /// discovery hasn't run yet. Returns [`PdError::Serialize`] if the response couldn't be decoded.
+    pub async fn get_cable_vdos(
+        &mut self,
+        port: LocalPortId,
+    ) -> Result<Option<sop_prime::ResponseVdos>, Error<B::Error>> {
+        let data = self.get_received_sop_prime_identity_data(port).await?;
+        if !matches!(data.response_type(), CommandType::Ack) || data.number_valid_vdos() == 0 {
+            return Ok(None);
+        }
+
+        match data.try_into() {
+            Ok(vdos) => Ok(Some(vdos)),
+            Err(e) => {
+                error!("{:?}: Failed to decode cable identity: {:?}", port, e);
+                PdError::Serialize.into()
+            }
+        }
+    }
+
     /// Get DP config
     pub async fn get_dp_config(
         &mut self,
         port: LocalPortId,
     ) -> Result<registers::field_sets::DpConfig, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.get_dp_config(port).await
     }
@@ -741,6 +2005,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         config: registers::field_sets::DpConfig,
     ) -> Result<(), Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.set_dp_config(port, config).await
     }
@@ -751,6 +2016,7 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         port: LocalPortId,
         f: impl FnOnce(&mut registers::field_sets::DpConfig) -> registers::field_sets::DpConfig,
     ) -> Result<registers::field_sets::DpConfig, Error<B::Error>> {
+        self.validate_port(port)?;
         let mut inner = self.lock_inner().await;
         inner.modify_dp_config(port, f).await
     }
@@ -765,6 +2031,83 @@ impl<'a, M: RawMutex, B: I2c> Tps6699x<'a, M, B> {
         self.execute_command(port, Command::HRST, None, None).await
     }
 
+    /// Run `cmd` on `port` then wait for any of the `event` flags to be reported for that port through `receiver`
+    ///
+    /// Matching flags that are already pending are discarded before the command is sent, so only events caused by
+    /// it complete the wait. Only the flags in `event` for `port` are consumed, everything else is left pending.
+    /// Returns [`PdError::InvalidParams`] if `receiver` belongs to a different controller.
+    async fn execute_command_wait_event(
+        &mut self,
+        receiver: &mut InterruptReceiver<'_, M, B>,
+        port: LocalPortId,
+        cmd: Command,
+        indata: Option<&[u8]>,
+        event: IntEventBus1,
+    ) -> Result<(), Error<B::Error>> {
+        if !core::ptr::eq(receiver.controller, self.controller) {
+            return PdError::InvalidParams.into();
+        }
+
+        let mut mask = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+        *mask.get_mut(port.0 as usize).ok_or(PdError::InvalidPort)? = event;
+        self.controller.take_interrupts(mask);
+
+        self.execute_command_checked(port, cmd, indata, None)
+            .await?
+            .success_or(PdError::Failed)
+            .map_err(Error::Pd)?;
+
+        with_timeout(
+            Duration::from_millis(PORT_RESET_EVENT_TIMEOUT_MS),
+            receiver.wait_any_masked(false, mask),
+        )
+        .await
+        .map_err(|_| {
+            error!("{:?}: {:?} event timed out", port, cmd);
+            Error::Pd(PdError::Timeout)
+        })?;
+        Ok(())
+    }
+
+    /// Send a USB PD hard reset on `port` with [`Command::HRST`] and wait for it to be performed
+    ///
+    /// Unlike [`Self::reset`], only this port is affected, the controller isn't rebooted. Completion is detected
+    /// through the [`IntEventBus1::hard_reset`] event, which is taken from `receiver`, so interrupt processing must be
+    /// running. Returns [`PdError::Timeout`] if the event isn't reported in time.
+    pub async fn send_hard_reset(
+        &mut self,
+        port: LocalPortId,
+        receiver: &mut InterruptReceiver<'_, M, B>,
+    ) -> Result<(), Error<B::Error>> {
+        let mut event = IntEventBus1::new_zero();
+        event.set_hard_reset(true);
+        self.execute_command_wait_event(receiver, port, Command::HRST, None, event)
+            .await
+    }
+
+    /// Put `port` through Type-C error recovery and wait for the resulting detach
+    ///
+    /// The register map has no dedicated error recovery task, so this uses [`Command::DISC`] to remove the port's
+    /// terminations for one second before it reconnects. Only this port is affected. The detach is detected through
+    /// the [`IntEventBus1::plug_event`] event, which is taken from `receiver`, so interrupt processing must be running
+    /// and a partner must be attached. Returns [`PdError::Timeout`] if the event isn't reported in time.
+    pub async fn trigger_error_recovery(
+        &mut self,
+        port: LocalPortId,
+        receiver: &mut InterruptReceiver<'_, M, B>,
+    ) -> Result<(), Error<B::Error>> {
+        let mut event = IntEventBus1::new_zero();
+        event.set_plug_event(true);
+        self.execute_command_wait_event(
+            receiver,
+            port,
+            Command::DISC,
+            Some(&[ERROR_RECOVERY_DISCONNECT_TIME_S]),
+            event,
+        )
+        .await
+    }
+
     /// Get Rx discovered custom modes
     pub async fn execute_gcdm(
         &mut self,
@@ -828,3 +2171,1512 @@ impl<'a, M: RawMutex, B: I2c> InterruptController for Tps6699x<'a, M, B> {
         Ok(interrupt::InterruptGuard::new(self.controller, enabled))
     }
 }
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use std::vec;
+    use std::vec::Vec;
+
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use static_cell::StaticCell;
+
+    use super::*;
+    use crate::test::{
+        PORT0_ADDR0, PORT1_ADDR0, TEST_SRC_PDO_FIXED_5V3A_RAW, TEST_SRC_PDO_FIXED_9V3000MA_RAW, create_register_read,
+        create_register_write,
+    };
+    use crate::{PORT0, PORT1};
+
+    /// Bus transactions for a command on port 0 with no input or output data that completes with `ret`
+    fn command_transactions(cmd: Command, ret: ReturnValue) -> [Transaction; 3] {
+        let mut data = [0u8; registers::REG_DATA1_LEN];
+        data[0] = ret as u8;
+        [
+            create_register_write(PORT0_ADDR0, 0x08, (cmd as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x08, (Command::Success as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x09, data),
+        ]
+    }
+
+    /// Bus transactions for a command on port 0 with input data and no output data that completes with `ret`
+    fn command_transactions_with_input<const N: usize>(
+        cmd: Command,
+        input: [u8; N],
+        ret: ReturnValue,
+    ) -> Vec<Transaction> {
+        let mut transactions = vec![create_register_write(PORT0_ADDR0, registers::REG_DATA1, input)];
+        transactions.extend(command_transactions(cmd, ret));
+        transactions
+    }
+
+    /// Bus transactions for a command on port 0 with input and output data that completes with `ret`
+    fn command_transactions_with_output<const N: usize>(
+        cmd: Command,
+        input: [u8; N],
+        ret: ReturnValue,
+        output: &[u8],
+    ) -> [Transaction; 4] {
+        let mut data = [0u8; registers::REG_DATA1_LEN];
+        data[0] = ret as u8;
+        data[1..=output.len()].copy_from_slice(output);
+        [
+            create_register_write(PORT0_ADDR0, registers::REG_DATA1, input),
+            create_register_write(PORT0_ADDR0, 0x08, (cmd as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x08, (Command::Success as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x09, data),
+        ]
+    }
+
+    /// Bus transactions for a `GCdm` command on port 0 that discovers a single DP mode at `position`
+    fn dp_mode_transactions(position: u8) -> [Transaction; 4] {
+        // GCdm has no return value, the discovered modes start at the first byte
+        let mut data = [0u8; registers::REG_DATA1_LEN];
+        data[..4].copy_from_slice(&0x0000_0c46u32.to_le_bytes());
+        data[4] = position;
+        [
+            create_register_write(PORT0_ADDR0, registers::REG_DATA1, [0x00, 0x01, 0xFF]),
+            create_register_write(PORT0_ADDR0, 0x08, (Command::GCdm as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x08, (Command::Success as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x09, data),
+        ]
+    }
+
+    /// Stand-in for the interrupt processor, signals command completion on `port` once the command has been sent
+    async fn complete_command(controller: &controller::Controller<NoopRawMutex, Mock>, port: LocalPortId) {
+        Timer::after_millis(1).await;
+        controller.command_complete[port.0 as usize].signal(());
+    }
+
+    /// Keep signalling command completion on `port` until `fut` completes, for operations that run several commands
+    async fn complete_commands<F: Future>(
+        controller: &controller::Controller<NoopRawMutex, Mock>,
+        port: LocalPortId,
+        fut: F,
+    ) -> F::Output {
+        let mut fut = core::pin::pin!(fut);
+        loop {
+            tokio::select! {
+                output = &mut fut => return output,
+                _ = complete_command(controller, port) => {}
+            }
+        }
+    }
+
+    /// Test that the configured bus retry policy is applied to register accesses
+    #[tokio::test]
+    async fn test_bus_retry() {
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        const NAK: ErrorKind = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+        let read = create_register_read(PORT0_ADDR0, 0x03, (Mode::App0 as u32).to_le_bytes());
+        let config = controller::Config {
+            bus_retry: internal::RetryConfig {
+                count: 2,
+                delay_us: 100,
+            },
+            ..Default::default()
+        };
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&[read.clone().with_error(NAK), read.clone().with_error(NAK), read]),
+                config,
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(pd.get_mode().await, Ok(Mode::App0));
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_validate_port() {
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER
+            .init(controller::Controller::new_tps66993(Mock::new(&[]), Default::default(), PORT0_ADDR0).unwrap());
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(pd.validate_port(PORT0), Ok(()));
+        assert_eq!(pd.validate_port(PORT1), Err(Error::Pd(PdError::InvalidPort)));
+
+        // Rejected before touching the bus
+        assert_eq!(
+            pd.get_port_status(PORT1).await.map(|_| ()),
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_rx_snk_caps() {
+        // Sink fixed PDOs, 5V 3A and 28V 5A
+        const SNK_PDO_FIXED_5V3A_RAW: u32 = (100 << 10) | 300;
+        const SNK_PDO_FIXED_28V5A_RAW: u32 = (560 << 10) | 500;
+
+        // 1 SPR PDO, 1 EPR PDO
+        let mut caps = [0u8; registers::rx_caps::LEN];
+        caps[0] = 0x09;
+        caps[1..5].copy_from_slice(&SNK_PDO_FIXED_5V3A_RAW.to_le_bytes());
+        let epr_start = registers::rx_caps::HEADER_LEN + 4 * registers::rx_caps::EPR_PDO_START_INDEX;
+        caps[epr_start..epr_start + 4].copy_from_slice(&SNK_PDO_FIXED_28V5A_RAW.to_le_bytes());
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&[
+                    create_register_read(
+                        PORT0_ADDR0,
+                        registers::rx_caps::RX_SNK_ADDR,
+                        [0u8; registers::rx_caps::LEN],
+                    ),
+                    create_register_read(PORT0_ADDR0, registers::rx_caps::RX_SNK_ADDR, caps),
+                ]),
+                Default::default(),
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        // No sink caps received
+        assert!(pd.get_rx_snk_caps(PORT0).await.unwrap().is_empty());
+
+        let caps = pd.get_rx_snk_caps(PORT0).await.unwrap();
+        assert_eq!(
+            caps.spr_as_slice(),
+            &[pdo::sink::Pdo::Fixed(pdo::sink::FixedData {
+                frs_required_current: pdo::sink::FrsRequiredCurrent::None,
+                voltage_mv: 5000,
+                operational_current_ma: 3000,
+                ..Default::default()
+            })]
+        );
+        assert_eq!(
+            caps.epr_as_slice(),
+            &[pdo::sink::Pdo::Fixed(pdo::sink::FixedData {
+                frs_required_current: pdo::sink::FrsRequiredCurrent::None,
+                voltage_mv: 28000,
+                operational_current_ma: 5000,
+                ..Default::default()
+            })]
+        );
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_swap_data_role() {
+        let mut transactions = Vec::new();
+        transactions.extend(command_transactions(Command::SwapToDfp, ReturnValue::Success));
+        transactions.extend(command_transactions(Command::SwapToUfp, ReturnValue::Success));
+        transactions.extend(command_transactions(Command::SwapToDfp, ReturnValue::Rejected));
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        let (result, _) = tokio::join!(
+            pd.swap_data_role(PORT0, DataRole::Dfp),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Ok(()));
+
+        let (result, _) = tokio::join!(
+            pd.swap_data_role(PORT0, DataRole::Ufp),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Ok(()));
+
+        let (result, _) = tokio::join!(
+            pd.swap_data_role(PORT0, DataRole::Dfp),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Err(Error::Pd(PdError::Rejected)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_swap_power_role() {
+        let mut source_status = registers::field_sets::Status::new_zero();
+        source_status.set_port_role(true);
+
+        let mut transactions = Vec::new();
+        transactions.extend(command_transactions(Command::SwapToSource, ReturnValue::Success));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x1A, source_status));
+        transactions.extend(command_transactions(Command::SwapToSink, ReturnValue::Success));
+        transactions.push(create_register_read(
+            PORT0_ADDR0,
+            0x1A,
+            registers::field_sets::Status::new_zero(),
+        ));
+        transactions.extend(command_transactions(Command::SwapToSource, ReturnValue::Rejected));
+        transactions.extend(command_transactions(Command::SwapToSource, ReturnValue::Abort));
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        let (result, _) = tokio::join!(
+            pd.swap_power_role(PORT0, PowerRole::Source),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Ok(PowerRole::Source));
+
+        let (result, _) = tokio::join!(
+            pd.swap_power_role(PORT0, PowerRole::Sink),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Ok(PowerRole::Sink));
+
+        // Rejected by the port partner, role isn't read back
+        let (result, _) = tokio::join!(
+            pd.swap_power_role(PORT0, PowerRole::Source),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Err(Error::Pd(PdError::Rejected)));
+
+        let (result, _) = tokio::join!(
+            pd.swap_power_role(PORT0, PowerRole::Source),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Err(Error::Pd(PdError::Timeout)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_send_vdm() {
+        // Discover Identity request
+        const DISCOVER_IDENTITY: u32 = 0xFF00A001;
+
+        let mut expected = [0u8; vdms::INPUT_LEN];
+        // 1 VDO, version 2, SOP'
+        expected[0] = 0x19;
+        expected[1..5].copy_from_slice(&DISCOVER_IDENTITY.to_le_bytes());
+        // Initiator
+        expected[29] = 0x01;
+        expected[30] = vdms::INITIATOR_WAIT_TIME_MS;
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&command_transactions_with_input(
+                    Command::VDMs,
+                    expected,
+                    ReturnValue::Success,
+                )),
+                Default::default(),
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        let (result, _) = tokio::join!(
+            pd.send_vdm(PORT0, vdms::SopTarget::SopPrime, &[DISCOVER_IDENTITY]),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Ok(()));
+
+        // Rejected before touching the bus
+        assert_eq!(
+            pd.send_vdm(PORT0, vdms::SopTarget::Sop, &[]).await,
+            Err(Error::Pd(PdError::InvalidParams))
+        );
+        assert_eq!(
+            pd.send_vdm(PORT0, vdms::SopTarget::Sop, &[0; vdms::MAX_NUM_DATA_OBJECTS + 1])
+                .await,
+            Err(Error::Pd(PdError::InvalidParams))
+        );
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_query_fw_update_status() {
+        use crate::command::{TFUQ_RETURN_LEN, TfuqBlockStatus, TfuqReturnValue};
+
+        let mut output = [0u8; TFUQ_RETURN_LEN];
+        // Active host, current state
+        output[0] = 0x01;
+        output[1] = 0x02;
+        // Header and first data block written
+        output[5] = 0x03;
+        output[7] = TfuqBlockStatus::HeaderValidAndAuthentic as u8;
+        output[8] = TfuqBlockStatus::DataValidAndAuthentic as u8;
+        output[20..24].copy_from_slice(&0x800u32.to_le_bytes());
+        output[26..30].copy_from_slice(&0x4000u32.to_le_bytes());
+
+        let mut transactions = Vec::new();
+        // Status in progress query
+        transactions.extend(command_transactions_with_output(
+            Command::Tfuq,
+            [0x01, 0x00],
+            ReturnValue::Success,
+            &output,
+        ));
+        transactions.extend(command_transactions_with_output(
+            Command::Tfuq,
+            [0x01, 0x00],
+            ReturnValue::Rejected,
+            &[],
+        ));
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        let mut block_status = [TfuqBlockStatus::Success; 13];
+        block_status[0] = TfuqBlockStatus::HeaderValidAndAuthentic;
+        block_status[1] = TfuqBlockStatus::DataValidAndAuthentic;
+        let (result, _) = tokio::join!(pd.query_fw_update_status(), complete_command(controller, PORT0));
+        assert_eq!(
+            result,
+            Ok(TfuqReturnValue {
+                active_host: 0x01,
+                current_state: 0x02,
+                image_write_status: 0x00,
+                blocks_written_bitfield: 0x03,
+                block_status,
+                num_of_header_bytes_received: 0x800,
+                num_of_data_bytes_received: 0x4000,
+                num_of_app_config_updates: 0,
+            })
+        );
+
+        let (result, _) = tokio::join!(pd.query_fw_update_status(), complete_command(controller, PORT0));
+        assert_eq!(result, Err(Error::Pd(PdError::Failed)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_hpd() {
+        let mut transactions = Vec::new();
+        transactions.extend(dp_mode_transactions(2));
+        transactions.extend(command_transactions_with_input(
+            Command::VDMs,
+            *vdms::Input::dp_hpd_attention(2, vdms::HpdState::High, false).as_bytes(),
+            ReturnValue::Success,
+        ));
+        transactions.extend(dp_mode_transactions(2));
+        transactions.extend(command_transactions_with_input(
+            Command::VDMs,
+            *vdms::Input::dp_hpd_attention(2, vdms::HpdState::High, true).as_bytes(),
+            ReturnValue::Success,
+        ));
+        transactions.extend(dp_mode_transactions(2));
+        transactions.extend(command_transactions_with_input(
+            Command::VDMs,
+            *vdms::Input::dp_hpd_attention(2, vdms::HpdState::Low, false).as_bytes(),
+            ReturnValue::Success,
+        ));
+        // No DP mode discovered
+        transactions.extend(dp_mode_transactions(0));
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        // HPD starts low, IRQ is rejected without touching the bus
+        assert_eq!(pd.pulse_hpd_irq(PORT0).await, Err(Error::Pd(PdError::InvalidMode)));
+
+        let result = complete_commands(controller, PORT0, pd.set_hpd(PORT0, vdms::HpdState::High)).await;
+        assert_eq!(result, Ok(()));
+
+        let result = complete_commands(controller, PORT0, pd.pulse_hpd_irq(PORT0)).await;
+        assert_eq!(result, Ok(()));
+
+        // Detach forgets the HPD state
+        let mut event = IntEventBus1::new_zero();
+        event.set_plug_event(true);
+        controller.clear_hpd(PORT0, &event);
+        assert_eq!(pd.pulse_hpd_irq(PORT0).await, Err(Error::Pd(PdError::InvalidMode)));
+
+        let result = complete_commands(controller, PORT0, pd.set_hpd(PORT0, vdms::HpdState::Low)).await;
+        assert_eq!(result, Ok(()));
+
+        assert_eq!(pd.pulse_hpd_irq(PORT0).await, Err(Error::Pd(PdError::InvalidMode)));
+        assert_eq!(pd.pulse_hpd_irq(PORT1).await, Err(Error::Pd(PdError::InvalidPort)));
+
+        let result = complete_commands(controller, PORT0, pd.set_hpd(PORT0, vdms::HpdState::High)).await;
+        assert_eq!(result, Err(Error::Pd(PdError::InvalidMode)));
+        assert_eq!(pd.pulse_hpd_irq(PORT0).await, Err(Error::Pd(PdError::InvalidMode)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_set_fast_role_swap_enabled() {
+        const FRS_BIT: u64 = 1 << 22;
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&[
+                    create_register_read(PORT0_ADDR0, 0x29, (!FRS_BIT).to_le_bytes()),
+                    create_register_write(PORT0_ADDR0, 0x29, u64::MAX.to_le_bytes()),
+                    create_register_read(PORT0_ADDR0, 0x29, u64::MAX.to_le_bytes()),
+                    create_register_write(PORT0_ADDR0, 0x29, (!FRS_BIT).to_le_bytes()),
+                ]),
+                Default::default(),
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        // All other control bits must be preserved
+        pd.set_fast_role_swap_enabled(PORT0, true).await.unwrap();
+        pd.set_fast_role_swap_enabled(PORT0, false).await.unwrap();
+        assert_eq!(
+            pd.set_fast_role_swap_enabled(PORT1, true).await,
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_vconn_status() {
+        // PA VCONN switch on CC2, bits 0-1
+        const POWER_PATH_VCONN_CC2: [u8; 5] = [0x03, 0x00, 0x00, 0x00, 0x00];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&[create_register_read(PORT0_ADDR0, 0x26, POWER_PATH_VCONN_CC2)]),
+                Default::default(),
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(pd.get_vconn_status(PORT0).await, Ok(registers::PpVconnSw::Cc2));
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[test]
+    fn test_cc_config_encode() {
+        use registers::TypecCurrent;
+        use registers::port_config::TypeCStateMachine;
+
+        assert_eq!(
+            CcConfig::RpDefault.encode(),
+            (TypeCStateMachine::Source, Some(TypecCurrent::UsbDefault))
+        );
+        assert_eq!(
+            CcConfig::Rp1A5.encode(),
+            (TypeCStateMachine::Source, Some(TypecCurrent::Current1A5))
+        );
+        assert_eq!(
+            CcConfig::Rp3A0.encode(),
+            (TypeCStateMachine::Source, Some(TypecCurrent::Current3A0))
+        );
+        assert_eq!(CcConfig::Rd.encode(), (TypeCStateMachine::Sink, None));
+        assert_eq!(CcConfig::Open.encode(), (TypeCStateMachine::Disabled, None));
+    }
+
+    #[tokio::test]
+    async fn test_set_cc_termination() {
+        const CONFIG_ALL_SET: [u8; registers::port_config::LEN] = [0xFF; registers::port_config::LEN];
+        // Type-C state machine is bits 0-1 of the port configuration
+        let port_config = |state_machine: u8| {
+            let mut config = CONFIG_ALL_SET;
+            config[0] = 0xFC | state_machine;
+            config
+        };
+        // Type-C current is bits 0-1 of the port control
+        let port_control = |current: u64| (!0x3u64 | current).to_le_bytes();
+
+        let mut transactions = Vec::new();
+        for current in [0x0, 0x1, 0x2] {
+            transactions.push(create_register_read(PORT0_ADDR0, 0x29, u64::MAX.to_le_bytes()));
+            transactions.push(create_register_write(PORT0_ADDR0, 0x29, port_control(current)));
+            transactions.push(create_register_read(
+                PORT0_ADDR0,
+                registers::port_config::ADDR,
+                CONFIG_ALL_SET,
+            ));
+            // Source
+            transactions.push(create_register_write(
+                PORT0_ADDR0,
+                registers::port_config::ADDR,
+                port_config(0x1),
+            ));
+        }
+        // Rd and open only touch the port configuration
+        for state_machine in [0x0, 0x3] {
+            transactions.push(create_register_read(
+                PORT0_ADDR0,
+                registers::port_config::ADDR,
+                port_config(0x2),
+            ));
+            transactions.push(create_register_write(
+                PORT0_ADDR0,
+                registers::port_config::ADDR,
+                port_config(state_machine),
+            ));
+        }
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        for config in [
+            CcConfig::RpDefault,
+            CcConfig::Rp1A5,
+            CcConfig::Rp3A0,
+            CcConfig::Rd,
+            CcConfig::Open,
+        ] {
+            pd.set_cc_termination(PORT0, config).await.unwrap();
+        }
+        assert_eq!(
+            pd.set_cc_termination(PORT1, CcConfig::Rd).await,
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_drp_preference() {
+        const ALL_SET: [u8; registers::port_config::LEN] = [0xFF; registers::port_config::LEN];
+        // TypeC Support Options is bits 8-9, Try.SRC is 0x1
+        let mut try_src = ALL_SET;
+        try_src[1] = 0xFD;
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&[
+                    create_register_read(PORT0_ADDR0, registers::port_config::ADDR, ALL_SET),
+                    create_register_read(PORT0_ADDR0, registers::port_config::ADDR, ALL_SET),
+                    create_register_write(PORT0_ADDR0, registers::port_config::ADDR, try_src),
+                    create_register_read(PORT0_ADDR0, registers::port_config::ADDR, try_src),
+                ]),
+                Default::default(),
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        // Reserved value
+        assert_eq!(
+            pd.get_drp_preference(PORT0).await,
+            Err(Error::Pd(PdError::InvalidParams))
+        );
+
+        // Only the support options bits may change
+        pd.set_drp_preference(PORT0, registers::port_config::DrpPreference::TrySrc)
+            .await
+            .unwrap();
+        assert_eq!(
+            pd.get_drp_preference(PORT0).await,
+            Ok(registers::port_config::DrpPreference::TrySrc)
+        );
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_recover_from_dead_battery() {
+        // Dead battery flag is bit 128 and port A power provider bit 130
+        let mut dead_battery = [0u8; registers::boot_flags::LEN];
+        dead_battery[16] = 0x05;
+        // PA external switch enabled as input, bits 12-14
+        const POWER_PATH_SINK_ON: [u8; 5] = [0x00, 0x30, 0x00, 0x00, 0x00];
+
+        let mut transactions = Vec::new();
+        // Not in dead battery mode, nothing to do
+        transactions.push(create_register_read(
+            PORT0_ADDR0,
+            registers::boot_flags::ADDR,
+            [0u8; registers::boot_flags::LEN],
+        ));
+        transactions.push(create_register_read(
+            PORT0_ADDR0,
+            registers::boot_flags::ADDR,
+            dead_battery,
+        ));
+        transactions.extend(command_transactions_with_input(
+            Command::Srdy,
+            [SrdySwitch::PpExt1.into()],
+            ReturnValue::Success,
+        ));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x26, POWER_PATH_SINK_ON));
+        transactions.extend(command_transactions(Command::Dbfg, ReturnValue::Success));
+        transactions.extend(command_transactions(Command::Aneg, ReturnValue::Success));
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        assert_eq!(pd.recover_from_dead_battery(PORT0).await, Ok(()));
+        assert_eq!(
+            complete_commands(controller, PORT0, pd.recover_from_dead_battery(PORT0)).await,
+            Ok(())
+        );
+        assert_eq!(pd.is_dead_battery(PORT1).await, Err(Error::Pd(PdError::InvalidPort)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_enable_source_checked() {
+        use registers::VbusSwConfig;
+        use registers::field_sets::SystemConfig;
+
+        // PA internal switch, bits 6-8
+        const POWER_PATH_SOURCE_ON: [u8; 5] = [0x80, 0x00, 0x00, 0x00, 0x00];
+        const POWER_PATH_SOURCE_FAULT: [u8; 5] = [0x40, 0x00, 0x00, 0x00, 0x00];
+        const POWER_PATH_SOURCE_OFF: [u8; 5] = [0x00; 5];
+
+        let mut source_on = SystemConfig::new_zero();
+        source_on.set_pa_pp_5_v_vbus_sw_config(VbusSwConfig::Source);
+
+        let mut transactions = Vec::new();
+        // Switch comes up after one poll
+        transactions.push(create_register_read(PORT0_ADDR0, 0x27, SystemConfig::new_zero()));
+        transactions.push(create_register_write(PORT0_ADDR0, 0x27, source_on));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x26, POWER_PATH_SOURCE_OFF));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x26, POWER_PATH_SOURCE_ON));
+        // Switch disabled by a fault, e.g. overcurrent
+        transactions.push(create_register_read(PORT0_ADDR0, 0x27, SystemConfig::new_zero()));
+        transactions.push(create_register_write(PORT0_ADDR0, 0x27, source_on));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x26, POWER_PATH_SOURCE_FAULT));
+        // Switch turns off
+        transactions.push(create_register_read(PORT0_ADDR0, 0x27, source_on));
+        transactions.push(create_register_write(PORT0_ADDR0, 0x27, SystemConfig::new_zero()));
+        transactions.push(create_register_read(PORT0_ADDR0, 0x26, POWER_PATH_SOURCE_OFF));
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        let timeout = Duration::from_millis(100);
+        assert_eq!(pd.enable_source_checked(PORT0, true, timeout).await, Ok(()));
+        assert_eq!(
+            pd.enable_source_checked(PORT0, true, timeout).await,
+            Err(Error::Pd(PdError::Failed))
+        );
+        assert_eq!(pd.enable_source_checked(PORT0, false, timeout).await, Ok(()));
+        assert_eq!(
+            pd.enable_source_checked(PORT1, true, timeout).await,
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_cable_vdos() {
+        use embedded_usb_pd::vdm::structured::command::discover_identity::CertStatVdo;
+
+        // Plug connector, passive cable product type
+        const PASSIVE_CABLE_ID_HEADER: u32 = (0b11 << 21) | (0b011 << 27);
+        // 3 A, <10 ns latency, USB Type-C plug
+        const PASSIVE_CABLE_VDO: u32 = (0b01 << 5) | (0b0001 << 13) | (0b10 << 18);
+        const CERT_STAT: u32 = 0x1234;
+
+        let identity = |header: u8, vdos: &[u32]| {
+            let mut raw = [0u8; registers::received_sop_prime_identity_data::LEN];
+            raw[0] = header;
+            for (i, vdo) in vdos.iter().enumerate() {
+                raw[1 + i * 4..5 + i * 4].copy_from_slice(&vdo.to_le_bytes());
+            }
+            raw
+        };
+        // ACK (bits 7:6 = 0b01) with the number of VDOs in bits 2:0
+        let ack = |num_vdos: u8| 0x40 | num_vdos;
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&[
+                    create_register_read(
+                        PORT0_ADDR0,
+                        registers::received_sop_prime_identity_data::ADDR,
+                        identity(ack(4), &[PASSIVE_CABLE_ID_HEADER, CERT_STAT, 0, PASSIVE_CABLE_VDO]),
+                    ),
+                    // No cable discovered
+                    create_register_read(
+                        PORT0_ADDR0,
+                        registers::received_sop_prime_identity_data::ADDR,
+                        identity(0, &[]),
+                    ),
+                    // NAK from the cable plug
+                    create_register_read(
+                        PORT0_ADDR0,
+                        registers::received_sop_prime_identity_data::ADDR,
+                        identity(0x80, &[]),
+                    ),
+                    // Passive cable without its cable VDO
+                    create_register_read(
+                        PORT0_ADDR0,
+                        registers::received_sop_prime_identity_data::ADDR,
+                        identity(ack(3), &[PASSIVE_CABLE_ID_HEADER, CERT_STAT, 0]),
+                    ),
+                ]),
+                Default::default(),
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        let vdos = pd.get_cable_vdos(PORT0).await.unwrap().unwrap();
+        assert_eq!(vdos.cert_stat, CertStatVdo(CERT_STAT));
+        assert_eq!(
+            vdos.product_type_vdos,
+            sop_prime::ProductTypeVdos::PassiveCable(PASSIVE_CABLE_VDO.try_into().unwrap())
+        );
+
+        assert_eq!(pd.get_cable_vdos(PORT0).await, Ok(None));
+        assert_eq!(pd.get_cable_vdos(PORT0).await, Ok(None));
+        assert_eq!(pd.get_cable_vdos(PORT0).await, Err(Error::Pd(PdError::Serialize)));
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_mode_summary() {
+        let mut dp_status = [0u8; registers::dp_status::LEN];
+        dp_status[0] = 0x02;
+        let mut intel_vid_status = registers::field_sets::IntelVidStatus::new_zero();
+        intel_vid_status.set_tbt_mode_active(true);
+        let mut usb_status = registers::field_sets::UsbStatus::new_zero();
+        usb_status.set_eudo_sop_sent_status(registers::EudoSopSentStatus::SuccessfulEnterUsb);
+
+        let transactions = [
+            create_register_read(PORT0_ADDR0, 0x58, dp_status),
+            create_register_read(PORT0_ADDR0, 0x59, intel_vid_status),
+            create_register_read(PORT0_ADDR0, 0x24, usb_status),
+            create_register_read(PORT0_ADDR0, 0x58, [0u8; registers::dp_status::LEN]),
+            create_register_read(PORT0_ADDR0, 0x59, registers::field_sets::IntelVidStatus::new_zero()),
+            create_register_read(PORT0_ADDR0, 0x24, registers::field_sets::UsbStatus::new_zero()),
+        ];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(
+            pd.get_mode_summary(PORT0).await,
+            Ok(summary::ModeSummary {
+                dp_active: true,
+                tbt_active: true,
+                usb4_active: true,
+            })
+        );
+        assert_eq!(pd.get_mode_summary(PORT0).await, Ok(summary::ModeSummary::default()));
+        assert_eq!(pd.get_mode_summary(PORT1).await, Err(Error::Pd(PdError::InvalidPort)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_system_power_state() {
+        let mut s0ix = registers::field_sets::SxAppConfig::new();
+        s0ix.set_sleep_state(registers::SystemPowerState::S0Ix);
+        let s0 = registers::field_sets::SxAppConfig::new();
+
+        let transactions = [
+            create_register_write(PORT0_ADDR0, 0x20, s0ix),
+            create_register_write(PORT1_ADDR0, 0x20, s0ix),
+            create_register_read(PORT0_ADDR0, 0x20, s0ix),
+            create_register_write(PORT0_ADDR0, 0x20, s0),
+            create_register_write(PORT1_ADDR0, 0x20, s0),
+            create_register_read(PORT0_ADDR0, 0x20, s0),
+        ];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66994(Mock::new(&transactions), Default::default(), crate::ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(
+            pd.set_system_power_state(registers::SystemPowerState::S0Ix).await,
+            Ok(())
+        );
+        assert_eq!(pd.get_system_power_state().await, Ok(registers::SystemPowerState::S0Ix));
+        assert_eq!(pd.set_system_power_state(registers::SystemPowerState::S0).await, Ok(()));
+        assert_eq!(pd.get_system_power_state().await, Ok(registers::SystemPowerState::S0));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_new_detect() {
+        const TEST_FW_VERSION: u32 = 0x12345678;
+        let transactions = [
+            create_register_read(PORT0_ADDR0, 0x0F, TEST_FW_VERSION.to_le_bytes()),
+            create_register_read(PORT1_ADDR0, 0x0F, TEST_FW_VERSION.to_le_bytes()).with_error(
+                embedded_hal::i2c::ErrorKind::NoAcknowledge(embedded_hal::i2c::NoAcknowledgeSource::Address),
+            ),
+        ];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_detect(Mock::new(&transactions), Default::default(), crate::ADDR0)
+                .await
+                .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        assert_eq!(pd.num_ports(), crate::TPS66993_NUM_PORTS);
+        assert_eq!(pd.lock_inner().await.num_ports(), crate::TPS66993_NUM_PORTS);
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_abort_command() {
+        let mut aborted = [0u8; registers::REG_DATA1_LEN];
+        aborted[0] = ReturnValue::Abort as u8;
+
+        let transactions = [
+            // Explicit abort
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Abrt as u32).to_le_bytes()),
+            // Timed out, still in progress so it's aborted before reading the result
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Aneg as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x08, (Command::Aneg as u32).to_le_bytes()),
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Abrt as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x08, (Command::Success as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x09, aborted),
+        ];
+
+        let config = controller::Config {
+            abort_on_timeout: true,
+            ..Default::default()
+        };
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER
+            .init(controller::Controller::new_tps66993(Mock::new(&transactions), config, PORT0_ADDR0).unwrap());
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        let (result, _) = tokio::join!(pd.abort_command(PORT0), complete_command(controller, PORT0));
+        assert_eq!(result, Ok(()));
+
+        let timeout = Duration::from_millis(10);
+        let (result, _) = tokio::join!(
+            pd.execute_command_with_timeout(PORT0, Command::Aneg, None, None, timeout),
+            async {
+                Timer::after(timeout + Duration::from_millis(5)).await;
+                complete_command(controller, PORT0).await;
+            }
+        );
+        assert_eq!(result, Err(Error::Pd(PdError::Timeout)));
+
+        assert_eq!(pd.abort_command(PORT1).await, Err(Error::Pd(PdError::InvalidPort)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_faults() {
+        // PA int VBUS switch disabled due to a fault (bits 8:6) and overcurrent (bit 28)
+        const POWER_PATH_SOURCE_OC: [u8; 5] = [0x40, 0x00, 0x00, 0x10, 0x00];
+
+        let transactions = [
+            create_register_read(PORT0_ADDR0, 0x26, POWER_PATH_SOURCE_OC),
+            create_register_read(PORT0_ADDR0, 0x26, [0u8; 5]),
+        ];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        let faults = pd.get_faults(PORT0).await.unwrap();
+        assert!(faults.vbus_overcurrent);
+        assert!(faults.source_switch_fault);
+        assert!(!faults.sink_switch_fault);
+        assert!(!pd.get_faults(PORT0).await.unwrap().any());
+        assert_eq!(pd.get_faults(PORT1).await, Err(Error::Pd(PdError::InvalidPort)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_connection_state() {
+        let mut status = registers::field_sets::Status::new_zero();
+        status.set_plug_present(true);
+        status.set_plug_orientation(true);
+        let mut pd_status = registers::field_sets::PdStatus::new_zero();
+        pd_status.set_is_source(true);
+
+        let transactions = [
+            create_register_read(PORT0_ADDR0, 0x1A, status),
+            create_register_read(PORT0_ADDR0, 0x40, pd_status),
+        ];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(
+            pd.get_connection_state(PORT0).await,
+            Ok(summary::ConnectionState {
+                connected: true,
+                orientation: embedded_usb_pd::PlugOrientation::CC2,
+                power_role: PowerRole::Source,
+                data_role: DataRole::Ufp,
+            })
+        );
+        assert_eq!(
+            pd.get_connection_state(PORT1).await,
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_with_timeout() {
+        let mut transactions = Vec::new();
+        transactions.extend(command_transactions(Command::Aneg, ReturnValue::Success));
+        // Timed out, the result read afterwards reports a rejection
+        transactions.extend(command_transactions(Command::Aneg, ReturnValue::Rejected));
+        // Timed out without a definite error
+        transactions.extend(command_transactions(Command::Aneg, ReturnValue::Success));
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        // Completes after the default timeout would have expired
+        let timeout = Command::Aneg.timeout() * 2;
+        let (result, _) = tokio::join!(
+            pd.execute_command_with_timeout(PORT0, Command::Aneg, None, None, timeout),
+            async {
+                Timer::after(Command::Aneg.timeout() + Duration::from_millis(10)).await;
+                complete_command(controller, PORT0).await;
+            }
+        );
+        assert_eq!(result, Ok(ReturnValue::Success));
+
+        let result = pd
+            .execute_command_with_timeout(PORT0, Command::Aneg, None, None, Duration::from_millis(10))
+            .await;
+        assert_eq!(result, Err(Error::Pd(PdError::Rejected)));
+
+        let result = pd
+            .execute_command_with_timeout(PORT0, Command::Aneg, None, None, Duration::from_millis(10))
+            .await;
+        assert_eq!(result, Err(Error::Pd(PdError::Timeout)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_execute_raw_command() {
+        const FOURCC: [u8; 4] = *b"XYZw";
+
+        let mut output = [0u8; registers::REG_DATA1_LEN];
+        output[0] = ReturnValue::Success as u8;
+        output[1..3].copy_from_slice(&[0xAB, 0xCD]);
+
+        let transactions = [
+            create_register_write(PORT0_ADDR0, registers::REG_DATA1, [0x12]),
+            create_register_write(PORT0_ADDR0, 0x08, FOURCC),
+            create_register_read(PORT0_ADDR0, 0x08, (Command::Success as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x09, output),
+            // Firmware that doesn't support the command
+            create_register_write(PORT0_ADDR0, 0x08, FOURCC),
+            create_register_read(PORT0_ADDR0, 0x08, (Command::Invalid as u32).to_le_bytes()),
+        ];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        let mut outdata = [0u8; 2];
+        let (result, _) = tokio::join!(
+            pd.execute_raw_command(
+                PORT0,
+                FOURCC,
+                Some(&[0x12]),
+                Some(&mut outdata),
+                Duration::from_millis(100)
+            ),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Ok(ReturnValue::Success));
+        assert_eq!(outdata, [0xAB, 0xCD]);
+
+        let (result, _) = tokio::join!(
+            pd.execute_raw_command(PORT0, FOURCC, None, None, Duration::from_millis(100)),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Err(Error::Pd(PdError::UnrecognizedCommand)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_connection_snapshot() {
+        let mut status = registers::field_sets::Status::new_zero();
+        status.set_plug_present(true);
+        let pd_status = [0x02, 0x00, 0x00, 0x00];
+        let mut active_pdo = [0u8; 6];
+        active_pdo[..4].copy_from_slice(&TEST_SRC_PDO_FIXED_5V3A_RAW.to_le_bytes());
+
+        let transactions = [
+            create_register_read(PORT0_ADDR0, 0x1A, status),
+            create_register_read(PORT0_ADDR0, 0x40, pd_status),
+            create_register_read(PORT0_ADDR0, 0x34, active_pdo),
+        ];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(
+            pd.get_connection_snapshot(PORT0).await,
+            Ok(dump::ConnectionSnapshot {
+                status,
+                pd_status: pd_status.into(),
+                active_pdo_contract: active_pdo.into(),
+            })
+        );
+        assert_eq!(
+            pd.get_connection_snapshot(PORT1).await,
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_dump_registers() {
+        let mut status = [0u8; 5];
+        status[0] = 0x01;
+        let pd_status = [0x02, 0x00, 0x00, 0x00];
+        let port_control = 0x0000_0000_0040_0000u64;
+        let power_path_status = [0x03, 0x00, 0x00, 0x00, 0x00];
+        let mut active_pdo = [0u8; 6];
+        active_pdo[..4].copy_from_slice(&TEST_SRC_PDO_FIXED_5V3A_RAW.to_le_bytes());
+        let mut active_rdo = [0u8; 16];
+        active_rdo[3] = 0x10;
+        let mut event = IntEventBus1::new_zero();
+        event.set_plug_event(true);
+        let mut boot_flags = [0u8; registers::boot_flags::LEN];
+        boot_flags[0] = 0x05;
+
+        let transactions = [
+            create_register_read(PORT0_ADDR0, 0x1A, status),
+            create_register_read(PORT0_ADDR0, 0x40, pd_status),
+            create_register_read(PORT0_ADDR0, 0x29, port_control.to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x26, power_path_status),
+            create_register_read(PORT0_ADDR0, 0x34, active_pdo),
+            create_register_read(PORT0_ADDR0, 0x35, active_rdo),
+            // Only read, not cleared
+            create_register_read(PORT0_ADDR0, 0x14, event),
+            create_register_read(PORT0_ADDR0, 0x03, Mode::App0),
+            create_register_read(PORT0_ADDR0, 0x0F, 0x0001_0203u32.to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x2D, boot_flags),
+        ];
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        let port = pd.dump_port_registers(PORT0).await.unwrap();
+        assert_eq!(port.status, status.into());
+        assert_eq!(port.pd_status, pd_status.into());
+        assert_eq!(port.port_control, port_control.to_le_bytes().into());
+        assert_eq!(port.power_path_status, power_path_status.into());
+        assert_eq!(port.active_pdo_contract, active_pdo.into());
+        assert_eq!(port.active_rdo_contract, active_rdo.into());
+        assert_eq!(port.int_event_bus_1, event);
+
+        let controller_dump = pd.dump_controller_registers().await.unwrap();
+        assert_eq!(controller_dump.mode, Mode::App0);
+        assert_eq!(controller_dump.fw_version, 0x0001_0203);
+        assert_eq!(controller_dump.boot_flags.boot_stage(), 0x05);
+
+        assert_eq!(
+            pd.dump_port_registers(PORT1).await.unwrap_err(),
+            Error::Pd(PdError::InvalidPort)
+        );
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_retimer_force_pwr_settle() {
+        const SETTLE: Duration = Duration::from_millis(20);
+
+        let mut transactions = Vec::new();
+        transactions.extend(command_transactions_with_input(
+            Command::Trig,
+            [trig::Edge::Rising as u8, trig::Cmd::RetimerForcePwr as u8],
+            ReturnValue::Success,
+        ));
+        transactions.extend(command_transactions_with_input(
+            Command::Trig,
+            [trig::Edge::Falling as u8, trig::Cmd::RetimerForcePwr as u8],
+            ReturnValue::Success,
+        ));
+
+        let config = controller::Config {
+            retimer_power_settle: SETTLE,
+            ..Default::default()
+        };
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER
+            .init(controller::Controller::new_tps66993(Mock::new(&transactions), config, PORT0_ADDR0).unwrap());
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+        let controller = pd.controller;
+
+        // Configured settle time
+        let start = embassy_time::Instant::now();
+        let (result, _) = tokio::join!(pd.retimer_force_pwr(PORT0, true), complete_command(controller, PORT0));
+        assert_eq!(result, Ok(()));
+        assert!(start.elapsed() >= SETTLE);
+
+        // Explicit settle time overrides the configured one
+        let (result, _) = tokio::join!(
+            pd.retimer_force_pwr_with_settle(PORT0, false, Duration::from_ticks(0)),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Ok(()));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    /// Complete the pending command on port 0 then report `flags` through the interrupt signal
+    async fn complete_command_with_events(
+        controller: &controller::Controller<NoopRawMutex, Mock>,
+        flags: [IntEventBus1; MAX_SUPPORTED_PORTS],
+    ) {
+        complete_command(controller, PORT0).await;
+        Timer::after_millis(1).await;
+        controller.signal_interrupts(flags);
+    }
+
+    #[tokio::test]
+    async fn test_send_hard_reset() {
+        let mut hard_reset = IntEventBus1::new_zero();
+        hard_reset.set_hard_reset(true);
+        let mut plug_event = IntEventBus1::new_zero();
+        plug_event.set_plug_event(true);
+        let no_events = [IntEventBus1::new_zero(); MAX_SUPPORTED_PORTS];
+
+        let mut transactions = Vec::new();
+        transactions.extend(command_transactions(Command::HRST, ReturnValue::Success));
+        transactions.extend(command_transactions_with_input(
+            Command::DISC,
+            [ERROR_RECOVERY_DISCONNECT_TIME_S],
+            ReturnValue::Success,
+        ));
+        transactions.extend(command_transactions(Command::HRST, ReturnValue::Success));
+        transactions.extend(command_transactions(Command::HRST, ReturnValue::Success));
+        transactions.extend(command_transactions(Command::HRST, ReturnValue::Rejected));
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66994(Mock::new(&transactions), Default::default(), crate::ADDR0).unwrap(),
+        );
+        let (mut pd, _processor, mut receiver) = controller.make_parts();
+        let controller = pd.controller;
+        assert_eq!(controller.pending_interrupts(), no_events);
+
+        // Other events on the port are left pending
+        let (result, _) = tokio::join!(
+            pd.send_hard_reset(PORT0, &mut receiver),
+            complete_command_with_events(controller, [hard_reset | plug_event, IntEventBus1::new_zero()])
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(controller.pending_interrupts(), [plug_event, IntEventBus1::new_zero()]);
+        controller.take_interrupts([IntEventBus1::all(); MAX_SUPPORTED_PORTS]);
+
+        // The plug event is only reported once the command has completed
+        let (result, _) = tokio::join!(
+            pd.trigger_error_recovery(PORT0, &mut receiver),
+            complete_command_with_events(controller, [plug_event, IntEventBus1::new_zero()])
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(controller.pending_interrupts(), no_events);
+
+        // A stale hard reset from before the command doesn't complete the wait
+        controller.signal_interrupts([hard_reset, IntEventBus1::new_zero()]);
+        let (result, _) = tokio::join!(
+            pd.send_hard_reset(PORT0, &mut receiver),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Err(Error::Pd(PdError::Timeout)));
+        assert_eq!(controller.pending_interrupts(), no_events);
+
+        // A hard reset on the other port doesn't complete the wait and isn't consumed
+        let (result, _) = tokio::join!(
+            pd.send_hard_reset(PORT0, &mut receiver),
+            complete_command_with_events(controller, [IntEventBus1::new_zero(), hard_reset])
+        );
+        assert_eq!(result, Err(Error::Pd(PdError::Timeout)));
+        assert_eq!(controller.pending_interrupts(), [IntEventBus1::new_zero(), hard_reset]);
+
+        let (result, _) = tokio::join!(
+            pd.send_hard_reset(PORT0, &mut receiver),
+            complete_command(controller, PORT0)
+        );
+        assert_eq!(result, Err(Error::Pd(PdError::Rejected)));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_contract_power() {
+        // VBUS at the contract voltage, bits 20-21
+        const STATUS_VBUS_NORMAL: [u8; 5] = [0x00, 0x00, 0x20, 0x00, 0x00];
+        // VCONN on CC1, bits 0-1
+        const POWER_PATH_VCONN_CC1: [u8; 5] = [0x02, 0x00, 0x00, 0x00, 0x00];
+        // SPR PPS APDO, 3.3-11V 3A, requesting 9V at 2A
+        const PPS_PDO: u32 = 0xc000_0000 | (110 << 17) | (33 << 8) | 60;
+        const PPS_RDO: u32 = (1 << 28) | (450 << 9) | 40;
+
+        let pdo_bytes = |raw: u32| {
+            let mut bytes = [0u8; 6];
+            bytes[..4].copy_from_slice(&raw.to_le_bytes());
+            bytes
+        };
+        let mut pps_rdo = [0u8; 16];
+        pps_rdo[..4].copy_from_slice(&PPS_RDO.to_le_bytes());
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&[
+                    // Fixed contract
+                    create_register_read(PORT0_ADDR0, 0x1A, STATUS_VBUS_NORMAL),
+                    create_register_read(PORT0_ADDR0, 0x26, POWER_PATH_VCONN_CC1),
+                    create_register_read(PORT0_ADDR0, 0x34, pdo_bytes(TEST_SRC_PDO_FIXED_9V3000MA_RAW)),
+                    create_register_read(PORT0_ADDR0, 0x35, [0u8; 16]),
+                    // PPS contract
+                    create_register_read(PORT0_ADDR0, 0x1A, STATUS_VBUS_NORMAL),
+                    create_register_read(PORT0_ADDR0, 0x26, [0u8; 5]),
+                    create_register_read(PORT0_ADDR0, 0x34, pdo_bytes(PPS_PDO)),
+                    create_register_read(PORT0_ADDR0, 0x35, pps_rdo),
+                    // No contract
+                    create_register_read(PORT0_ADDR0, 0x1A, [0u8; 5]),
+                    create_register_read(PORT0_ADDR0, 0x26, [0u8; 5]),
+                    create_register_read(PORT0_ADDR0, 0x34, [0u8; 6]),
+                    create_register_read(PORT0_ADDR0, 0x35, [0u8; 16]),
+                ]),
+                Default::default(),
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(
+            pd.get_contract_power(PORT0).await,
+            Ok(ContractPower {
+                vbus: registers::VbusMode::Normal,
+                voltage_mv: Some(9000),
+                current_ma: Some(3000),
+                vconn: registers::PpVconnSw::Cc1,
+            })
+        );
+        assert_eq!(
+            pd.get_contract_power(PORT0).await,
+            Ok(ContractPower {
+                vbus: registers::VbusMode::Normal,
+                voltage_mv: Some(9000),
+                current_ma: Some(2000),
+                vconn: registers::PpVconnSw::Disabled,
+            })
+        );
+        assert_eq!(
+            pd.get_contract_power(PORT0).await,
+            Ok(ContractPower {
+                vbus: registers::VbusMode::AtVsafe0,
+                voltage_mv: None,
+                current_ma: None,
+                vconn: registers::PpVconnSw::Disabled,
+            })
+        );
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_thermal_status() {
+        // Error recovery details are bits 22-27
+        let over_temperature = 0x1u32 << 22;
+        let other_recovery = 0x2u32 << 22;
+
+        static CONTROLLER: StaticCell<controller::Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            controller::Controller::new_tps66993(
+                Mock::new(&[
+                    create_register_read(PORT0_ADDR0, 0x40, over_temperature.to_le_bytes()),
+                    create_register_read(PORT0_ADDR0, 0x40, other_recovery.to_le_bytes()),
+                ]),
+                Default::default(),
+                PORT0_ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(
+            pd.get_thermal_status(PORT0).await,
+            Ok(ThermalStatus { over_temperature: true })
+        );
+        assert_eq!(
+            pd.get_thermal_status(PORT0).await,
+            Ok(ThermalStatus {
+                over_temperature: false
+            })
+        );
+        pd.lock_inner().await.bus.done();
+    }
+
+    #[test]
+    fn test_validate_ports() {
+        assert_eq!(validate_ports(&[PORT0_ADDR0, 0], 1), Ok(()));
+        assert_eq!(validate_ports(&crate::ADDR0, 2), Ok(()));
+
+        assert_eq!(validate_ports(&crate::ADDR0, 0), Err(PdError::InvalidParams));
+        assert_eq!(
+            validate_ports(&crate::ADDR0, MAX_SUPPORTED_PORTS + 1),
+            Err(PdError::InvalidParams)
+        );
+
+        // Zero address on an active port
+        assert_eq!(validate_ports(&[0, 0], 1), Err(PdError::InvalidParams));
+        assert_eq!(validate_ports(&[PORT0_ADDR0, 0], 2), Err(PdError::InvalidParams));
+    }
+
+    #[test]
+    fn test_fixed_contract() {
+        let data = fixed_contract(TEST_SRC_PDO_FIXED_5V3A_RAW).unwrap();
+        assert_eq!(u32::from(data.voltage_mv), 5000);
+        assert_eq!(u32::from(data.current_ma), 3000);
+
+        let data = fixed_contract(TEST_SRC_PDO_FIXED_9V3000MA_RAW).unwrap();
+        assert_eq!(u32::from(data.voltage_mv), 9000);
+        assert_eq!(u32::from(data.current_ma), 3000);
+
+        // No explicit contract
+        assert!(fixed_contract(0).is_none());
+    }
+
+    #[test]
+    fn test_pps_contract() {
+        // SPR PPS APDO, 3.3-11V 3A
+        const PPS_PDO: u32 = 0xc000_0000 | (110 << 17) | (33 << 8) | 60;
+        // 9V at 2A
+        const PPS_RDO: u32 = (1 << 28) | (450 << 9) | 40;
+
+        assert_eq!(
+            pps_contract(PPS_PDO, PPS_RDO),
+            Some(PpsStatus {
+                output_voltage_mv: 9000,
+                output_current_ma: 2000,
+            })
+        );
+
+        // Fixed supply contract
+        assert_eq!(pps_contract(TEST_SRC_PDO_FIXED_5V3A_RAW, PPS_RDO), None);
+        // EPR AVS APDO
+        assert_eq!(pps_contract(0xd000_0000, PPS_RDO), None);
+        // No explicit contract
+        assert_eq!(pps_contract(0, 0), None);
+    }
+
+    #[test]
+    fn test_source_path_off() {
+        use registers::PpIntVbusSw;
+        use registers::field_sets::PowerPathStatus;
+
+        let mut status = PowerPathStatus::new_zero();
+        assert_eq!(source_path_off(&status, PORT0), Ok(true));
+
+        status.set_pa_int_vbus_sw(PpIntVbusSw::EnabledOutput);
+        assert_eq!(source_path_off(&status, PORT0), Ok(false));
+        assert_eq!(source_path_off(&status, PORT1), Ok(true));
+
+        status.set_pb_int_vbus_sw(PpIntVbusSw::DisabledFault);
+        assert_eq!(source_path_off(&status, PORT1), Ok(true));
+
+        assert_eq!(source_path_off(&status, LocalPortId(2)), Err(PdError::InvalidPort));
+    }
+
+    #[test]
+    fn test_power_path_stable() {
+        use registers::field_sets::PowerPathStatus;
+        use registers::{PpExtVbusSw, PpIntVbusSw};
+
+        let mut status = PowerPathStatus::new_zero();
+        assert_eq!(power_path_stable(&status, PORT0, PowerPath::Source), Ok(false));
+        assert_eq!(power_path_stable(&status, PORT1, PowerPath::Sink), Ok(false));
+
+        // Enabled, but still current-limiting
+        status.set_pa_int_vbus_sw(PpIntVbusSw::EnabledOutput);
+        status.set_pa_int_vbus_oc(true);
+        assert_eq!(power_path_stable(&status, PORT0, PowerPath::Source), Ok(false));
+
+        status.set_pa_int_vbus_oc(false);
+        assert_eq!(power_path_stable(&status, PORT0, PowerPath::Source), Ok(true));
+        // Other port and path are unaffected
+        assert_eq!(power_path_stable(&status, PORT1, PowerPath::Source), Ok(false));
+        assert_eq!(power_path_stable(&status, PORT0, PowerPath::Sink), Ok(false));
+
+        status.set_pb_ext_vbus_sw(PpExtVbusSw::EnabledInput);
+        assert_eq!(power_path_stable(&status, PORT1, PowerPath::Sink), Ok(true));
+
+        status.set_pb_int_vbus_sw(PpIntVbusSw::DisabledFault);
+        assert_eq!(
+            power_path_stable(&status, PORT1, PowerPath::Source),
+            Err(PdError::Failed)
+        );
+
+        assert_eq!(
+            power_path_stable(&status, LocalPortId(2), PowerPath::Source),
+            Err(PdError::InvalidPort)
+        );
+    }
+}
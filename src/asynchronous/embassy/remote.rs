@@ -0,0 +1,150 @@
+//! Serializable requests and responses for driving a controller remotely, see [`super::Tps6699x::handle_request`].
+//!
+//! These types only define the message contents, framing (e.g. postcard with COBS over a UART) is left to the
+//! transport. Register values are returned as raw bytes and can be decoded on the host with the matching
+//! [`crate::registers::field_sets`] `From<[u8; N]>` implementations.
+use embedded_usb_pd::{Error, PdError};
+use serde::{Deserialize, Serialize};
+
+use super::summary::PortSummary;
+use crate::Mode;
+
+/// Remote request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Request {
+    /// Get the current controller mode
+    GetMode,
+    /// Get the firmware version
+    GetFwVersion,
+    /// Get the customer use value
+    GetCustomerUse,
+    /// Get the raw port status register
+    GetPortStatus { port: u8 },
+    /// Get the raw PD status register
+    GetPdStatus { port: u8 },
+    /// Get the raw power path status register
+    GetPowerPathStatus { port: u8 },
+    /// Get the raw active PDO contract register
+    GetActivePdoContract { port: u8 },
+    /// Get the raw active RDO contract register
+    GetActiveRdoContract { port: u8 },
+    /// Get a summary of the port state
+    GetPortSummary { port: u8 },
+}
+
+/// Remote error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RemoteError {
+    /// Bus error
+    Bus,
+    /// Invalid port
+    InvalidPort,
+    /// Invalid parameters
+    InvalidParams,
+    /// Controller busy
+    Busy,
+    /// Timeout
+    Timeout,
+    /// Rejected by the controller
+    Rejected,
+    /// Other failure
+    Failed,
+}
+
+impl<BE> From<Error<BE>> for RemoteError {
+    fn from(value: Error<BE>) -> Self {
+        match value {
+            Error::Bus(_) => RemoteError::Bus,
+            Error::Pd(PdError::InvalidPort) => RemoteError::InvalidPort,
+            Error::Pd(PdError::InvalidParams) => RemoteError::InvalidParams,
+            Error::Pd(PdError::Busy) => RemoteError::Busy,
+            Error::Pd(PdError::Timeout) => RemoteError::Timeout,
+            Error::Pd(PdError::Rejected) => RemoteError::Rejected,
+            Error::Pd(_) => RemoteError::Failed,
+        }
+    }
+}
+
+/// Remote response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Response {
+    /// Response to [`Request::GetMode`]
+    Mode(Mode),
+    /// Response to [`Request::GetFwVersion`]
+    FwVersion(u32),
+    /// Response to [`Request::GetCustomerUse`]
+    CustomerUse(u64),
+    /// Response to [`Request::GetPortStatus`]
+    PortStatus([u8; 5]),
+    /// Response to [`Request::GetPdStatus`]
+    PdStatus([u8; 4]),
+    /// Response to [`Request::GetPowerPathStatus`]
+    PowerPathStatus([u8; 5]),
+    /// Response to [`Request::GetActivePdoContract`]
+    ActivePdoContract([u8; 6]),
+    /// Response to [`Request::GetActiveRdoContract`]
+    ActiveRdoContract([u8; 16]),
+    /// Response to [`Request::GetPortSummary`]
+    PortSummary(PortSummary),
+    /// The request failed
+    Error(RemoteError),
+}
+
+#[cfg(test)]
+mod test {
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embedded_hal_mock::eh1::i2c::Mock;
+    use static_cell::StaticCell;
+
+    use super::*;
+    use crate::ADDR0;
+    use crate::asynchronous::embassy::controller::Controller;
+    use crate::test::{PORT0_ADDR0, create_register_read};
+
+    /// Test firmware version, no particular meaning to this value
+    const TEST_FW_VERSION: u32 = 0x12345678;
+
+    #[test]
+    fn test_remote_error_from() {
+        assert_eq!(RemoteError::from(Error::<()>::Bus(())), RemoteError::Bus);
+        assert_eq!(
+            RemoteError::from(Error::<()>::Pd(PdError::InvalidPort)),
+            RemoteError::InvalidPort
+        );
+        assert_eq!(
+            RemoteError::from(Error::<()>::Pd(PdError::Timeout)),
+            RemoteError::Timeout
+        );
+        assert_eq!(
+            RemoteError::from(Error::<()>::Pd(PdError::Serialize)),
+            RemoteError::Failed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_request() {
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER.init(
+            Controller::new_tps66994(
+                Mock::new(&[create_register_read(PORT0_ADDR0, 0x0F, TEST_FW_VERSION.to_le_bytes())]),
+                Default::default(),
+                ADDR0,
+            )
+            .unwrap(),
+        );
+        let (mut pd, _processor, _receiver) = controller.make_parts();
+
+        assert_eq!(
+            pd.handle_request(Request::GetFwVersion).await,
+            Response::FwVersion(TEST_FW_VERSION)
+        );
+        assert_eq!(
+            pd.handle_request(Request::GetPortStatus { port: 2 }).await,
+            Response::Error(RemoteError::InvalidPort)
+        );
+        pd.lock_inner().await.bus.done();
+    }
+}
@@ -5,6 +5,7 @@ use embedded_usb_pd::pdo::{self, Common, sink, source};
 use crate::registers::rx_caps::{NUM_EPR_PDOS, NUM_SPR_PDOS};
 
 /// Higher-level wrapper for the rx src/sink caps register.
+#[derive(Clone)]
 pub struct RxCaps<T: Common> {
     pub(super) spr: heapless::Vec<T, NUM_SPR_PDOS>,
     pub(super) epr: heapless::Vec<T, NUM_EPR_PDOS>,
@@ -0,0 +1,396 @@
+//! Human-readable port summary, see [`super::Tps6699x::summarize_port`].
+
+use core::fmt;
+
+use embedded_usb_pd::{DataRole, LocalPortId, PdError, PlugOrientation, PowerRole};
+
+use crate::registers;
+use crate::registers::dp_status::DpStatus;
+use crate::registers::field_sets::{IntelVidStatus, PdStatus, PowerPathStatus, Status, UsbStatus};
+use crate::registers::{PpExtVbusSw, PpIntVbusSw, PpVconnSw};
+
+/// Power contract summary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContractSummary {
+    /// Contract voltage in mV
+    pub voltage_mv: u32,
+    /// Contract current in mA
+    pub current_ma: u32,
+}
+
+/// One-line summary of a port's role, contract and alt-mode state.
+///
+/// Renders as e.g. `SNK @ 20V/5A, DFP, DP active`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PortSummary {
+    /// Plug is present
+    pub connected: bool,
+    /// Port is currently the power source
+    pub is_source: bool,
+    /// Port is currently the DFP
+    pub is_dfp: bool,
+    /// Fixed-supply explicit contract, if any
+    pub contract: Option<ContractSummary>,
+    /// DisplayPort alt-mode is active
+    pub dp: bool,
+    /// Thunderbolt alt-mode is active
+    pub tbt: bool,
+    /// USB4 is active
+    pub usb4: bool,
+}
+
+/// Active alt-modes and USB4 on a port, see [`super::Tps6699x::get_mode_summary`]
+///
+/// Renders as e.g. `DP, TBT`, or `None` if nothing is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModeSummary {
+    /// DisplayPort alt-mode is active
+    pub dp_active: bool,
+    /// Thunderbolt alt-mode is active
+    pub tbt_active: bool,
+    /// USB4 is active
+    pub usb4_active: bool,
+}
+
+impl ModeSummary {
+    /// Interpret the DP, Intel VID and USB status registers
+    pub fn from_status(dp_status: &DpStatus, intel_vid_status: &IntelVidStatus, usb_status: &UsbStatus) -> Self {
+        Self {
+            dp_active: dp_status.dp_mode_active() != 0,
+            tbt_active: intel_vid_status.tbt_mode_active(),
+            usb4_active: usb_status.eudo_sop_sent_status() == registers::EudoSopSentStatus::SuccessfulEnterUsb,
+        }
+    }
+
+    /// Returns true if no alt-mode or USB4 is active
+    pub fn is_none(&self) -> bool {
+        !(self.dp_active || self.tbt_active || self.usb4_active)
+    }
+}
+
+impl fmt::Display for ModeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_none() {
+            return write!(f, "None");
+        }
+
+        let mut separator = "";
+        for (active, name) in [
+            (self.dp_active, "DP"),
+            (self.tbt_active, "TBT"),
+            (self.usb4_active, "USB4"),
+        ] {
+            if active {
+                write!(f, "{}{}", separator, name)?;
+                separator = ", ";
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ModeSummary {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", defmt::Display2Format(self))
+    }
+}
+
+/// Connection state of a port, see [`super::Tps6699x::get_connection_state`]
+///
+/// The orientation and roles are only meaningful while [`Self::connected`] is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConnectionState {
+    /// Plug is present
+    pub connected: bool,
+    /// Which CC line the plug is oriented on
+    pub orientation: PlugOrientation,
+    /// Current power role
+    pub power_role: PowerRole,
+    /// Current data role
+    pub data_role: DataRole,
+}
+
+impl ConnectionState {
+    /// Interpret the status and PD status registers
+    pub fn from_status(status: &Status, pd_status: &PdStatus) -> Self {
+        Self {
+            connected: status.plug_present(),
+            orientation: if status.plug_orientation() {
+                PlugOrientation::CC2
+            } else {
+                PlugOrientation::CC1
+            },
+            power_role: if pd_status.is_source() {
+                PowerRole::Source
+            } else {
+                PowerRole::Sink
+            },
+            data_role: if status.data_role() {
+                DataRole::Dfp
+            } else {
+                DataRole::Ufp
+            },
+        }
+    }
+}
+
+/// Power path faults on a port, see [`super::Tps6699x::get_faults`]
+///
+/// These reflect the current state of the power path rather than latched events, a switch disabled by a fault stays
+/// reported until it's enabled again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FaultStatus {
+    /// The internal VBUS switch is current-limiting
+    pub vbus_overcurrent: bool,
+    /// The VCONN switch is current-limiting
+    pub vconn_overcurrent: bool,
+    /// The internal VBUS switch, used to source, was disabled due to a fault
+    pub source_switch_fault: bool,
+    /// The external VBUS switch, used to sink, was disabled due to a fault
+    pub sink_switch_fault: bool,
+    /// The VCONN switch was disabled due to a fault
+    pub vconn_switch_fault: bool,
+}
+
+impl FaultStatus {
+    /// Interpret the power path status register for `port`
+    pub fn from_power_path(status: &PowerPathStatus, port: LocalPortId) -> Result<Self, PdError> {
+        let (int_sw, ext_sw, vconn_sw, vbus_oc, vconn_oc) = match port.0 {
+            0 => (
+                status.pa_int_vbus_sw(),
+                status.pa_ext_vbus_sw(),
+                status.pa_vconn_sw(),
+                status.pa_int_vbus_oc(),
+                status.pa_vconn_oc(),
+            ),
+            1 => (
+                status.pb_int_vbus_sw(),
+                status.pb_ext_vbus_sw(),
+                status.pb_vconn_sw(),
+                status.pb_int_vbus_oc(),
+                status.pb_vconn_oc(),
+            ),
+            _ => return Err(PdError::InvalidPort),
+        };
+
+        Ok(Self {
+            vbus_overcurrent: vbus_oc,
+            vconn_overcurrent: vconn_oc,
+            source_switch_fault: int_sw == PpIntVbusSw::DisabledFault,
+            sink_switch_fault: ext_sw == PpExtVbusSw::DisabledFault,
+            vconn_switch_fault: vconn_sw == PpVconnSw::DisabledFault,
+        })
+    }
+
+    /// Returns true if any fault is present
+    pub fn any(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Write a milli-unit value in whole units, e.g. 1500 as `1.5`
+fn write_milli(f: &mut fmt::Formatter<'_>, value: u32) -> fmt::Result {
+    let whole = value / 1000;
+    let mut frac = value % 1000;
+    if frac == 0 {
+        return write!(f, "{}", whole);
+    }
+
+    let mut digits = 3;
+    while frac % 10 == 0 {
+        frac /= 10;
+        digits -= 1;
+    }
+    write!(f, "{}.{:0width$}", whole, frac, width = digits)
+}
+
+impl fmt::Display for PortSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.connected {
+            return write!(f, "Disconnected");
+        }
+
+        write!(f, "{}", if self.is_source { "SRC" } else { "SNK" })?;
+        if let Some(contract) = self.contract {
+            write!(f, " @ ")?;
+            write_milli(f, contract.voltage_mv)?;
+            write!(f, "V/")?;
+            write_milli(f, contract.current_ma)?;
+            write!(f, "A")?;
+        }
+        write!(f, ", {}", if self.is_dfp { "DFP" } else { "UFP" })?;
+
+        if self.dp {
+            write!(f, ", DP active")?;
+        }
+        if self.tbt {
+            write!(f, ", TBT active")?;
+        }
+        if self.usb4 {
+            write!(f, ", USB4 active")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PortSummary {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", defmt::Display2Format(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::format;
+
+    use super::*;
+
+    #[test]
+    fn test_port_summary_display() {
+        let summary = PortSummary {
+            connected: true,
+            is_source: false,
+            is_dfp: true,
+            contract: Some(ContractSummary {
+                voltage_mv: 20000,
+                current_ma: 5000,
+            }),
+            dp: true,
+            tbt: false,
+            usb4: false,
+        };
+        assert_eq!(format!("{}", summary), "SNK @ 20V/5A, DFP, DP active");
+
+        let summary = PortSummary {
+            connected: true,
+            is_source: true,
+            is_dfp: false,
+            contract: Some(ContractSummary {
+                voltage_mv: 5000,
+                current_ma: 1500,
+            }),
+            dp: false,
+            tbt: true,
+            usb4: true,
+        };
+        assert_eq!(format!("{}", summary), "SRC @ 5V/1.5A, UFP, TBT active, USB4 active");
+
+        let summary = PortSummary {
+            connected: true,
+            contract: Some(ContractSummary {
+                voltage_mv: 9250,
+                current_ma: 3050,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(format!("{}", summary), "SNK @ 9.25V/3.05A, UFP");
+
+        let summary = PortSummary {
+            connected: true,
+            ..Default::default()
+        };
+        assert_eq!(format!("{}", summary), "SNK, UFP");
+
+        assert_eq!(format!("{}", PortSummary::default()), "Disconnected");
+    }
+
+    #[test]
+    fn test_fault_status_from_power_path() {
+        let status = PowerPathStatus::new_zero();
+        let faults = FaultStatus::from_power_path(&status, LocalPortId(0)).unwrap();
+        assert_eq!(faults, FaultStatus::default());
+        assert!(!faults.any());
+
+        // PB VCONN switch fault (bits 3:2), PB int VBUS switch fault (bits 11:9),
+        // PA ext VBUS switch fault (bits 14:12) and PB int VBUS overcurrent (bit 29)
+        let status = PowerPathStatus::from([0x04, 0x12, 0x00, 0x20, 0x00]);
+        let faults = FaultStatus::from_power_path(&status, LocalPortId(1)).unwrap();
+        assert_eq!(
+            faults,
+            FaultStatus {
+                vbus_overcurrent: true,
+                vconn_overcurrent: false,
+                source_switch_fault: true,
+                sink_switch_fault: false,
+                vconn_switch_fault: true,
+            }
+        );
+        assert!(faults.any());
+
+        // Port A only has its external switch fault
+        let faults = FaultStatus::from_power_path(&status, LocalPortId(0)).unwrap();
+        assert_eq!(
+            faults,
+            FaultStatus {
+                sink_switch_fault: true,
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(
+            FaultStatus::from_power_path(&status, LocalPortId(2)),
+            Err(PdError::InvalidPort)
+        );
+    }
+
+    #[test]
+    fn test_connection_state_from_status() {
+        let state = ConnectionState::from_status(&Status::new_zero(), &PdStatus::new_zero());
+        assert_eq!(
+            state,
+            ConnectionState {
+                connected: false,
+                orientation: PlugOrientation::CC1,
+                power_role: PowerRole::Sink,
+                data_role: DataRole::Ufp,
+            }
+        );
+
+        let mut status = Status::new_zero();
+        status.set_plug_present(true);
+        status.set_plug_orientation(true);
+        status.set_data_role(true);
+        let mut pd_status = PdStatus::new_zero();
+        pd_status.set_is_source(true);
+        let state = ConnectionState::from_status(&status, &pd_status);
+        assert_eq!(
+            state,
+            ConnectionState {
+                connected: true,
+                orientation: PlugOrientation::CC2,
+                power_role: PowerRole::Source,
+                data_role: DataRole::Dfp,
+            }
+        );
+    }
+
+    #[test]
+    fn test_mode_summary_display() {
+        assert_eq!(format!("{}", ModeSummary::default()), "None");
+        assert!(ModeSummary::default().is_none());
+
+        let summary = ModeSummary {
+            dp_active: true,
+            tbt_active: false,
+            usb4_active: true,
+        };
+        assert!(!summary.is_none());
+        assert_eq!(format!("{}", summary), "DP, USB4");
+
+        let summary = ModeSummary {
+            tbt_active: true,
+            ..Default::default()
+        };
+        assert_eq!(format!("{}", summary), "TBT");
+    }
+}
@@ -1,56 +1,222 @@
+use core::convert::Infallible;
+use core::future::Future;
+
+use embassy_futures::join::join_array;
 use embassy_sync::blocking_mutex::raw::RawMutex;
-use embedded_hal::digital::InputPin;
+use embedded_hal::digital::{ErrorType, InputPin};
+use embedded_hal_async::delay::DelayNs;
 use embedded_hal_async::digital::Wait;
 use embedded_hal_async::i2c::I2c;
+use embedded_usb_pd::{Error, LocalPortId};
 
+use super::controller::Controller;
 use super::interrupt::InterruptProcessor;
+use crate::registers::field_sets::IntEventBus1;
 use crate::{error, trace, warn};
 
+/// Source of interrupt assertions for [`interrupt_task`]
+///
+/// Any pin implementing [`Wait`] and [`InputPin`] is a source, treating the active low INT line as the assertion.
+/// [`PollingSource`] can be used on boards where the INT line can't be awaited.
+pub trait InterruptSource {
+    /// Line passed to [`InterruptProcessor::process_interrupt`]
+    type Line: InputPin;
+    type Error;
+
+    /// Wait until the controller asserts an interrupt
+    fn wait_asserted(&mut self) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Returns true if the controller is still asserting an interrupt
+    fn is_asserted(&mut self) -> impl Future<Output = Result<bool, Self::Error>>;
+
+    /// Line used to stop processing early once interrupts are no longer asserted
+    fn line(&mut self) -> &mut Self::Line;
+}
+
+impl<INT: Wait + InputPin> InterruptSource for INT {
+    type Line = INT;
+    type Error = INT::Error;
+
+    async fn wait_asserted(&mut self) -> Result<(), Self::Error> {
+        self.wait_for_low().await
+    }
+
+    async fn is_asserted(&mut self) -> Result<bool, Self::Error> {
+        self.is_low()
+    }
+
+    fn line(&mut self) -> &mut Self::Line {
+        self
+    }
+}
+
+/// Line that always reads as asserted, so every port is checked when processing interrupts
+pub struct AssertedLine;
+
+impl ErrorType for AssertedLine {
+    type Error = Infallible;
+}
+
+impl InputPin for AssertedLine {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// Interrupt source that polls the interrupt event registers instead of waiting on the INT line
+///
+/// An interrupt is asserted while any port with interrupts enabled has a nonzero `IntEventBus1`. The registers are
+/// only read, clearing is still done by [`InterruptProcessor::process_interrupt`].
+pub struct PollingSource<'a, M: RawMutex, B: I2c, D: DelayNs> {
+    controller: &'a Controller<M, B>,
+    delay: D,
+    interval_ms: u32,
+    line: AssertedLine,
+}
+
+impl<'a, M: RawMutex, B: I2c, D: DelayNs> PollingSource<'a, M, B, D> {
+    /// Create a new polling source for the controller of `processor`, polling every `interval_ms`
+    pub fn new(processor: &InterruptProcessor<'a, M, B>, delay: D, interval_ms: u32) -> Self {
+        Self {
+            controller: processor.controller,
+            delay,
+            interval_ms,
+            line: AssertedLine,
+        }
+    }
+}
+
+impl<M: RawMutex, B: I2c, D: DelayNs> InterruptSource for PollingSource<'_, M, B, D> {
+    type Line = AssertedLine;
+    type Error = Error<B::Error>;
+
+    async fn wait_asserted(&mut self) -> Result<(), Self::Error> {
+        loop {
+            match self.is_asserted().await {
+                Ok(true) => return Ok(()),
+                Ok(false) => self.delay.delay_ms(self.interval_ms).await,
+                Err(e) => {
+                    // Wait before returning so a persistent bus error doesn't turn into a busy loop
+                    self.delay.delay_ms(self.interval_ms).await;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    async fn is_asserted(&mut self) -> Result<bool, Self::Error> {
+        let interrupts_enabled = self.controller.interrupts_enabled();
+        let mut inner = self.controller.inner.lock().await;
+        for (port, enabled) in interrupts_enabled.iter().enumerate().take(self.controller.num_ports) {
+            if *enabled && inner.get_interrupt(LocalPortId(port as u8)).await? != IntEventBus1::new_zero() {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn line(&mut self) -> &mut Self::Line {
+        &mut self.line
+    }
+}
+
 /// Task to process all given interrupts
-pub async fn interrupt_task<M: RawMutex, B: I2c, INT: Wait + InputPin>(
-    int: &mut INT,
+///
+/// Controllers are processed one after another, stopping early once the interrupt line is no longer asserted. This is
+/// always safe, including when controllers share a bus through a `RefCell`, see [`interrupt_task_concurrent`] for
+/// controllers on independent buses.
+pub async fn interrupt_task<M: RawMutex, B: I2c, S: InterruptSource>(
+    source: &mut S,
     interrupts: &mut [&mut InterruptProcessor<'_, M, B>],
 ) {
     let mut retry_strategy = retry_strategy::ExponentialBackoff::default();
     loop {
-        if int.wait_for_low().await.is_err() {
+        if source.wait_asserted().await.is_err() {
             error!("Error waiting for interrupt");
             continue;
         }
 
         for interrupt in &mut *interrupts {
-            if interrupt.process_interrupt(int).await.is_err() {
+            if interrupt.process_interrupt(source.line()).await.is_err() {
                 warn!("Error processing interrupt");
             }
 
-            if let Ok(true) = int.is_high() {
+            if let Ok(true) = source.line().is_high() {
                 // Done handling pending interrupts
                 break;
             }
         }
 
-        // If interrupt line is still asserted, retry following backoff strategy
-        match int.is_low() {
-            Ok(true) => {
-                match retry_strategy.next() {
-                    None => {
-                        trace!("Interrupt line still asserted, retrying immediately");
-                    }
-                    Some(backoff) => {
-                        // If this was not the first try, back off
-                        trace!("Interrupt line still asserted, backing off for {:?}", backoff);
-                        embassy_time::Timer::after(backoff).await;
-                    }
-                }
-            }
-            Ok(false) => {
-                // Interrupt line is no longer asserted, reset backoff
-                retry_strategy = Default::default();
+        backoff_while_asserted(source, &mut retry_strategy).await;
+    }
+}
+
+/// Task to process all given interrupts, processing each controller concurrently
+///
+/// Reduces latency when several controllers share an interrupt line but sit on independent I2C buses, their clear
+/// sequences overlap instead of running back to back. Every port of every controller is checked since the shared
+/// line can't tell which controller is still asserting it.
+///
+/// Controllers sharing a bus through an async mutex are still correct but gain nothing as their transfers are
+/// serialized by the mutex. Controllers sharing a bus through a `RefCell` must use [`interrupt_task`] instead, a
+/// transfer from one controller can be in progress when another tries to borrow the bus.
+pub async fn interrupt_task_concurrent<M: RawMutex, B: I2c, S: InterruptSource, const N: usize>(
+    source: &mut S,
+    interrupts: &mut [&mut InterruptProcessor<'_, M, B>; N],
+) {
+    let mut retry_strategy = retry_strategy::ExponentialBackoff::default();
+    loop {
+        if source.wait_asserted().await.is_err() {
+            error!("Error waiting for interrupt");
+            continue;
+        }
+
+        let results = join_array(interrupts.each_mut().map(|interrupt| async move {
+            // The line can't be shared between concurrent futures
+            interrupt.process_interrupt(&mut AssertedLine).await
+        }))
+        .await;
+
+        for result in results {
+            if result.is_err() {
+                warn!("Error processing interrupt");
             }
-            Err(_) => {
-                error!("Error post-checking interrupt line");
+        }
+
+        backoff_while_asserted(source, &mut retry_strategy).await;
+    }
+}
+
+/// If the interrupt line is still asserted after processing, back off following `retry_strategy`
+async fn backoff_while_asserted<S: InterruptSource>(
+    source: &mut S,
+    retry_strategy: &mut retry_strategy::ExponentialBackoff,
+) {
+    match source.is_asserted().await {
+        Ok(true) => {
+            match retry_strategy.next() {
+                None => {
+                    trace!("Interrupt line still asserted, retrying immediately");
+                }
+                Some(backoff) => {
+                    // If this was not the first try, back off
+                    trace!("Interrupt line still asserted, backing off for {:?}", backoff);
+                    embassy_time::Timer::after(backoff).await;
+                }
             }
         }
+        Ok(false) => {
+            // Interrupt line is no longer asserted, reset backoff
+            *retry_strategy = Default::default();
+        }
+        Err(_) => {
+            error!("Error post-checking interrupt line");
+        }
     }
 }
 
@@ -194,3 +360,142 @@ mod retry_strategy {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_time::{Delay, Duration, with_timeout};
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+    use static_cell::StaticCell;
+
+    use super::*;
+    use crate::test::{PORT0_ADDR0, create_register_read, create_register_write};
+
+    /// Tests running [`interrupt_task`] from a [`PollingSource`]
+    #[tokio::test]
+    async fn test_polling_source() {
+        let mut event = IntEventBus1::new_zero();
+        event.set_plug_event(true);
+
+        let transactions = [
+            // Polled directly
+            create_register_read(PORT0_ADDR0, 0x14, IntEventBus1::new_zero()),
+            create_register_read(PORT0_ADDR0, 0x14, event),
+            // Interrupt task, the poll sees the event and processing clears it
+            create_register_read(PORT0_ADDR0, 0x14, event),
+            create_register_read(PORT0_ADDR0, 0x14, event),
+            create_register_write(PORT0_ADDR0, 0x18, event),
+            // No longer asserted after processing, then idle polling
+            create_register_read(PORT0_ADDR0, 0x14, IntEventBus1::new_zero()),
+            create_register_read(PORT0_ADDR0, 0x14, IntEventBus1::new_zero()),
+        ];
+        static CONTROLLER: StaticCell<Controller<NoopRawMutex, Mock>> = StaticCell::new();
+        let controller = CONTROLLER
+            .init(Controller::new_tps66993(Mock::new(&transactions), Default::default(), PORT0_ADDR0).unwrap());
+        let (mut pd, mut processor, mut receiver) = controller.make_parts();
+
+        let mut source = PollingSource::new(&processor, Delay, 1000);
+        assert_eq!(source.is_asserted().await, Ok(false));
+        assert_eq!(source.is_asserted().await, Ok(true));
+
+        let flags = tokio::select! {
+            _ = interrupt_task(&mut source, [&mut processor].as_mut_slice()) => None,
+            flags = with_timeout(Duration::from_millis(100), receiver.wait_any(false)) => Some(flags),
+        };
+        assert_eq!(flags, Some(Ok([event, IntEventBus1::new_zero()])));
+
+        pd.lock_inner().await.bus.done();
+    }
+
+    /// Source that's asserted once, the line reads as asserted so no processing is skipped
+    struct OneShotSource {
+        asserted: bool,
+        line: AssertedLine,
+    }
+
+    impl InterruptSource for OneShotSource {
+        type Line = AssertedLine;
+        type Error = Infallible;
+
+        async fn wait_asserted(&mut self) -> Result<(), Self::Error> {
+            if core::mem::take(&mut self.asserted) {
+                Ok(())
+            } else {
+                core::future::pending().await
+            }
+        }
+
+        async fn is_asserted(&mut self) -> Result<bool, Self::Error> {
+            Ok(false)
+        }
+
+        fn line(&mut self) -> &mut Self::Line {
+            &mut self.line
+        }
+    }
+
+    /// Transactions for a plug event on port 0 being read and cleared
+    fn plug_event_transactions() -> [Transaction; 2] {
+        let mut event = IntEventBus1::new_zero();
+        event.set_plug_event(true);
+        [
+            create_register_read(PORT0_ADDR0, 0x14, event),
+            create_register_write(PORT0_ADDR0, 0x18, event),
+        ]
+    }
+
+    /// Tests that both the serial and concurrent tasks clear every controller's interrupts
+    #[tokio::test]
+    async fn test_interrupt_task_multiple_controllers() {
+        static CONTROLLERS: StaticCell<[Controller<NoopRawMutex, Mock>; 4]> = StaticCell::new();
+        let controllers = CONTROLLERS.init(core::array::from_fn(|_| {
+            Controller::new_tps66993(Mock::new(&plug_event_transactions()), Default::default(), PORT0_ADDR0).unwrap()
+        }));
+        let [serial0, serial1, concurrent0, concurrent1] = controllers;
+
+        let mut event = IntEventBus1::new_zero();
+        event.set_plug_event(true);
+        let expected = Some((
+            Ok([event, IntEventBus1::new_zero()]),
+            Ok([event, IntEventBus1::new_zero()]),
+        ));
+
+        // Serial
+        let (mut pd0, mut processor0, mut receiver0) = serial0.make_parts();
+        let (mut pd1, mut processor1, mut receiver1) = serial1.make_parts();
+        let mut source = OneShotSource {
+            asserted: true,
+            line: AssertedLine,
+        };
+        let flags = tokio::select! {
+            _ = interrupt_task(&mut source, [&mut processor0, &mut processor1].as_mut_slice()) => None,
+            flags = async {
+                let flags0 = with_timeout(Duration::from_millis(100), receiver0.wait_any(false)).await;
+                let flags1 = with_timeout(Duration::from_millis(100), receiver1.wait_any(false)).await;
+                (flags0, flags1)
+            } => Some(flags),
+        };
+        assert_eq!(flags, expected);
+        pd0.lock_inner().await.bus.done();
+        pd1.lock_inner().await.bus.done();
+
+        // Concurrent
+        let (mut pd0, mut processor0, mut receiver0) = concurrent0.make_parts();
+        let (mut pd1, mut processor1, mut receiver1) = concurrent1.make_parts();
+        let mut source = OneShotSource {
+            asserted: true,
+            line: AssertedLine,
+        };
+        let flags = tokio::select! {
+            _ = interrupt_task_concurrent(&mut source, &mut [&mut processor0, &mut processor1]) => None,
+            flags = async {
+                let flags0 = with_timeout(Duration::from_millis(100), receiver0.wait_any(false)).await;
+                let flags1 = with_timeout(Duration::from_millis(100), receiver1.wait_any(false)).await;
+                (flags0, flags1)
+            } => Some(flags),
+        };
+        assert_eq!(flags, expected);
+        pd0.lock_inner().await.bus.done();
+        pd1.lock_inner().await.bus.done();
+    }
+}
@@ -4,15 +4,18 @@ use core::iter::zip;
 use core::marker::PhantomData;
 
 use bincode::config;
+use embassy_futures::yield_now;
 use embedded_hal_async::delay::DelayNs;
+use embedded_io_async::{Read, Seek, SeekFrom};
 use embedded_usb_pd::{Error, PdError};
 
 use super::interrupt::InterruptController;
-use crate::command::{ReturnValue, TfudArgs, TfuiArgs, TfuqBlockStatus};
+use crate::command::{ReturnValue, TfudArgs, TfuiArgs, TfuqBlockStatus, TfuqReturnValue};
 use crate::fw_update::{
-    APP_CONFIG_BLOCK_INDEX, DATA_BLOCK_LEN, DATA_BLOCK_METADATA_LEN, DATA_BLOCK_START_INDEX, HEADER_BLOCK_INDEX,
-    HEADER_BLOCK_LEN, HEADER_BLOCK_OFFSET, HEADER_METADATA_LEN, HEADER_METADATA_OFFSET, IMAGE_ID_LEN, MAX_METADATA_LEN,
-    State, TFUD_BURST_WRITE_DELAY_MS, TFUI_BURST_WRITE_DELAY_MS, UPDATE_CHUNK_LENGTH, UpdateConfig,
+    APP_CONFIG_BLOCK_INDEX, AcceptedBlocks, DATA_BLOCK_LEN, DATA_BLOCK_METADATA_LEN, DATA_BLOCK_START_INDEX, FwImage,
+    FwUpdatePhase, FwUpdateProgress, HEADER_BLOCK_INDEX, HEADER_BLOCK_LEN, HEADER_BLOCK_OFFSET, HEADER_METADATA_LEN,
+    HEADER_METADATA_OFFSET, IMAGE_ID_LEN, MAX_FW_IMAGE_SIZE, MAX_METADATA_LEN, State, UPDATE_CHUNK_LENGTH,
+    UpdateConfig,
 };
 use crate::stream::*;
 use crate::{PORT0, debug, error, info, trace, warn};
@@ -53,6 +56,12 @@ pub trait UpdateTarget: InterruptController {
         block_index: usize,
     ) -> impl Future<Output = Result<TfuqBlockStatus, Error<Self::BusError>>>;
 
+    /// Query the status of the FW update in progress
+    fn fw_update_query_status(
+        &mut self,
+        delay: &mut impl DelayNs,
+    ) -> impl Future<Output = Result<TfuqReturnValue, Error<Self::BusError>>>;
+
     /// Stream a block to the device
     fn fw_update_stream_data(
         &mut self,
@@ -85,8 +94,14 @@ pub const fn block_offset(metadata_offset: usize) -> usize {
 }
 
 /// Computes the offset of the app config block's metadata
-pub const fn app_config_block_metadata_offset(num_data_blocks: usize, app_size: usize) -> usize {
-    app_size + IMAGE_ID_LEN + HEADER_METADATA_LEN + HEADER_BLOCK_LEN + num_data_blocks * DATA_BLOCK_METADATA_LEN
+///
+/// Both arguments come from the update image, returns [`PdError::InvalidParams`] if the offset overflows.
+pub fn app_config_block_metadata_offset(num_data_blocks: usize, app_size: usize) -> Result<usize, PdError> {
+    num_data_blocks
+        .checked_mul(DATA_BLOCK_METADATA_LEN)
+        .and_then(|len| len.checked_add(IMAGE_ID_LEN + HEADER_METADATA_LEN + HEADER_BLOCK_LEN))
+        .and_then(|len| len.checked_add(app_size))
+        .ok_or(PdError::InvalidParams)
 }
 
 /// Converts a data block into a block index
@@ -105,6 +120,29 @@ async fn abort_fw_update<T: UpdateTarget>(controllers: &mut [&mut T], delay: &mu
     }
 }
 
+/// Query every controller after a failed burst write and log the result
+///
+/// A controller that doesn't respond, or that has received fewer bytes than the others, is the likely cause of the
+/// failure. This is best effort and only used for diagnostics, failures are logged but otherwise ignored.
+async fn diagnose_burst_write_failure<T: UpdateTarget>(controllers: &mut [&mut T], delay: &mut impl DelayNs) {
+    for (i, controller) in controllers.iter_mut().enumerate() {
+        match controller.fw_update_query_status(delay).await {
+            Ok(status) => error!(
+                "Controller {}: TFU state {}, write status {}, header bytes received {}, data bytes received {}",
+                i,
+                status.current_state,
+                status.image_write_status,
+                status.num_of_header_bytes_received,
+                status.num_of_data_bytes_received
+            ),
+            Err(_) => error!(
+                "Controller {}: Not responding to TFUq, likely cause of the failed burst write",
+                i
+            ),
+        }
+    }
+}
+
 /// Initializes the FW update process
 ///
 /// All supplied controllers are updated in parallel using burst writes
@@ -174,6 +212,8 @@ pub struct BorrowedUpdaterInProgress<T: UpdateTarget> {
     image_size: usize,
     /// Block args
     block_args: Option<TfudArgs>,
+    /// Number of bytes burst written so far
+    bytes_written: usize,
     /// Phantom target
     _target: PhantomData<T>,
     /// Update configuration
@@ -189,11 +229,25 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
             update_args: None,
             image_size: 0,
             block_args: None,
+            bytes_written: 0,
             _target: PhantomData,
             config,
         }
     }
 
+    /// Create an updater for an update whose header has already been accepted by the controllers
+    fn resumed(config: UpdateConfig, image: &FwImage<'_>) -> Result<Self, PdError> {
+        let mut updater = Self::new(config);
+
+        let mut args = image.header_args()?;
+        // Override broadcast address if specified
+        args.broadcast_u16_address = updater.config.broadcast_addr.unwrap_or(args.broadcast_u16_address);
+
+        updater.update_args = Some(args);
+        updater.image_size = image.image_size();
+        Ok(updater)
+    }
+
     /// Initialize FW update on all controllers
     async fn fw_update_init(
         &mut self,
@@ -226,23 +280,47 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
 
     /// Send data to all controllers on the burst write address
     ///
-    /// Since all controllers are listening on the same burst write address,
-    /// we only use the first supplied controller to actually do the write.
+    /// Since all controllers on a bus are listening on the same burst write address,
+    /// we only use the first supplied controller to actually do the write. If the
+    /// controllers are on separate buses, see [`UpdateConfig::with_separate_buses`],
+    /// the write is sent through every controller so that it goes out on each bus.
+    /// Data longer than the configured chunk size is split into multiple writes.
+    /// If a write fails each controller is queried to help identify the one at fault.
     async fn fw_update_burst_write(
         &mut self,
         controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
         data: &[u8],
     ) -> Result<(), Error<T::BusError>> {
-        trace!("Controllers: Sending burst write");
         let update_args = self.update_args.ok_or(Error::Pd(PdError::InvalidParams))?;
-        if let Err(e) = controllers
-            .get_mut(0)
-            .ok_or(PdError::InvalidParams)?
-            .fw_update_burst_write(update_args.broadcast_u16_address as u8, data)
-            .await
-        {
-            debug!("Controllers: Failed to send burst write");
-            return Err(e);
+        if controllers.is_empty() {
+            return Err(PdError::InvalidParams.into());
+        }
+
+        let num_writers = if self.config.separate_buses {
+            controllers.len()
+        } else {
+            1
+        };
+        for chunk in data.chunks(self.config.timing.chunk_size.max(1)) {
+            let mut result = Ok(());
+            for (i, controller) in controllers.iter_mut().take(num_writers).enumerate() {
+                trace!("Controller {}: Sending burst write", i);
+                result = controller
+                    .fw_update_burst_write(update_args.broadcast_u16_address as u8, chunk)
+                    .await;
+                if result.is_err() {
+                    error!("Controller {}: Failed to send burst write", i);
+                    break;
+                }
+            }
+
+            if let Err(e) = result {
+                diagnose_burst_write_failure(controllers, delay).await;
+                return Err(e);
+            }
+
+            self.bytes_written += chunk.len();
         }
 
         Ok(())
@@ -301,6 +379,26 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
         Ok(())
     }
 
+    /// Report the current progress
+    fn report_progress(&self, progress: &mut impl FnMut(FwUpdateProgress)) {
+        let phase = match self.state {
+            State::UpdateArgs | State::HeaderBlockStart | State::ImageSize | State::HeaderBlockRest => {
+                FwUpdatePhase::Header
+            }
+            State::DataBlockHeader(index) | State::DataBlock(index) => FwUpdatePhase::DataBlock {
+                index,
+                total: self.update_args.map_or(0, |args| usize::from(args.num_data_blocks_tx)),
+            },
+            State::ConfigHeader | State::ConfigBlock => FwUpdatePhase::AppConfig,
+            State::Complete => FwUpdatePhase::Complete,
+        };
+
+        progress(FwUpdateProgress {
+            phase,
+            bytes_written: self.bytes_written,
+        });
+    }
+
     /// Supply update contents to the updater
     ///
     /// Returns Ok(true) if the update is complete
@@ -309,6 +407,21 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
         controllers: &mut [&mut T],
         delay: &mut impl DelayNs,
         data: &[u8],
+    ) -> Result<bool, Error<T::BusError>> {
+        self.write_bytes_with_progress(controllers, delay, data, &mut |_| {})
+            .await
+    }
+
+    /// Supply update contents to the updater, reporting progress before each burst write and after each block is
+    /// validated
+    ///
+    /// Returns Ok(true) if the update is complete
+    pub async fn write_bytes_with_progress(
+        &mut self,
+        controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
+        data: &[u8],
+        progress: &mut impl FnMut(FwUpdateProgress),
     ) -> Result<bool, Error<T::BusError>> {
         let mut data = data;
 
@@ -318,7 +431,7 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
                     data = self.handle_seek(data, stream).await?;
                 }
                 Stream::Reading(stream) => {
-                    data = self.handle_read(controllers, delay, data, stream).await?;
+                    data = self.handle_read(controllers, delay, data, stream, progress).await?;
                 }
             }
         }
@@ -326,6 +439,17 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
         Ok(self.state == State::Complete)
     }
 
+    /// Skip any bytes the updater would discard, returns the image offset of the next byte it needs
+    fn skip_to_next_offset(&mut self) -> usize {
+        match &mut self.stream {
+            Stream::Seeking(stream) => {
+                stream.skip_to_target();
+                stream.position()
+            }
+            Stream::Reading(stream) => stream.position(),
+        }
+    }
+
     /// Handle seeking in the stream
     async fn handle_seek<'c>(
         &mut self,
@@ -359,6 +483,7 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
         delay: &mut impl DelayNs,
         data: &'c [u8],
         mut stream: ReadingStream,
+        progress: &mut impl FnMut(FwUpdateProgress),
     ) -> Result<&'c [u8], Error<T::BusError>> {
         let read_result = stream.read_bytes(data);
 
@@ -369,15 +494,17 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
             }
             State::HeaderBlockStart => {
                 trace!("Reading header start");
-                self.process_header_start(controllers, &read_result).await
+                self.process_header_start(controllers, delay, &read_result, progress)
+                    .await
             }
             State::ImageSize => {
                 trace!("Reading image size");
-                self.read_image_size(controllers, &read_result).await
+                self.read_image_size(controllers, delay, &read_result, progress).await
             }
             State::HeaderBlockRest => {
                 trace!("Reading header rest");
-                self.process_header_rest(controllers, delay, &read_result).await
+                self.process_header_rest(controllers, delay, &read_result, progress)
+                    .await
             }
             State::DataBlockHeader(block_index) => {
                 trace!("Reading data block header: {}", block_index);
@@ -385,7 +512,7 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
             }
             State::DataBlock(block_index) => {
                 trace!("Reading data block: {}", block_index);
-                self.read_data_block(controllers, delay, &read_result, block_index)
+                self.read_data_block(controllers, delay, &read_result, block_index, progress)
                     .await
             }
             State::ConfigHeader => {
@@ -394,7 +521,7 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
             }
             State::ConfigBlock => {
                 trace!("Reading config block");
-                self.read_config_block(controllers, delay, &read_result).await
+                self.read_config_block(controllers, delay, &read_result, progress).await
             }
             State::Complete => {
                 trace!("Read other: {:#?}", self.state);
@@ -461,9 +588,13 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
     async fn process_header_start(
         &mut self,
         controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
         read_result: &ReadResult<'_>,
+        progress: &mut impl FnMut(FwUpdateProgress),
     ) -> Result<Option<SeekOperation>, Error<T::BusError>> {
-        self.fw_update_burst_write(controllers, read_result.read_data).await?;
+        self.report_progress(progress);
+        self.fw_update_burst_write(controllers, delay, read_result.read_data)
+            .await?;
         if read_result.is_complete() {
             Ok(Some(self.state.next_seek().map_err(Error::Pd)?))
         } else {
@@ -478,16 +609,21 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
         controllers: &mut [&mut T],
         delay: &mut impl DelayNs,
         read_result: &ReadResult<'_>,
+        progress: &mut impl FnMut(FwUpdateProgress),
     ) -> Result<Option<SeekOperation>, Error<T::BusError>> {
-        self.fw_update_burst_write(controllers, read_result.read_data).await?;
+        self.report_progress(progress);
+        self.fw_update_burst_write(controllers, delay, read_result.read_data)
+            .await?;
         if read_result.is_complete() {
             // Full header has been written
             // Validate and proceed to the next state
-            delay.delay_ms(TFUI_BURST_WRITE_DELAY_MS).await;
+            delay.delay_ms(self.config.timing.tfui_delay_ms).await;
             trace!("Validing header");
             self.fw_update_validate_stream(controllers, delay, HEADER_BLOCK_INDEX)
                 .await?;
             trace!("Header validated");
+            self.report_progress(progress);
+            yield_now().await;
             Ok(Some(self.state.next_seek().map_err(Error::Pd)?))
         } else {
             Ok(None)
@@ -547,15 +683,20 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
         delay: &mut impl DelayNs,
         read_result: &ReadResult<'_>,
         block_index: usize,
+        progress: &mut impl FnMut(FwUpdateProgress),
     ) -> Result<Option<SeekOperation>, Error<T::BusError>> {
-        self.fw_update_burst_write(controllers, read_result.read_data).await?;
+        self.report_progress(progress);
+        self.fw_update_burst_write(controllers, delay, read_result.read_data)
+            .await?;
 
         if read_result.is_complete() {
             // Full block has been written
             // Validate and proceed to the next state
-            delay.delay_ms(TFUD_BURST_WRITE_DELAY_MS).await;
+            delay.delay_ms(self.config.timing.tfud_delay_ms).await;
             self.fw_update_validate_stream(controllers, delay, data_block_index_to_block_index(block_index))
                 .await?;
+            self.report_progress(progress);
+            yield_now().await;
 
             let update_args = self.update_args.ok_or(Error::Pd(PdError::InvalidParams))?;
             Ok(Some(
@@ -573,7 +714,9 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
     async fn read_image_size(
         &mut self,
         controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
         read_result: &ReadResult<'_>,
+        progress: &mut impl FnMut(FwUpdateProgress),
     ) -> Result<Option<SeekOperation>, Error<T::BusError>> {
         let current = read_result.read_state.current;
         let read_len = read_result.read_data.len();
@@ -581,7 +724,9 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
             .get_mut(current..current + read_len)
             .ok_or(PdError::InvalidParams)?
             .copy_from_slice(read_result.read_data);
-        self.fw_update_burst_write(controllers, read_result.read_data).await?;
+        self.report_progress(progress);
+        self.fw_update_burst_write(controllers, delay, read_result.read_data)
+            .await?;
         if read_result.is_complete() {
             // We have the full image size
             let (image_size, _): (u32, _) =
@@ -620,15 +765,19 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
         controllers: &mut [&mut T],
         delay: &mut impl DelayNs,
         read_result: &ReadResult<'_>,
+        progress: &mut impl FnMut(FwUpdateProgress),
     ) -> Result<Option<SeekOperation>, Error<T::BusError>> {
-        self.fw_update_burst_write(controllers, read_result.read_data).await?;
+        self.report_progress(progress);
+        self.fw_update_burst_write(controllers, delay, read_result.read_data)
+            .await?;
 
         if read_result.is_complete() {
             // Full block has been written
             // Validate and proceed to the next state
-            delay.delay_ms(TFUD_BURST_WRITE_DELAY_MS).await;
+            delay.delay_ms(self.config.timing.tfud_delay_ms).await;
             self.fw_update_validate_stream(controllers, delay, APP_CONFIG_BLOCK_INDEX)
                 .await?;
+            self.report_progress(progress);
 
             // Proceed to the next state
             trace!("FW update complete");
@@ -638,31 +787,168 @@ impl<T: UpdateTarget> BorrowedUpdaterInProgress<T> {
         }
     }
 
+    /// Re-send a single data block to all controllers
+    ///
+    /// `block_index` is the zero-based data block index.
+    /// This repeats the `TFUd` setup, burst write and validation for just that block, allowing recovery from a
+    /// single failed block without restarting the whole update. The update header must have already been
+    /// accepted by the controllers.
+    pub async fn fw_update_restream_block(
+        &mut self,
+        controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
+        image: &FwImage<'_>,
+        block_index: usize,
+    ) -> Result<(), Error<T::BusError>> {
+        // Header must have been accepted
+        if self.update_args.is_none() {
+            return Err(PdError::InvalidParams.into());
+        }
+
+        debug!("Controllers: Re-streaming data block {}", block_index);
+        self.restream(
+            controllers,
+            delay,
+            image,
+            image.data_block_metadata_offset(block_index)?,
+            data_block_index_to_block_index(block_index),
+        )
+        .await
+    }
+
+    /// Re-send the app config block to all controllers
+    async fn fw_update_restream_app_config(
+        &mut self,
+        controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
+        image: &FwImage<'_>,
+    ) -> Result<(), Error<T::BusError>> {
+        // Header must have been accepted
+        if self.update_args.is_none() {
+            return Err(PdError::InvalidParams.into());
+        }
+
+        debug!("Controllers: Re-streaming app config block");
+        self.restream(
+            controllers,
+            delay,
+            image,
+            image.app_config_block_metadata_offset()?,
+            APP_CONFIG_BLOCK_INDEX,
+        )
+        .await
+    }
+
+    /// Send the block with metadata at `metadata_offset` and validate it as `block_index`
+    async fn restream(
+        &mut self,
+        controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
+        image: &FwImage<'_>,
+        metadata_offset: usize,
+        block_index: usize,
+    ) -> Result<(), Error<T::BusError>> {
+        // Data block and app config metadata have the same layout
+        let (mut args, data) = image.block(metadata_offset)?;
+
+        // Override broadcast address if specified
+        args.broadcast_u16_address = self.config.broadcast_addr.unwrap_or(args.broadcast_u16_address);
+
+        self.fw_update_stream_data(controllers, delay, &args).await?;
+        for chunk in data.chunks(UPDATE_CHUNK_LENGTH) {
+            self.fw_update_burst_write(controllers, delay, chunk).await?;
+        }
+
+        delay.delay_ms(self.config.timing.tfud_delay_ms).await;
+        self.fw_update_validate_stream(controllers, delay, block_index).await
+    }
+
+    /// Send every block not in `accepted`, after which the update is ready to be completed
+    async fn restream_missing(
+        &mut self,
+        controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
+        image: &FwImage<'_>,
+        accepted: AcceptedBlocks,
+    ) -> Result<(), Error<T::BusError>> {
+        for block in 0..image.num_data_blocks() {
+            if !accepted.contains(data_block_index_to_block_index(block)) {
+                self.fw_update_restream_block(controllers, delay, image, block).await?;
+                yield_now().await;
+            }
+        }
+
+        if !accepted.contains(APP_CONFIG_BLOCK_INDEX) {
+            self.fw_update_restream_app_config(controllers, delay, image).await?;
+        }
+
+        self.state = State::Complete;
+        Ok(())
+    }
+
     /// Abort the FW update process
     pub async fn abort_fw_update(self, controllers: &mut [&mut T], delay: &mut impl DelayNs) {
         abort_fw_update(controllers, delay).await
     }
 
     /// Complete the FW update process
+    ///
+    /// This is a two-phase commit to keep multi-controller systems on a consistent FW version. Every controller must
+    /// first report a validated app config block, the final block of the update. Only then is the new image committed
+    /// on each controller. If the image wasn't completely supplied or any controller fails validation, no controller
+    /// commits and all exit FW update mode. A controller that fails the commit itself exits FW update mode and an error
+    /// is returned, but controllers that already committed can't be rolled back.
     pub async fn complete_fw_update(
-        self,
+        mut self,
         controllers: &mut [&mut T],
         delay: &mut impl DelayNs,
     ) -> Result<(), Error<T::BusError>> {
         // The update blob wasn't completely supplied
         if self.state != State::Complete {
+            error!("Update incomplete, not committing");
+            abort_fw_update(controllers, delay).await;
             return Err(PdError::Failed.into());
         }
 
+        // Confirm every controller is ready to commit before committing any of them
+        if let Err(e) = self
+            .fw_update_validate_stream(controllers, delay, APP_CONFIG_BLOCK_INDEX)
+            .await
+        {
+            error!("Not all controllers validated, not committing");
+            abort_fw_update(controllers, delay).await;
+            return Err(e);
+        }
+
+        let mut result = Ok(());
         for (i, controller) in controllers.iter_mut().enumerate() {
             debug!("Controller {}: Completing FW update", i);
-            if controller.fw_update_complete(delay).await.is_err() {
+            if let Err(e) = controller.fw_update_complete(delay).await {
                 warn!("Controller {}: Failed to complete FW update, attempting to exit", i);
-                controller.fw_update_mode_exit(delay).await?;
-                // Don't return to allow the other controllers to exit FW update mode
+                result = Err(e);
+                if controller.fw_update_mode_exit(delay).await.is_err() {
+                    error!("Controller {}: Failed to exit FW update mode", i);
+                }
+                // Don't return to allow the other controllers to complete
             }
         }
 
+        result
+    }
+
+    /// Complete the FW update process, reporting [`FwUpdatePhase::Complete`] if the update was committed
+    pub async fn complete_fw_update_with_progress(
+        self,
+        controllers: &mut [&mut T],
+        delay: &mut impl DelayNs,
+        progress: &mut impl FnMut(FwUpdateProgress),
+    ) -> Result<(), Error<T::BusError>> {
+        let bytes_written = self.bytes_written;
+        self.complete_fw_update(controllers, delay).await?;
+        progress(FwUpdateProgress {
+            phase: FwUpdatePhase::Complete,
+            bytes_written,
+        });
         Ok(())
     }
 }
@@ -689,16 +975,15 @@ pub async fn enable_port0_interrupts<T: UpdateTarget>(
     Ok(())
 }
 
-/// General FW update function
+/// Enter FW update mode on all controllers, managing interrupts as required
 ///
 /// interrupt_guards have a length twice of that of controllers
-pub async fn perform_fw_update_borrowed<T: UpdateTarget>(
+async fn start_fw_update_guarded<T: UpdateTarget>(
     controllers: &mut [&mut T],
     interrupt_guards: &mut [Option<T::Guard>],
     delay: &mut impl DelayNs,
     config: UpdateConfig,
-    pd_fw_bytes: &[u8],
-) -> Result<(), Error<T::BusError>> {
+) -> Result<BorrowedUpdaterInProgress<T>, Error<T::BusError>> {
     // Need two sets of interrupt guards for each controller
     if interrupt_guards.len() != 2 * controllers.len() {
         return Err(PdError::InvalidParams.into());
@@ -726,46 +1011,319 @@ pub async fn perform_fw_update_borrowed<T: UpdateTarget>(
     )
     .await?;
 
-    match result {
-        Err(e) => {
-            error!("Failed to enter FW update mode");
-            Err(e)
-        }
-        Ok(mut updater) => {
-            info!("Sending chunks");
-            for chunk in pd_fw_bytes.chunks(UPDATE_CHUNK_LENGTH) {
-                match updater.write_bytes(controllers, delay, chunk).await {
-                    Err(e) => {
-                        error!("Failed to write chunk");
-                        updater.abort_fw_update(controllers, delay).await;
-                        return Err(e);
-                    }
-                    Ok(true) => {
-                        info!("Update contents written");
-                        break;
-                    }
-                    _ => {}
-                }
+    if result.is_err() {
+        error!("Failed to enter FW update mode");
+    }
+    result
+}
+
+/// General FW update function
+///
+/// interrupt_guards have a length twice of that of controllers
+///
+/// The update yields to the executor after each block so that other tasks can make progress during a long update.
+/// Controllers are only borrowed for individual operations, but other users of the bus must still not address the
+/// controllers or the broadcast address while an update is in progress as this would corrupt the burst writes.
+///
+/// Malformed images are rejected when the [`FwImage`] is created, before any controller is put into FW update mode.
+/// Use [`perform_fw_update_borrowed_with_progress`] to be notified of progress.
+pub async fn perform_fw_update_borrowed<T: UpdateTarget>(
+    controllers: &mut [&mut T],
+    interrupt_guards: &mut [Option<T::Guard>],
+    delay: &mut impl DelayNs,
+    config: UpdateConfig,
+    image: &FwImage<'_>,
+) -> Result<(), Error<T::BusError>> {
+    perform_fw_update_borrowed_with_progress(controllers, interrupt_guards, delay, config, image, |_| {}).await
+}
+
+/// Same as [`perform_fw_update_borrowed`], calling `progress` before each burst write and after each block is
+/// validated
+pub async fn perform_fw_update_borrowed_with_progress<T: UpdateTarget>(
+    controllers: &mut [&mut T],
+    interrupt_guards: &mut [Option<T::Guard>],
+    delay: &mut impl DelayNs,
+    config: UpdateConfig,
+    image: &FwImage<'_>,
+    mut progress: impl FnMut(FwUpdateProgress),
+) -> Result<(), Error<T::BusError>> {
+    progress(FwUpdateProgress {
+        phase: FwUpdatePhase::EnteringMode,
+        bytes_written: 0,
+    });
+    let mut updater = start_fw_update_guarded(controllers, interrupt_guards, delay, config).await?;
+
+    info!("Sending chunks");
+    for chunk in image.bytes().chunks(UPDATE_CHUNK_LENGTH) {
+        match updater
+            .write_bytes_with_progress(controllers, delay, chunk, &mut progress)
+            .await
+        {
+            Err(e) => {
+                error!("Failed to write chunk");
+                updater.abort_fw_update(controllers, delay).await;
+                return Err(e);
+            }
+            Ok(true) => {
+                info!("Update contents written");
+                break;
             }
+            _ => {}
+        }
+    }
+
+    updater
+        .complete_fw_update_with_progress(controllers, delay, &mut progress)
+        .await
+}
+
+/// FW update function for a single controller
+///
+/// Behaves identically to [`perform_fw_update_borrowed`] with just `controller`. Only this controller is sent `TFUi`
+/// with the broadcast address, so other controllers on the bus ignore the burst writes and stay on their current FW.
+/// If other controllers may also be in FW update mode, give this update a unique address with
+/// [`UpdateConfig::with_broadcast_addr`].
+///
+/// The burst writes can't be sent to the controller's own I2C address as it would interpret them as register writes.
+/// Use [`perform_fw_update_single_with_progress`] to be notified of progress.
+pub async fn perform_fw_update_single<T: UpdateTarget>(
+    controller: &mut T,
+    interrupt_guards: &mut [Option<T::Guard>; 2],
+    delay: &mut impl DelayNs,
+    config: UpdateConfig,
+    image: &FwImage<'_>,
+) -> Result<(), Error<T::BusError>> {
+    perform_fw_update_single_with_progress(controller, interrupt_guards, delay, config, image, |_| {}).await
+}
+
+/// Same as [`perform_fw_update_single`], calling `progress` before each burst write and after each block is
+/// validated
+pub async fn perform_fw_update_single_with_progress<T: UpdateTarget>(
+    controller: &mut T,
+    interrupt_guards: &mut [Option<T::Guard>; 2],
+    delay: &mut impl DelayNs,
+    config: UpdateConfig,
+    image: &FwImage<'_>,
+    progress: impl FnMut(FwUpdateProgress),
+) -> Result<(), Error<T::BusError>> {
+    perform_fw_update_borrowed_with_progress(&mut [controller], interrupt_guards, delay, config, image, progress).await
+}
+
+/// Resume an interrupted FW update, only sending the blocks the controllers haven't already accepted
+///
+/// The controllers must still be in FW update mode. Each controller is queried with `TFUq` and only blocks accepted
+/// by every controller are skipped. If any controller hasn't accepted the update header this falls back to a full
+/// update with [`perform_fw_update_borrowed`]. If querying any controller fails, the error is returned without
+/// writing anything.
+///
+/// interrupt_guards have a length twice of that of controllers
+///
+/// Use [`resume_fw_update_with_progress`] to be notified of progress.
+pub async fn resume_fw_update<T: UpdateTarget>(
+    controllers: &mut [&mut T],
+    interrupt_guards: &mut [Option<T::Guard>],
+    delay: &mut impl DelayNs,
+    config: UpdateConfig,
+    image: &FwImage<'_>,
+) -> Result<(), Error<T::BusError>> {
+    resume_fw_update_with_progress(controllers, interrupt_guards, delay, config, image, |_| {}).await
+}
+
+/// Same as [`resume_fw_update`], calling `progress` as the update proceeds
+///
+/// Blocks restreamed while resuming aren't reported individually, only completion is. A fallback to a full update is
+/// reported as in [`perform_fw_update_borrowed_with_progress`].
+pub async fn resume_fw_update_with_progress<T: UpdateTarget>(
+    controllers: &mut [&mut T],
+    interrupt_guards: &mut [Option<T::Guard>],
+    delay: &mut impl DelayNs,
+    config: UpdateConfig,
+    image: &FwImage<'_>,
+    mut progress: impl FnMut(FwUpdateProgress),
+) -> Result<(), Error<T::BusError>> {
+    // Need two sets of interrupt guards for each controller
+    if controllers.is_empty() || interrupt_guards.len() != 2 * controllers.len() {
+        return Err(PdError::InvalidParams.into());
+    }
+
+    // Same interrupt configuration as start_fw_update_guarded, but the controllers are already in FW update mode
+    let half = interrupt_guards.len() / 2;
+    disable_all_interrupts(
+        controllers,
+        interrupt_guards.get_mut(half..).ok_or(PdError::InvalidParams)?,
+    )
+    .await?;
+    enable_port0_interrupts(
+        controllers,
+        interrupt_guards.get_mut(0..half).ok_or(PdError::InvalidParams)?,
+    )
+    .await?;
+
+    let mut accepted: Option<AcceptedBlocks> = None;
+    for (i, controller) in controllers.iter_mut().enumerate() {
+        debug!("Controller {}: Querying FW update status", i);
+        let status = controller.fw_update_query_status(delay).await.inspect_err(|_| {
+            error!("Controller {}: Failed to query FW update status", i);
+        })?;
+        let blocks = AcceptedBlocks::from_tfuq(&status);
+        accepted = Some(accepted.map_or(blocks, |accepted| accepted.intersection(blocks)));
+    }
+    let accepted = accepted.ok_or(PdError::InvalidParams)?;
+
+    if !accepted.contains(HEADER_BLOCK_INDEX) {
+        info!("Update header not accepted, performing full update");
+        // Restore interrupts before they're reconfigured, first half first to match the drop order
+        interrupt_guards.iter_mut().for_each(|guard| *guard = None);
+        return perform_fw_update_borrowed_with_progress(controllers, interrupt_guards, delay, config, image, progress)
+            .await;
+    }
+
+    info!("Resuming update");
+    let mut updater = BorrowedUpdaterInProgress::resumed(config, image)?;
+    if let Err(e) = updater.restream_missing(controllers, delay, image, accepted).await {
+        error!("Failed to resume update");
+        updater.abort_fw_update(controllers, delay).await;
+        return Err(e);
+    }
+
+    updater
+        .complete_fw_update_with_progress(controllers, delay, &mut progress)
+        .await
+}
+
+/// Feed the image from `reader` to the updater, seeking past any regions the updater doesn't need
+async fn stream_fw_image<T: UpdateTarget, R: Read + Seek>(
+    updater: &mut BorrowedUpdaterInProgress<T>,
+    controllers: &mut [&mut T],
+    delay: &mut impl DelayNs,
+    reader: &mut R,
+    image_len: usize,
+    progress: &mut impl FnMut(FwUpdateProgress),
+) -> Result<(), Error<T::BusError>> {
+    let mut buf = [0u8; UPDATE_CHUNK_LENGTH];
+    // The reader was left at the end of the image when measuring its length
+    let mut position = image_len;
+
+    loop {
+        let offset = updater.skip_to_next_offset();
+        if offset >= image_len {
+            // Out of data, complete_fw_update will reject the update if it wasn't fully supplied
+            return Ok(());
+        }
+
+        if offset != position {
+            trace!("Seeking to {:#x}", offset);
+            reader.seek(SeekFrom::Start(offset as u64)).await.map_err(|_| {
+                error!("Failed to seek FW image");
+                PdError::Failed
+            })?;
+        }
 
-            updater.complete_fw_update(controllers, delay).await
+        // Keep reads aligned to the same chunk boundaries as the slice-based update so the burst writes are identical
+        let len = (UPDATE_CHUNK_LENGTH - offset % UPDATE_CHUNK_LENGTH).min(image_len - offset);
+        let chunk = buf.get_mut(..len).ok_or(PdError::InvalidParams)?;
+        reader.read_exact(chunk).await.map_err(|_| {
+            error!("Failed to read FW image");
+            PdError::Failed
+        })?;
+        position = offset + len;
+
+        if updater
+            .write_bytes_with_progress(controllers, delay, chunk, progress)
+            .await?
+        {
+            info!("Update contents written");
+            return Ok(());
         }
     }
 }
 
+/// FW update function for images that aren't memory-mapped, e.g. stored on external flash
+///
+/// Behaves identically to [`perform_fw_update_borrowed`], but reads the image from `reader` in
+/// [`UPDATE_CHUNK_LENGTH`] chunks, seeking past regions of the image that aren't sent to the controllers.
+/// Use [`perform_fw_update_streamed_with_progress`] to be notified of progress.
+pub async fn perform_fw_update_streamed<T: UpdateTarget, R: Read + Seek>(
+    controllers: &mut [&mut T],
+    interrupt_guards: &mut [Option<T::Guard>],
+    delay: &mut impl DelayNs,
+    config: UpdateConfig,
+    reader: &mut R,
+) -> Result<(), Error<T::BusError>> {
+    perform_fw_update_streamed_with_progress(controllers, interrupt_guards, delay, config, reader, |_| {}).await
+}
+
+/// Same as [`perform_fw_update_streamed`], calling `progress` as described in
+/// [`perform_fw_update_borrowed_with_progress`]
+pub async fn perform_fw_update_streamed_with_progress<T: UpdateTarget, R: Read + Seek>(
+    controllers: &mut [&mut T],
+    interrupt_guards: &mut [Option<T::Guard>],
+    delay: &mut impl DelayNs,
+    config: UpdateConfig,
+    reader: &mut R,
+    mut progress: impl FnMut(FwUpdateProgress),
+) -> Result<(), Error<T::BusError>> {
+    let image_len = reader.seek(SeekFrom::End(0)).await.map_err(|_| {
+        error!("Failed to get FW image size");
+        PdError::Failed
+    })?;
+    let image_len = usize::try_from(image_len).map_err(|_| PdError::InvalidParams)?;
+    if image_len > MAX_FW_IMAGE_SIZE {
+        error!("FW image too large: {} bytes", image_len);
+        return Err(PdError::InvalidParams.into());
+    }
+
+    progress(FwUpdateProgress {
+        phase: FwUpdatePhase::EnteringMode,
+        bytes_written: 0,
+    });
+    let mut updater = start_fw_update_guarded(controllers, interrupt_guards, delay, config).await?;
+
+    info!("Sending chunks");
+    if let Err(e) = stream_fw_image(&mut updater, controllers, delay, reader, image_len, &mut progress).await {
+        error!("Failed to write chunk");
+        updater.abort_fw_update(controllers, delay).await;
+        return Err(e);
+    }
+
+    updater
+        .complete_fw_update_with_progress(controllers, delay, &mut progress)
+        .await
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::MAX_SUPPORTED_PORTS;
     use crate::asynchronous::interrupt::InterruptGuard;
-    use crate::test::{Delay, generate_mock_fw};
+    use crate::command::TFUQ_RETURN_BLOCK_STATUS_LEN;
+    use crate::fw_update::FwUpdateTiming;
+    use crate::test::{
+        Delay, MOCK_APP_CONFIG_SIZE, MOCK_DEFAULT_DATA_BLOCK_SIZE, MOCK_LAST_DATA_BLOCK_SIZE, generate_mock_fw,
+        mock_tfuq_status,
+    };
     extern crate std;
+    use std::vec::Vec;
 
     /// Simple mock update target for testing that validates the length of the data written
     #[derive(Debug)]
     struct UpdateTargetNoop {
         pub expected_write_len: Option<usize>,
         pub write_len: usize,
+        /// Fail validation of all blocks
+        pub fail_validation: bool,
+        /// FW update was committed
+        pub completed: bool,
+        /// FW update mode was exited
+        pub exited: bool,
+        /// Contents of each burst write
+        pub burst_writes: Vec<Vec<u8>>,
+        /// Result of a FW update status query, `None` to fail the query
+        pub status: Option<TfuqReturnValue>,
+        /// Number of FW update status queries
+        pub status_queries: usize,
+        /// Fail all burst writes
+        pub fail_burst_write: bool,
     }
 
     impl UpdateTargetNoop {
@@ -773,6 +1331,13 @@ mod test {
             Self {
                 expected_write_len: None,
                 write_len: 0,
+                fail_validation: false,
+                completed: false,
+                exited: false,
+                burst_writes: Vec::new(),
+                status: None,
+                status_queries: 0,
+                fail_burst_write: false,
             }
         }
     }
@@ -823,6 +1388,7 @@ mod test {
         }
 
         async fn fw_update_mode_exit(&mut self, _delay: &mut impl DelayNs) -> Result<(), Error<Self::BusError>> {
+            self.exited = true;
             Ok(())
         }
 
@@ -831,7 +1397,19 @@ mod test {
             _delay: &mut impl DelayNs,
             _block_index: usize,
         ) -> Result<TfuqBlockStatus, Error<Self::BusError>> {
-            Ok(TfuqBlockStatus::DataValidAndAuthentic)
+            if self.fail_validation {
+                Ok(TfuqBlockStatus::DataNotValid)
+            } else {
+                Ok(TfuqBlockStatus::DataValidAndAuthentic)
+            }
+        }
+
+        async fn fw_update_query_status(
+            &mut self,
+            _delay: &mut impl DelayNs,
+        ) -> Result<TfuqReturnValue, Error<Self::BusError>> {
+            self.status_queries += 1;
+            self.status.ok_or(Error::Bus(()))
         }
 
         async fn fw_update_stream_data(
@@ -850,11 +1428,17 @@ mod test {
         }
 
         async fn fw_update_complete(&mut self, _delay: &mut impl DelayNs) -> Result<(), Error<Self::BusError>> {
+            self.completed = true;
             Ok(())
         }
 
         async fn fw_update_burst_write(&mut self, _address: u8, data: &[u8]) -> Result<(), Error<Self::BusError>> {
+            if self.fail_burst_write {
+                return Err(Error::Bus(()));
+            }
+
             self.write_len += data.len();
+            self.burst_writes.push(data.to_vec());
             Ok(())
         }
     }
@@ -866,7 +1450,8 @@ mod test {
         let mut target = UpdateTargetNoop::new();
         let mut controllers = [&mut target];
         let mut guards = [const { None }; 2];
-        let fw_mock = &generate_mock_fw();
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
 
         perform_fw_update_borrowed(
             &mut controllers,
@@ -877,21 +1462,221 @@ mod test {
         )
         .await
         .unwrap();
+        assert!(target.completed);
     }
 
-    /// Test return value of write_bytes when the update is complete
+    /// Test progress reporting during a FW update
     #[tokio::test]
-    async fn test_fw_update_completion_write_bytes() {
+    async fn test_fw_update_progress() {
         let mut delay = Delay {};
         let mut target = UpdateTargetNoop::new();
         let mut controllers = [&mut target];
-        let fw_mock = &generate_mock_fw();
+        let mut guards = [const { None }; 2];
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+        let mut reports = Vec::new();
 
-        // Don't need to do anything with interrupts since this isn't actual hardware
-        let mut updater = BorrowedUpdater::default();
-        let mut updater = updater.start_fw_update(&mut controllers, &mut delay).await.unwrap();
+        perform_fw_update_borrowed_with_progress(
+            &mut controllers,
+            &mut guards,
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+            |progress| reports.push(progress),
+        )
+        .await
+        .unwrap();
 
-        for chunk in fw_mock.chunks(UPDATE_CHUNK_LENGTH) {
+        let total_written: usize = target.burst_writes.iter().map(Vec::len).sum();
+        assert_eq!(
+            reports.first(),
+            Some(&FwUpdateProgress {
+                phase: FwUpdatePhase::EnteringMode,
+                bytes_written: 0,
+            })
+        );
+        assert_eq!(
+            reports.last(),
+            Some(&FwUpdateProgress {
+                phase: FwUpdatePhase::Complete,
+                bytes_written: total_written,
+            })
+        );
+        assert!(reports.windows(2).all(|w| w[0].bytes_written <= w[1].bytes_written));
+        assert!(
+            reports
+                .iter()
+                .any(|r| r.phase == FwUpdatePhase::DataBlock { index: 10, total: 11 })
+        );
+        assert!(reports.iter().any(|r| r.phase == FwUpdatePhase::AppConfig));
+    }
+
+    /// Seekable in-memory image for testing streamed updates
+    struct Cursor<'a> {
+        data: &'a [u8],
+        position: usize,
+        /// Number of seeks performed
+        seeks: usize,
+    }
+
+    impl<'a> Cursor<'a> {
+        fn new(data: &'a [u8]) -> Self {
+            Self {
+                data,
+                position: 0,
+                seeks: 0,
+            }
+        }
+    }
+
+    impl embedded_io_async::ErrorType for Cursor<'_> {
+        type Error = embedded_io_async::ErrorKind;
+    }
+
+    impl Read for Cursor<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let remaining = &self.data[self.position.min(self.data.len())..];
+            let len = buf.len().min(remaining.len());
+            buf[..len].copy_from_slice(&remaining[..len]);
+            self.position += len;
+            Ok(len)
+        }
+    }
+
+    impl Seek for Cursor<'_> {
+        async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+            self.seeks += 1;
+            self.position = match pos {
+                SeekFrom::Start(offset) => offset as usize,
+                SeekFrom::End(offset) => (self.data.len() as i64 + offset) as usize,
+                SeekFrom::Current(offset) => (self.position as i64 + offset) as usize,
+            };
+            Ok(self.position as u64)
+        }
+    }
+
+    /// Test that a streamed update matches the slice-based update
+    #[tokio::test]
+    async fn test_fw_update_streamed() {
+        let mut delay = Delay {};
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        let mut expected = UpdateTargetNoop::new();
+        perform_fw_update_borrowed(
+            &mut [&mut expected],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        let mut target = UpdateTargetNoop::new();
+        let mut reader = Cursor::new(&fw_bytes);
+        perform_fw_update_streamed(
+            &mut [&mut target],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            &mut reader,
+        )
+        .await
+        .unwrap();
+
+        assert!(target.completed);
+        assert_eq!(target.burst_writes, expected.burst_writes);
+        // Regions that aren't sent to the controllers should be skipped rather than read
+        assert!(reader.seeks > 1);
+    }
+
+    /// Test that a truncated streamed image isn't committed
+    #[tokio::test]
+    async fn test_fw_update_streamed_truncated() {
+        let mut delay = Delay {};
+        let mut target = UpdateTargetNoop::new();
+        let fw_mock = &generate_mock_fw();
+        let mut reader = Cursor::new(&fw_mock[..fw_mock.len() / 2]);
+
+        assert_eq!(
+            perform_fw_update_streamed(
+                &mut [&mut target],
+                &mut [const { None }; 2],
+                &mut delay,
+                UpdateConfig::default(),
+                &mut reader,
+            )
+            .await,
+            Err(Error::Pd(PdError::Failed))
+        );
+        assert!(!target.completed);
+        assert!(target.exited);
+    }
+
+    /// Test that no controller commits unless all controllers validated
+    #[tokio::test]
+    async fn test_fw_update_complete_all_or_nothing() {
+        let mut delay = Delay {};
+        let mut target0 = UpdateTargetNoop::new();
+        let mut target1 = UpdateTargetNoop::new();
+        let mut controllers = [&mut target0, &mut target1];
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        let mut updater = BorrowedUpdater::default();
+        let mut updater = updater.start_fw_update(&mut controllers, &mut delay).await.unwrap();
+        for chunk in fw_mock.bytes().chunks(UPDATE_CHUNK_LENGTH) {
+            if updater.write_bytes(&mut controllers, &mut delay, chunk).await.unwrap() {
+                break;
+            }
+        }
+
+        // Second controller no longer reports a valid image
+        controllers[1].fail_validation = true;
+        assert_eq!(
+            updater.complete_fw_update(&mut controllers, &mut delay).await,
+            Err(Error::Pd(PdError::Failed))
+        );
+
+        for controller in controllers.iter() {
+            assert!(!controller.completed);
+            assert!(controller.exited);
+        }
+    }
+
+    /// Test that an incompletely supplied image isn't committed
+    #[tokio::test]
+    async fn test_fw_update_complete_incomplete() {
+        let mut delay = Delay {};
+        let mut target = UpdateTargetNoop::new();
+        let mut controllers = [&mut target];
+
+        let mut updater = BorrowedUpdater::default();
+        let updater = updater.start_fw_update(&mut controllers, &mut delay).await.unwrap();
+
+        assert_eq!(
+            updater.complete_fw_update(&mut controllers, &mut delay).await,
+            Err(Error::Pd(PdError::Failed))
+        );
+        assert!(!controllers[0].completed);
+        assert!(controllers[0].exited);
+    }
+
+    /// Test return value of write_bytes when the update is complete
+    #[tokio::test]
+    async fn test_fw_update_completion_write_bytes() {
+        let mut delay = Delay {};
+        let mut target = UpdateTargetNoop::new();
+        let mut controllers = [&mut target];
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        // Don't need to do anything with interrupts since this isn't actual hardware
+        let mut updater = BorrowedUpdater::default();
+        let mut updater = updater.start_fw_update(&mut controllers, &mut delay).await.unwrap();
+
+        for chunk in fw_mock.bytes().chunks(UPDATE_CHUNK_LENGTH) {
             if updater.write_bytes(&mut controllers, &mut delay, chunk).await.unwrap() {
                 // Update contents have been written
                 break;
@@ -917,6 +1702,387 @@ mod test {
         assert_eq!(updater.state, State::Complete);
     }
 
+    /// Test re-streaming a single data block after the update contents have been written
+    #[tokio::test]
+    async fn test_fw_update_restream_block() {
+        let mut delay = Delay {};
+        let mut target = UpdateTargetNoop::new();
+        let mut controllers = [&mut target];
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        let mut updater = BorrowedUpdater::default();
+        let mut updater = updater.start_fw_update(&mut controllers, &mut delay).await.unwrap();
+
+        for chunk in fw_mock.bytes().chunks(UPDATE_CHUNK_LENGTH) {
+            if updater.write_bytes(&mut controllers, &mut delay, chunk).await.unwrap() {
+                break;
+            }
+        }
+
+        updater
+            .fw_update_restream_block(&mut controllers, &mut delay, fw_mock, 3)
+            .await
+            .unwrap();
+        assert_eq!(controllers[0].write_len, MOCK_DEFAULT_DATA_BLOCK_SIZE as usize);
+
+        // Last data block has a different size
+        updater
+            .fw_update_restream_block(&mut controllers, &mut delay, fw_mock, 10)
+            .await
+            .unwrap();
+        assert_eq!(controllers[0].write_len, MOCK_LAST_DATA_BLOCK_SIZE as usize);
+
+        // Out of range block index
+        assert_eq!(
+            updater
+                .fw_update_restream_block(&mut controllers, &mut delay, fw_mock, 11)
+                .await,
+            Err(Error::Pd(PdError::InvalidParams))
+        );
+    }
+
+    /// Test that a failed burst write queries every controller and aborts the update
+    #[tokio::test]
+    async fn test_fw_update_burst_write_failure() {
+        let mut delay = Delay {};
+        let mut target0 = UpdateTargetNoop::new();
+        let mut target1 = UpdateTargetNoop::new();
+        target0.fail_burst_write = true;
+        target1.status = Some(mock_tfuq_status(
+            0,
+            [TfuqBlockStatus::Success; TFUQ_RETURN_BLOCK_STATUS_LEN],
+        ));
+
+        assert_eq!(
+            perform_fw_update_borrowed(
+                &mut [&mut target0, &mut target1],
+                &mut [const { None }; 4],
+                &mut delay,
+                UpdateConfig::default(),
+                &FwImage::new(&generate_mock_fw()).unwrap(),
+            )
+            .await,
+            Err(Error::Bus(()))
+        );
+
+        // Both controllers are queried, even though the first doesn't respond
+        assert_eq!(target0.status_queries, 1);
+        assert_eq!(target1.status_queries, 1);
+        assert!(target0.exited);
+        assert!(target1.exited);
+    }
+
+    /// Test that burst writes go out through every controller when they're on separate buses
+    #[tokio::test]
+    async fn test_fw_update_separate_buses() {
+        let mut delay = Delay {};
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        let mut target0 = UpdateTargetNoop::new();
+        let mut target1 = UpdateTargetNoop::new();
+        perform_fw_update_borrowed(
+            &mut [&mut target0, &mut target1],
+            &mut [const { None }; 4],
+            &mut delay,
+            UpdateConfig::default().with_separate_buses(true),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        assert!(target0.completed);
+        assert!(target1.completed);
+        assert!(!target0.burst_writes.is_empty());
+        assert_eq!(target0.burst_writes, target1.burst_writes);
+    }
+
+    /// Test that a single burst write is sent for controllers sharing a bus
+    #[tokio::test]
+    async fn test_fw_update_burst_write_shared_bus() {
+        let mut delay = Delay {};
+        let mut target0 = UpdateTargetNoop::new();
+        let mut target1 = UpdateTargetNoop::new();
+        let mut updater = BorrowedUpdaterInProgress::<UpdateTargetNoop>::new(UpdateConfig::default());
+        updater.update_args = Some(crate::test::MOCK_UPDATE_HEADER);
+
+        updater
+            .fw_update_burst_write(&mut [&mut target0, &mut target1], &mut delay, &[0xAA; 4])
+            .await
+            .unwrap();
+        assert_eq!(target0.burst_writes, [[0xAA; 4]]);
+        assert!(target1.burst_writes.is_empty());
+        assert_eq!(updater.bytes_written, 4);
+    }
+
+    /// Test that burst writes with no controllers are rejected
+    #[tokio::test]
+    async fn test_fw_update_burst_write_no_controllers() {
+        let mut delay = Delay {};
+        let mut updater = BorrowedUpdaterInProgress::<UpdateTargetNoop>::new(UpdateConfig::default());
+        updater.update_args = Some(crate::test::MOCK_UPDATE_HEADER);
+
+        assert_eq!(
+            updater.fw_update_burst_write(&mut [], &mut delay, &[0; 4]).await,
+            Err(Error::Pd(PdError::InvalidParams))
+        );
+    }
+
+    /// Test that burst writes are split according to the configured chunk size
+    #[tokio::test]
+    async fn test_fw_update_chunk_size() {
+        let mut delay = Delay {};
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        let mut expected = UpdateTargetNoop::new();
+        perform_fw_update_borrowed(
+            &mut [&mut expected],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        let timing = FwUpdateTiming {
+            chunk_size: 100,
+            ..Default::default()
+        };
+        let mut target = UpdateTargetNoop::new();
+        perform_fw_update_borrowed(
+            &mut [&mut target],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default().with_timing(timing).unwrap(),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        assert!(target.completed);
+        assert!(target.burst_writes.len() > expected.burst_writes.len());
+        assert!(target.burst_writes.iter().all(|write| write.len() <= 100));
+        // Same contents, just split differently
+        assert_eq!(target.burst_writes.concat(), expected.burst_writes.concat());
+    }
+
+    /// Test updating a single controller
+    #[tokio::test]
+    async fn test_fw_update_single() {
+        let mut delay = Delay {};
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        let mut expected = UpdateTargetNoop::new();
+        perform_fw_update_borrowed(
+            &mut [&mut expected],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        let mut target = UpdateTargetNoop::new();
+        perform_fw_update_single(
+            &mut target,
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        assert!(target.completed);
+        assert_eq!(target.burst_writes, expected.burst_writes);
+
+        let mut target = UpdateTargetNoop::new();
+        let mut reports = Vec::new();
+        perform_fw_update_single_with_progress(
+            &mut target,
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+            |progress| reports.push(progress),
+        )
+        .await
+        .unwrap();
+
+        assert!(target.completed);
+        assert_eq!(reports.last().map(|r| r.phase), Some(FwUpdatePhase::Complete));
+
+        // Validation failure on the single controller isn't committed
+        let mut target = UpdateTargetNoop::new();
+        target.fail_validation = true;
+        assert_eq!(
+            perform_fw_update_single(
+                &mut target,
+                &mut [const { None }; 2],
+                &mut delay,
+                UpdateConfig::default(),
+                fw_mock,
+            )
+            .await,
+            Err(Error::Pd(PdError::Failed))
+        );
+        assert!(!target.completed);
+        assert!(target.exited);
+    }
+
+    /// Create a TFUq status where the header and the first `data_blocks` data blocks have been accepted
+    fn partial_update_status(data_blocks: usize) -> TfuqReturnValue {
+        let mut block_status = [TfuqBlockStatus::Success; TFUQ_RETURN_BLOCK_STATUS_LEN];
+        block_status[HEADER_BLOCK_INDEX] = TfuqBlockStatus::HeaderValidAndAuthentic;
+        for status in &mut block_status[DATA_BLOCK_START_INDEX..DATA_BLOCK_START_INDEX + data_blocks] {
+            *status = TfuqBlockStatus::DataValidAndAuthentic;
+        }
+
+        mock_tfuq_status((1 << (data_blocks + 1)) - 1, block_status)
+    }
+
+    /// Test resuming an update only sends the blocks that weren't accepted
+    #[tokio::test]
+    async fn test_resume_fw_update() {
+        let mut delay = Delay {};
+        let mut target0 = UpdateTargetNoop::new();
+        let mut target1 = UpdateTargetNoop::new();
+        target0.status = Some(partial_update_status(5));
+        // Only blocks accepted by both controllers are skipped
+        target1.status = Some(partial_update_status(4));
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        resume_fw_update(
+            &mut [&mut target0, &mut target1],
+            &mut [const { None }; 4],
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        // Data blocks 4 to 9, the shorter last data block and the app config block
+        let expected_len = 6 * MOCK_DEFAULT_DATA_BLOCK_SIZE as usize
+            + MOCK_LAST_DATA_BLOCK_SIZE as usize
+            + MOCK_APP_CONFIG_SIZE as usize;
+        let total_written: usize = target0.burst_writes.iter().map(Vec::len).sum();
+        assert_eq!(total_written, expected_len);
+        assert_eq!(target0.burst_writes.first().map(Vec::len), Some(UPDATE_CHUNK_LENGTH));
+        assert!(target0.completed);
+        assert!(target1.completed);
+    }
+
+    /// Test resuming an update with every block accepted only completes the update
+    #[tokio::test]
+    async fn test_resume_fw_update_all_accepted() {
+        let mut delay = Delay {};
+        let mut target = UpdateTargetNoop::new();
+        let mut block_status = [TfuqBlockStatus::DataValidAndAuthentic; TFUQ_RETURN_BLOCK_STATUS_LEN];
+        block_status[HEADER_BLOCK_INDEX] = TfuqBlockStatus::HeaderValidAndAuthentic;
+        target.status = Some(mock_tfuq_status(0x1fff, block_status));
+
+        resume_fw_update(
+            &mut [&mut target],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            &FwImage::new(&generate_mock_fw()).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert!(target.burst_writes.is_empty());
+        assert!(target.completed);
+    }
+
+    /// Test that resuming reports completion through the progress callback
+    #[tokio::test]
+    async fn test_resume_fw_update_with_progress() {
+        let mut delay = Delay {};
+        let mut target = UpdateTargetNoop::new();
+        target.status = Some(partial_update_status(5));
+        let mut reports = Vec::new();
+
+        resume_fw_update_with_progress(
+            &mut [&mut target],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            &FwImage::new(&generate_mock_fw()).unwrap(),
+            |progress| reports.push(progress),
+        )
+        .await
+        .unwrap();
+
+        assert!(target.completed);
+        assert_eq!(reports.last().map(|r| r.phase), Some(FwUpdatePhase::Complete));
+    }
+
+    /// Test that an update without a valid header is restarted from the beginning
+    #[tokio::test]
+    async fn test_resume_fw_update_header_invalid() {
+        let mut delay = Delay {};
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
+
+        let mut expected = UpdateTargetNoop::new();
+        perform_fw_update_borrowed(
+            &mut [&mut expected],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        let mut target = UpdateTargetNoop::new();
+        let mut block_status = [TfuqBlockStatus::DataValidAndAuthentic; TFUQ_RETURN_BLOCK_STATUS_LEN];
+        block_status[HEADER_BLOCK_INDEX] = TfuqBlockStatus::HeaderNotValid;
+        target.status = Some(mock_tfuq_status(0x1fff, block_status));
+
+        resume_fw_update(
+            &mut [&mut target],
+            &mut [const { None }; 2],
+            &mut delay,
+            UpdateConfig::default(),
+            fw_mock,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(target.burst_writes, expected.burst_writes);
+        assert!(target.completed);
+    }
+
+    /// Test that a failed status query is reported without writing anything
+    #[tokio::test]
+    async fn test_resume_fw_update_query_failed() {
+        let mut delay = Delay {};
+        let mut target = UpdateTargetNoop::new();
+
+        assert_eq!(
+            resume_fw_update(
+                &mut [&mut target],
+                &mut [const { None }; 2],
+                &mut delay,
+                UpdateConfig::default(),
+                &FwImage::new(&generate_mock_fw()).unwrap(),
+            )
+            .await,
+            Err(Error::Bus(()))
+        );
+        assert!(target.burst_writes.is_empty());
+        assert!(!target.completed);
+    }
+
     /// Test error checking around number of interrupt guards and controllers
     #[tokio::test]
     async fn test_fw_update_guards_count() {
@@ -924,7 +2090,8 @@ mod test {
         let mut target = UpdateTargetNoop::new();
         let mut controllers = [&mut target];
         let mut guards = [const { None }; 1];
-        let fw_mock = &generate_mock_fw();
+        let fw_bytes = generate_mock_fw();
+        let fw_mock = &FwImage::new(&fw_bytes).unwrap();
 
         assert_eq!(
             perform_fw_update_borrowed(
@@ -938,4 +2105,13 @@ mod test {
             Err(Error::Pd(PdError::InvalidParams))
         );
     }
+
+    /// Test that oversized images are rejected before entering FW update mode
+    #[test]
+    fn test_fw_update_image_too_large() {
+        let mut fw_mock = generate_mock_fw();
+        fw_mock.resize(MAX_FW_IMAGE_SIZE + 1, 0);
+
+        assert!(matches!(FwImage::new(&fw_mock), Err(PdError::InvalidParams)));
+    }
 }
@@ -1,6 +1,5 @@
 //! This module implements functions to access the command register and its associate data register.
 //! The data register is larger than what device_driver can handle so access is done directly through the `AsyncRegisterInterface` trait.
-use bincode::config;
 use device_driver::AsyncRegisterInterface;
 use embedded_hal_async::delay::DelayNs;
 use embedded_hal_async::i2c::I2c;
@@ -10,13 +9,23 @@ use super::Tps6699x;
 use crate::command::*;
 use crate::{Mode, PORT0, debug, error, registers as regs};
 
-impl<B: I2c> Tps6699x<B> {
+impl<B: I2c, D: DelayNs> Tps6699x<B, D> {
     /// Sends a command without verifying that it is valid
     pub async fn send_command(
         &mut self,
         port: LocalPortId,
         cmd: Command,
         data: Option<&[u8]>,
+    ) -> Result<(), Error<B::Error>> {
+        self.send_raw_command(port, cmd as u32, data).await
+    }
+
+    /// Sends a raw 4CC command that may not be covered by [`Command`], without verifying that it is valid
+    pub async fn send_raw_command(
+        &mut self,
+        port: LocalPortId,
+        cmd: u32,
+        data: Option<&[u8]>,
     ) -> Result<(), Error<B::Error>> {
         let mut registers = self.borrow_port(port)?.into_registers();
 
@@ -27,7 +36,7 @@ impl<B: I2c> Tps6699x<B> {
                 .await?;
         }
 
-        registers.cmd_1().write_async(|r| r.set_command(cmd as u32)).await?;
+        registers.cmd_1().write_async(|r| r.set_command(cmd)).await?;
 
         Ok(())
     }
@@ -42,11 +51,7 @@ impl<B: I2c> Tps6699x<B> {
             .await?
             .command();
 
-        match Command::try_from(status).map_err(Error::Pd)? {
-            Command::Success => Ok(true),
-            Command::Invalid => Err(PdError::UnrecognizedCommand.into()),
-            _ => Ok(false),
-        }
+        decode_command_complete(status).map_err(Error::Pd)
     }
 
     /// Read the result of a command
@@ -68,15 +73,8 @@ impl<B: I2c> Tps6699x<B> {
             }
         }
 
-        let max_len = if has_return_value {
-            // -1 because one byte is used for the return value
-            regs::REG_DATA1_LEN - 1
-        } else {
-            regs::REG_DATA1_LEN
-        };
-
         if let Some(ref data) = data
-            && data.len() > max_len
+            && data.len() > max_output_len(has_return_value)
         {
             // Data length too long
             return PdError::InvalidParams.into();
@@ -90,93 +88,200 @@ impl<B: I2c> Tps6699x<B> {
             .read_register(regs::REG_DATA1, (regs::REG_DATA1_LEN * 8) as u32, &mut buf)
             .await?;
 
-        if has_return_value {
-            let return_code = buf[0] & CMD_4CC_TASK_RETURN_CODE_MASK;
-            let ret = ReturnValue::try_from(return_code).map_err(Error::Pd)?;
-            debug!("read_command_result: ret: {:?}", ret);
-            // Overwrite return value
-            if let Some(data) = data {
-                data.copy_from_slice(buf.get(1..=data.len()).ok_or(PdError::InvalidParams)?);
+        decode_command_result(&buf, data, has_return_value).map_err(Error::Pd)
+    }
+
+    /// Reset the controller
+    pub async fn reset(&mut self, delay: &mut impl DelayNs, args: &ResetArgs) -> Result<(), Error<B::Error>> {
+        self.reset_with_config(delay, args, &Default::default()).await
+    }
+
+    /// Reset the controller, waiting for it to restart as described by `config`
+    ///
+    /// When polling, readiness is checked with [`Self::wait_for_mode`] at the configured interval, and
+    /// [`PdError::Timeout`] is returned if the controller doesn't report an application mode within
+    /// [`ResetConfig::wait_ms`].
+    pub async fn reset_with_config(
+        &mut self,
+        delay: &mut impl DelayNs,
+        args: &ResetArgs,
+        config: &ResetConfig,
+    ) -> Result<(), Error<B::Error>> {
+        self.reset_nowait(args).await?;
+
+        match config.poll_mode {
+            ResetPollMode::Fixed => {
+                delay.delay_ms(config.wait_ms).await;
+                Ok(())
             }
-            Ok(ret)
-        } else {
-            // No return value to check
-            debug!("read_command_result: Done");
-            if let Some(data) = data {
-                data.copy_from_slice(buf.get(..data.len()).ok_or(PdError::InvalidParams)?);
+            ResetPollMode::Poll { interval_ms } => {
+                // Avoid spinning forever on a zero interval
+                let interval_ms = interval_ms.max(1);
+                // The controller still reports its application mode until the reset actually starts
+                delay.delay_ms(interval_ms).await;
+                self.poll_for_mode(
+                    &[Mode::App0, Mode::App1],
+                    delay,
+                    config.wait_ms.saturating_sub(interval_ms),
+                    interval_ms,
+                )
+                .await
+                .map(|_| ())
             }
-            Ok(ReturnValue::Success)
         }
     }
 
-    /// Reset the controller
-    pub async fn reset(&mut self, delay: &mut impl DelayNs, args: &ResetArgs) -> Result<(), Error<B::Error>> {
-        // This is a controller-level command, shouldn't matter which port we use
-        let mut arg_bytes = [0u8; RESET_ARGS_LEN];
+    /// Reset the controller and boot application firmware from `bank`
+    ///
+    /// Valid banks are `0` and `1`, anything else returns [`PdError::InvalidParams`]. The boot flags are read to
+    /// determine the active bank and [`ResetArgs::switch_banks`] is set only if `bank` isn't already active. The bank
+    /// contents are never copied, see [`ResetArgs::copy_bank`]. After the reset the mode register is read back and
+    /// [`PdError::Failed`] is returned if the controller isn't running from `bank`, e.g. because it's corrupt.
+    pub async fn reset_to_bank(&mut self, delay: &mut impl DelayNs, bank: u8) -> Result<(), Error<B::Error>> {
+        let expected_mode = match bank {
+            0 => Mode::App0,
+            1 => Mode::App1,
+            _ => return PdError::InvalidParams.into(),
+        };
 
-        bincode::encode_into_slice(args, &mut arg_bytes, config::standard().with_fixed_int_encoding())
-            .map_err(|_| Error::Pd(PdError::Serialize))?;
-        self.send_command(PORT0, Command::Gaid, Some(&arg_bytes)).await?;
+        let active_bank = self.get_boot_flags().await?.active_bank();
+        let args = ResetArgs {
+            switch_banks: active_bank != bank,
+            copy_bank: false,
+        };
+        self.reset(delay, &args).await?;
 
-        delay.delay_ms(RESET_DELAY_MS).await;
+        let mode = self.get_mode().await?;
+        if mode != expected_mode {
+            error!("Expected mode {:?} after reset, got {:?}", expected_mode, mode);
+            return PdError::Failed.into();
+        }
 
         Ok(())
     }
 
+    /// Trigger a controller reset without waiting for it to complete, see [`Self::wait_ready`]
+    pub async fn reset_nowait(&mut self, args: &ResetArgs) -> Result<(), Error<B::Error>> {
+        // This is a controller-level command, shouldn't matter which port we use
+        let arg_bytes = args.to_bytes().map_err(Error::Pd)?;
+        self.send_command(PORT0, Command::Gaid, Some(&arg_bytes)).await
+    }
+
+    /// Wait for the controller to finish booting into application firmware
+    ///
+    /// Polls the mode register periodically until the controller reports an application mode,
+    /// returning that mode. Returns [`PdError::Timeout`] if the controller isn't ready within `timeout_ms`.
+    pub async fn wait_ready(&mut self, delay: &mut impl DelayNs, timeout_ms: u32) -> Result<Mode, Error<B::Error>> {
+        self.wait_for_mode(&[Mode::App0, Mode::App1], delay, timeout_ms).await
+    }
+
+    /// Wait for the controller to enter one of the `targets` modes
+    ///
+    /// Polls the mode register every 50ms, returning the first target mode read. Unknown modes and read errors are
+    /// treated as transient, e.g. the controller doesn't respond while resetting. Returns [`PdError::Timeout`] if no
+    /// target mode is read within `timeout_ms`.
+    pub async fn wait_for_mode(
+        &mut self,
+        targets: &[Mode],
+        delay: &mut impl DelayNs,
+        timeout_ms: u32,
+    ) -> Result<Mode, Error<B::Error>> {
+        self.poll_for_mode(targets, delay, timeout_ms, READY_POLL_INTERVAL_MS)
+            .await
+    }
+
+    /// [`Self::wait_for_mode`] with a custom poll interval
+    async fn poll_for_mode(
+        &mut self,
+        targets: &[Mode],
+        delay: &mut impl DelayNs,
+        timeout_ms: u32,
+        interval_ms: u32,
+    ) -> Result<Mode, Error<B::Error>> {
+        let mut elapsed_ms = 0;
+        loop {
+            match self.get_mode_raw().await {
+                Ok(raw) => match Mode::try_from(raw) {
+                    Ok(mode) if targets.contains(&mode) => return Ok(mode),
+                    Ok(mode) => debug!("Waiting for mode, current mode: {:?}", mode),
+                    // Transitional modes, e.g. during a bank switch, aren't covered by `Mode`
+                    Err(_) => debug!("Waiting for mode, unknown mode: {:#x}", raw),
+                },
+                // The controller doesn't respond while it's resetting
+                Err(_) => debug!("Controller not responding"),
+            }
+
+            if elapsed_ms >= timeout_ms {
+                error!("Controller not in {:?} after {}ms", targets, elapsed_ms);
+                return PdError::Timeout.into();
+            }
+
+            delay.delay_ms(interval_ms).await;
+            elapsed_ms = elapsed_ms.saturating_add(interval_ms);
+        }
+    }
+
     /// Enter firmware update mode
     pub async fn execute_tfus(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<B::Error>> {
         // This is a controller-level command, shouldn't matter which port we use
         self.send_command(PORT0, Command::Tfus, None).await?;
 
+        // The controller keeps reporting its application mode until it actually resets, so give it the full settle
+        // time before polling
         delay.delay_ms(TFUS_DELAY_MS).await;
 
-        // Confirm we're in the correct mode
-        let mode = self.get_mode().await?;
-        if mode != Mode::F211 {
-            error!("Failed to enter firmware update mode, mode: {:?}", mode);
-            return Err(PdError::InvalidMode.into());
+        match self.wait_for_mode(&[Mode::F211], delay, TFUS_DELAY_MS).await {
+            Ok(_) => Ok(()),
+            Err(Error::Pd(PdError::Timeout)) => {
+                error!("Failed to enter firmware update mode");
+                Err(PdError::InvalidMode.into())
+            }
+            Err(e) => Err(e),
         }
-        Ok(())
     }
 
     /// Complete firmware update
     pub async fn execute_tfuc(&mut self, delay: &mut impl DelayNs) -> Result<(), Error<B::Error>> {
-        let mut arg_bytes = [0u8; RESET_ARGS_LEN];
-
         let args = ResetArgs {
             switch_banks: false,
             copy_bank: true,
         };
-
-        bincode::encode_into_slice(args, &mut arg_bytes, config::standard().with_fixed_int_encoding())
-            .map_err(|_| Error::Pd(PdError::Serialize))?;
+        let arg_bytes = args.to_bytes().map_err(Error::Pd)?;
 
         // This is a controller-level command, shouldn't matter which port we use
         let port = LocalPortId(0);
         self.send_command(port, Command::Tfuc, Some(&arg_bytes)).await?;
 
+        // The controller keeps reporting its application mode until it actually resets, so polling can't start
+        // immediately
         delay.delay_ms(RESET_DELAY_MS).await;
 
-        // Confirm we're in the correct mode
-        let mode = self.get_mode().await?;
-        if mode != Mode::App0 && mode != Mode::App1 {
-            error!("Failed to enter normal mode, mode: {:?}", mode);
-            return Err(PdError::InvalidMode.into());
+        match self
+            .wait_for_mode(&[Mode::App0, Mode::App1], delay, RESET_DELAY_MS)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(Error::Pd(PdError::Timeout)) => {
+                error!("Failed to enter normal mode");
+                Err(PdError::InvalidMode.into())
+            }
+            Err(e) => Err(e),
         }
-
-        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use embedded_hal_mock::eh1::i2c::Mock;
+    use bincode::config;
+    use embedded_hal::i2c::ErrorKind;
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
     use regs::REG_DATA1;
 
     use crate::asynchronous::internal::Tps6699x;
     use crate::{ADDR0, PORT0, PORT1};
 
     extern crate std;
+    use std::vec;
     use std::vec::Vec;
 
     use super::*;
@@ -269,6 +374,181 @@ mod test {
         tps6699x.bus.done();
     }
 
+    async fn run_reset_to_bank(
+        active_bank: u8,
+        bank: u8,
+        switch_banks: bool,
+        mode: Mode,
+    ) -> Result<(), Error<ErrorKind>> {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let mut delay = Delay {};
+
+        let mut flags: regs::boot_flags::BootFlags = regs::boot_flags::BootFlagsRaw([0u8; regs::boot_flags::LEN]);
+        flags.set_active_bank(active_bank);
+        let args = ResetArgs {
+            switch_banks,
+            copy_bank: false,
+        };
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x2D, flags.0),
+            create_register_write(PORT0_ADDR0, REG_DATA1, args.to_bytes().unwrap()),
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Gaid as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x03, mode),
+        ]);
+
+        let result = tps6699x.reset_to_bank(&mut delay, bank).await;
+        tps6699x.bus.done();
+        result
+    }
+
+    #[tokio::test]
+    async fn test_reset_to_bank() {
+        // Switch to the other bank
+        assert_eq!(run_reset_to_bank(0, 1, true, Mode::App1).await, Ok(()));
+        assert_eq!(run_reset_to_bank(1, 0, true, Mode::App0).await, Ok(()));
+        // Already active, plain reset
+        assert_eq!(run_reset_to_bank(1, 1, false, Mode::App1).await, Ok(()));
+        // Requested bank didn't boot
+        assert_eq!(
+            run_reset_to_bank(0, 1, true, Mode::F211).await,
+            Err(Error::Pd(PdError::Failed))
+        );
+        assert_eq!(
+            run_reset_to_bank(0, 1, true, Mode::App0).await,
+            Err(Error::Pd(PdError::Failed))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reset_to_bank_invalid() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let mut delay = Delay {};
+
+        assert_eq!(
+            tps6699x.reset_to_bank(&mut delay, 2).await,
+            Err(Error::Pd(PdError::InvalidParams))
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_reset_with_config_poll() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let mut delay = Delay {};
+        let config = ResetConfig {
+            wait_ms: 100,
+            poll_mode: ResetPollMode::Poll { interval_ms: 10 },
+        };
+
+        tps6699x.bus.update_expectations(&[
+            create_register_write(PORT0_ADDR0, REG_DATA1, [0u8; RESET_ARGS_LEN]),
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Gaid as u32).to_le_bytes()),
+            // Still in the bootloader
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::Boot as u32).to_le_bytes()),
+            // Controller not responding while it restarts
+            Transaction::write_read(PORT0_ADDR0, vec![0x03], vec![0u8; 5]).with_error(ErrorKind::Other),
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::App0 as u32).to_le_bytes()),
+        ]);
+
+        tps6699x
+            .reset_with_config(&mut delay, &Default::default(), &config)
+            .await
+            .unwrap();
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_reset_with_config_poll_timeout() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let mut delay = Delay {};
+        let config = ResetConfig {
+            wait_ms: 20,
+            poll_mode: ResetPollMode::Poll { interval_ms: 10 },
+        };
+
+        // Polled at 10 and 20ms
+        tps6699x.bus.update_expectations(&[
+            create_register_write(PORT0_ADDR0, REG_DATA1, [0u8; RESET_ARGS_LEN]),
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Gaid as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::Boot as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::Boot as u32).to_le_bytes()),
+        ]);
+
+        assert_eq!(
+            tps6699x
+                .reset_with_config(&mut delay, &Default::default(), &config)
+                .await,
+            Err(Error::Pd(PdError::Timeout))
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_reset_nowait() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+
+        tps6699x.bus.update_expectations(&[
+            create_register_write(PORT0_ADDR0, REG_DATA1, [0u8; RESET_ARGS_LEN]),
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Gaid as u32).to_le_bytes()),
+        ]);
+
+        tps6699x.reset_nowait(&Default::default()).await.unwrap();
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_wait_ready() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let mut delay = Delay {};
+
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::Boot as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::App1 as u32).to_le_bytes()),
+        ]);
+
+        assert_eq!(tps6699x.wait_ready(&mut delay, 1000).await, Ok(Mode::App1));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_mode() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let mut delay = Delay {};
+
+        // Transient unknown mode and an unresponsive controller are both tolerated
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x03, *b"PTCH"),
+            Transaction::write_read(PORT0_ADDR0, vec![0x03], vec![0; 5]).with_error(ErrorKind::Other),
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::App0 as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::F211 as u32).to_le_bytes()),
+        ]);
+
+        assert_eq!(
+            tps6699x.wait_for_mode(&[Mode::F211], &mut delay, 1000).await,
+            Ok(Mode::F211)
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_wait_ready_timeout() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let mut delay = Delay {};
+
+        // Polled at 0, 50 and 100ms
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::Boot as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::Boot as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x03, (Mode::Boot as u32).to_le_bytes()),
+        ]);
+
+        assert_eq!(
+            tps6699x.wait_ready(&mut delay, 2 * READY_POLL_INTERVAL_MS).await,
+            Err(Error::Pd(PdError::Timeout))
+        );
+        tps6699x.bus.done();
+    }
+
     #[tokio::test]
     async fn test_execute_tfus() {
         let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
@@ -280,6 +560,12 @@ mod test {
             0x08,
             (Command::Tfus as u32).to_le_bytes(),
         ));
+        // Still reporting the application mode after the settle delay, polling continues
+        transactions.push(create_register_read(
+            PORT0_ADDR0,
+            0x03,
+            (Mode::App0 as u32).to_le_bytes(),
+        ));
         transactions.push(create_register_read(
             PORT0_ADDR0,
             0x03,
@@ -323,6 +609,8 @@ mod test {
         transactions.push(create_register_read(expected_addr, 0x08, [0x21, 0x43, 0x4D, 0x44]));
         // Command still in progress
         transactions.push(create_register_read(expected_addr, 0x08, [0x47, 0x41, 0x49, 0x44]));
+        // Raw command not covered by `Command` still in progress
+        transactions.push(create_register_read(expected_addr, 0x08, *b"XXXX"));
 
         tps6699x.bus.update_expectations(&transactions);
 
@@ -332,6 +620,7 @@ mod test {
             Err(Error::Pd(PdError::UnrecognizedCommand))
         );
         assert_eq!(tps6699x.check_command_complete(port).await, Ok(false));
+        assert_eq!(tps6699x.check_command_complete(port).await, Ok(false));
 
         tps6699x.bus.done();
     }
@@ -1,30 +1,65 @@
 //! Asynchronous, low-level TPS6699x driver. This module provides a low-level interface
 use device_driver::AsyncRegisterInterface;
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::{Error as _, ErrorKind, I2c};
 use embedded_usb_pd::pdinfo::AltMode;
 use embedded_usb_pd::pdo::{self, sink, source};
 use embedded_usb_pd::{Error, LocalPortId, PdError};
 
 use crate::registers::rx_caps::{EPR_PDO_START_INDEX, RxCapsError};
 use crate::{
-    DeviceError, MAX_SUPPORTED_PORTS, Mode, PORT0, PORT1, TPS66993_NUM_PORTS, TPS66994_NUM_PORTS, registers, warn,
+    DeviceError, MAX_SUPPORTED_PORTS, Mode, PORT0, PORT1, TPS66993_NUM_PORTS, TPS66994_NUM_PORTS, debug, error,
+    registers,
 };
 
 mod command;
 
+/// Delay that returns immediately, used when no retry delay has been provided
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Retry policy for transient bus errors, such as the controller NAKing the first transaction after waking up
+///
+/// Only bus errors are retried, errors reported by the controller itself are returned immediately
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RetryConfig {
+    /// Number of retries after the initial attempt, zero disables retrying
+    pub count: u8,
+    /// Delay between attempts in microseconds
+    pub delay_us: u32,
+}
+
 /// Wrapper to allow implementing device_driver traits on our I2C bus
-pub struct Port<'a, B: I2c> {
+pub struct Port<'a, B: I2c, D: DelayNs = NoDelay> {
     bus: &'a mut B,
     addr: u8,
+    retry: RetryConfig,
+    delay: &'a mut D,
+    pec: bool,
 }
 
-impl<'a, B: I2c> Port<'a, B> {
-    pub fn into_registers(self) -> registers::Registers<Port<'a, B>> {
+impl<'a, B: I2c, D: DelayNs> Port<'a, B, D> {
+    /// Create a port wrapper for `addr`, retrying bus errors according to `retry` and waiting with `delay`
+    pub fn new(bus: &'a mut B, addr: u8, retry: RetryConfig, delay: &'a mut D, pec: bool) -> Self {
+        Self {
+            bus,
+            addr,
+            retry,
+            delay,
+            pec,
+        }
+    }
+
+    pub fn into_registers(self) -> registers::Registers<Port<'a, B, D>> {
         registers::Registers::new(self)
     }
 }
 
-impl<B: I2c> device_driver::AsyncRegisterInterface for Port<'_, B> {
+impl<B: I2c, D: DelayNs> device_driver::AsyncRegisterInterface for Port<'_, B, D> {
     type Error = Error<B::Error>;
 
     type AddressType = u8;
@@ -35,25 +70,24 @@ impl<B: I2c> device_driver::AsyncRegisterInterface for Port<'_, B> {
         _size_bits: u32,
         data: &[u8],
     ) -> Result<(), Self::Error> {
-        // Sized to accommodate up to 255 bytes of data
-        let mut buf = [0u8; 257];
+        let mut buf = [0u8; registers::REGISTER_WRITE_BUF_LEN];
+        let buf = if self.pec {
+            registers::encode_register_write_pec(self.addr, address, data, &mut buf)?
+        } else {
+            registers::encode_register_write(address, data, &mut buf)?
+        };
 
-        // Buffer length is sent as a byte
-        if data.len() > 255 {
-            return Err(PdError::InvalidParams.into());
+        let mut retries = self.retry.count;
+        loop {
+            match self.bus.write(self.addr, buf).await {
+                Ok(()) => return Ok(()),
+                Err(e) if retries == 0 => return Err(Error::Bus(e)),
+                Err(_) => {
+                    retries -= 1;
+                    self.delay.delay_us(self.retry.delay_us).await;
+                }
+            }
         }
-
-        buf[0] = address;
-        buf[1] = data.len() as u8;
-        let _ = &buf
-            .get_mut(2..data.len() + 2)
-            .ok_or(PdError::InvalidParams)?
-            .copy_from_slice(data);
-
-        self.bus
-            .write(self.addr, buf.get(..data.len() + 2).ok_or(PdError::InvalidParams)?)
-            .await
-            .map_err(Error::Bus)
     }
 
     async fn read_register(
@@ -62,52 +96,60 @@ impl<B: I2c> device_driver::AsyncRegisterInterface for Port<'_, B> {
         _size_bits: u32,
         data: &mut [u8],
     ) -> Result<(), Self::Error> {
-        // Sized to accommodate length byte + up to 255 bytes of data
-        let mut buf = [0u8; 256];
-        let full_len = data.len() + 1;
+        let mut buf = [0u8; registers::REGISTER_READ_BUF_LEN];
+        // +1 for the length byte, +1 for the PEC byte if enabled
+        let full_len = data.len() + 1 + usize::from(self.pec);
         let reg = [address];
 
         if data.is_empty() {
             return Err(PdError::InvalidParams.into());
         }
 
-        self.bus
-            .write_read(self.addr, &reg, buf.get_mut(..full_len).ok_or(PdError::InvalidParams)?)
-            .await
-            .map_err(Error::Bus)?;
-
-        let len = buf[0] as usize;
-        if len < data.len() {
-            // Just log a warning to workaround registers that might not match the TRM documentation
-            warn!(
-                "Under-read from TPS6699x register {:#02x}: expected {} bytes, got {}",
-                address,
-                data.len(),
-                len
-            );
+        let read_buf = buf.get_mut(..full_len).ok_or(PdError::InvalidParams)?;
+        let mut retries = self.retry.count;
+        loop {
+            match self.bus.write_read(self.addr, &reg, read_buf).await {
+                Ok(()) => break,
+                Err(e) if retries == 0 => return Err(Error::Bus(e)),
+                Err(_) => {
+                    retries -= 1;
+                    self.delay.delay_us(self.retry.delay_us).await;
+                }
+            }
         }
 
-        if len == 0xff || len == 0 {
-            // Controller is busy and can't respond
-            PdError::Busy.into()
+        if self.pec {
+            registers::decode_register_read_pec(self.addr, address, &buf, data).map_err(Error::Pd)
         } else {
-            data.copy_from_slice(buf.get(1..data.len() + 1).ok_or(PdError::InvalidParams)?);
-            Ok(())
+            registers::decode_register_read(address, &buf, data).map_err(Error::Pd)
         }
     }
 }
 
-/// Low-level TSP6699x driver, generic over I2C bus (B)
-pub struct Tps6699x<B: I2c> {
+/// Low-level TSP6699x driver, generic over I2C bus (B) and bus retry delay (D)
+pub struct Tps6699x<B: I2c, D: DelayNs = NoDelay> {
     pub(super) bus: B,
     /// I2C addresses for ports
     addr: [u8; MAX_SUPPORTED_PORTS],
     num_ports: usize,
+    /// Retry policy for bus errors
+    retry: RetryConfig,
+    /// Delay used between retries
+    delay: D,
+    /// Append and verify SMBus PEC bytes on register accesses
+    pec: bool,
 }
 
 impl<B: I2c> Tps6699x<B> {
     pub(super) fn new(bus: B, addr: [u8; MAX_SUPPORTED_PORTS], num_ports: usize) -> Self {
-        Self { bus, addr, num_ports }
+        Self {
+            bus,
+            addr,
+            num_ports,
+            retry: RetryConfig::default(),
+            delay: NoDelay,
+            pec: false,
+        }
     }
 
     pub fn new_tps66993(bus: B, addr: u8) -> Self {
@@ -117,24 +159,137 @@ impl<B: I2c> Tps6699x<B> {
     pub fn new_tps66994(bus: B, addr: [u8; 2]) -> Self {
         Self::new(bus, addr, TPS66994_NUM_PORTS)
     }
+}
+
+impl<B: I2c, D: DelayNs> Tps6699x<B, D> {
+    /// Retry register accesses that fail with a bus error according to `retry`, waiting with `delay` between attempts
+    pub fn with_retry<RD: DelayNs>(self, retry: RetryConfig, delay: RD) -> Tps6699x<B, RD> {
+        Tps6699x {
+            bus: self.bus,
+            addr: self.addr,
+            num_ports: self.num_ports,
+            retry,
+            delay,
+            pec: self.pec,
+        }
+    }
+
+    /// Returns the current bus retry policy
+    pub fn retry_config(&self) -> RetryConfig {
+        self.retry
+    }
+
+    /// Replace the bus retry policy, keeping the current retry delay
+    pub fn set_retry_config(&mut self, retry: RetryConfig) {
+        self.retry = retry;
+    }
+
+    /// Enable or disable SMBus packet error checking on register accesses
+    ///
+    /// When enabled a PEC byte is appended to every register write and verified on every register read, a mismatch
+    /// is reported as [`PdError::InvalidResponse`]. PEC must also be enabled in the controller configuration.
+    pub fn set_pec_enabled(&mut self, enabled: bool) {
+        self.pec = enabled;
+    }
+
+    /// Returns true if SMBus packet error checking is enabled
+    pub fn pec_enabled(&self) -> bool {
+        self.pec
+    }
 
     /// Get the I2C address for a port
     fn port_addr(&self, port: LocalPortId) -> Result<u8, Error<B::Error>> {
+        // The address array is always MAX_SUPPORTED_PORTS long, so also check against the number of ports to reject
+        // the second port on a single-port controller
+        if port.0 as usize >= self.num_ports {
+            return Err(PdError::InvalidPort.into());
+        }
+
         Ok(*self.addr.get(port.0 as usize).ok_or(PdError::InvalidPort)?)
     }
 
+    /// Set the I2C address used to access a port
+    ///
+    /// Returns [`PdError::InvalidParams`] if `addr` is zero. Only the driver's address is changed, nothing is written
+    /// to the controller.
+    pub fn set_port_addr(&mut self, port: LocalPortId, addr: u8) -> Result<(), Error<B::Error>> {
+        // Validate the port against the number of ports
+        self.port_addr(port)?;
+        if addr == 0 {
+            return Err(PdError::InvalidParams.into());
+        }
+
+        *self.addr.get_mut(port.0 as usize).ok_or(PdError::InvalidPort)? = addr;
+        Ok(())
+    }
+
     /// Returns number of ports
     pub fn num_ports(&self) -> usize {
         self.num_ports
     }
 
     /// Borrows the given port, providing exclusive access to it and therefore the underlying bus object
-    pub fn borrow_port(&mut self, port: LocalPortId) -> Result<Port<'_, B>, Error<B::Error>> {
+    pub fn borrow_port(&mut self, port: LocalPortId) -> Result<Port<'_, B, D>, Error<B::Error>> {
         let addr = self.port_addr(port)?;
-        Ok(Port {
-            bus: &mut self.bus,
-            addr,
-        })
+        Ok(self.borrow_addr(addr))
+    }
+
+    /// Borrows the bus for the given address without checking it against the number of ports
+    fn borrow_addr(&mut self, addr: u8) -> Port<'_, B, D> {
+        Port::new(&mut self.bus, addr, self.retry, &mut self.delay, self.pec)
+    }
+
+    /// Detect whether the controller has one or two ports, updating [`Self::num_ports`] to match
+    ///
+    /// The firmware version is read through both configured addresses. Only the TPS66994 responds on the second
+    /// address, a NAK there means a single port controller. Returns [`PdError::InvalidParams`] if the second address
+    /// responds with a different version, as it then belongs to some other device. If the second address is zero
+    /// only the first port is checked.
+    pub async fn detect_num_ports(&mut self) -> Result<usize, Error<B::Error>> {
+        let [addr0, addr1] = self.addr;
+        let version = self
+            .borrow_addr(addr0)
+            .into_registers()
+            .version()
+            .read_async()
+            .await?
+            .version();
+
+        let num_ports = if addr1 == 0 {
+            TPS66993_NUM_PORTS
+        } else {
+            match self.borrow_addr(addr1).into_registers().version().read_async().await {
+                Ok(r) if r.version() == version => TPS66994_NUM_PORTS,
+                Ok(r) => {
+                    error!(
+                        "Version mismatch between addresses {:#x} and {:#x}: {:#x} vs {:#x}",
+                        addr0,
+                        addr1,
+                        version,
+                        r.version()
+                    );
+                    return PdError::InvalidParams.into();
+                }
+                Err(Error::Bus(e)) if matches!(e.kind(), ErrorKind::NoAcknowledge(_)) => TPS66993_NUM_PORTS,
+                Err(e) => return Err(e),
+            }
+        };
+
+        debug!("Detected {} port(s)", num_ports);
+        self.num_ports = num_ports;
+        Ok(num_ports)
+    }
+
+    /// Get asserted interrupts on a port without clearing them
+    pub async fn get_interrupt(
+        &mut self,
+        port: LocalPortId,
+    ) -> Result<registers::field_sets::IntEventBus1, Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .int_event_bus_1()
+            .read_async()
+            .await
     }
 
     /// Clear interrupts on a port, returns asserted interrupts
@@ -260,11 +415,25 @@ impl<B: I2c> Tps6699x<B> {
     }
 
     /// Get controller operation mode
+    ///
+    /// Returns [`PdError::InvalidParams`] if the mode isn't recognized, see [`Self::get_mode_raw`] to read the value
+    /// regardless.
     pub async fn get_mode(&mut self) -> Result<Mode, Error<B::Error>> {
+        let mode = self.get_mode_raw().await?;
+        Mode::try_from(mode).map_err(Error::Pd)
+    }
+
+    /// Get the raw 4CC value of the controller operation mode
+    ///
+    /// The controller can transiently report modes not covered by [`Mode`], e.g. while switching banks.
+    pub async fn get_mode_raw(&mut self) -> Result<u32, Error<B::Error>> {
         // This is a controller-level command, shouldn't matter which port we use
-        let mode = self.borrow_port(PORT0)?.into_registers().mode().read_async().await?;
-        let mode = Mode::try_from(mode.mode()).map_err(Error::Pd)?;
-        Ok(mode)
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .mode()
+            .read_async()
+            .await
+            .map(|r| r.mode())
     }
 
     /// Get FW version
@@ -289,6 +458,65 @@ impl<B: I2c> Tps6699x<B> {
             .map(|r| r.customer_use())
     }
 
+    /// Set customer use value
+    ///
+    /// The value only persists across resets if the controller's flash is committed, otherwise the controller
+    /// reverts to the value from its configuration on the next boot.
+    pub async fn set_customer_use(&mut self, value: u64) -> Result<(), Error<B::Error>> {
+        // This is a controller-level command, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .customer_use()
+            .write_async(|r| r.set_customer_use(value))
+            .await
+    }
+
+    /// Get the build description, identifies the firmware build
+    pub async fn get_build_description(&mut self) -> Result<registers::device_info::BuildDescription, Error<B::Error>> {
+        let mut buf = [0u8; registers::device_info::BUILD_DESCRIPTION_LEN];
+        // This is a controller-level register, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .interface()
+            .read_register(
+                registers::device_info::BUILD_DESCRIPTION_ADDR,
+                (registers::device_info::BUILD_DESCRIPTION_LEN * 8) as u32,
+                &mut buf,
+            )
+            .await?;
+
+        Ok(registers::device_info::AsciiString(buf))
+    }
+
+    /// Get the device info string, includes the silicon revision
+    pub async fn get_device_info_string(
+        &mut self,
+    ) -> Result<registers::device_info::DeviceInfoString, Error<B::Error>> {
+        let mut buf = [0u8; registers::device_info::DEVICE_INFO_LEN];
+        // This is a controller-level register, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .interface()
+            .read_register(
+                registers::device_info::DEVICE_INFO_ADDR,
+                (registers::device_info::DEVICE_INFO_LEN * 8) as u32,
+                &mut buf,
+            )
+            .await?;
+
+        Ok(registers::device_info::AsciiString(buf))
+    }
+
+    /// Get controller identification for diagnostics
+    pub async fn get_device_info(&mut self) -> Result<registers::device_info::DeviceInfo, Error<B::Error>> {
+        Ok(registers::device_info::DeviceInfo {
+            device_info: self.get_device_info_string().await?,
+            build_description: self.get_build_description().await?,
+            fw_version: self.get_fw_version().await?,
+            customer_use: self.get_customer_use().await?,
+        })
+    }
+
     /// Get power path status
     pub async fn get_power_path_status(
         &mut self,
@@ -335,6 +563,22 @@ impl<B: I2c> Tps6699x<B> {
             .await
     }
 
+    /// Modify port control
+    ///
+    /// The register is read, passed to `f` and the result written back, so fields that `f` doesn't touch keep their
+    /// current values. Returns the value written.
+    pub async fn modify_port_control(
+        &mut self,
+        port: LocalPortId,
+        f: impl FnOnce(&mut registers::field_sets::PortControl) -> registers::field_sets::PortControl,
+    ) -> Result<registers::field_sets::PortControl, Error<B::Error>> {
+        self.borrow_port(port)?
+            .into_registers()
+            .port_control()
+            .modify_async(|r| f(r))
+            .await
+    }
+
     /// Get global system config
     pub async fn get_system_config(&mut self) -> Result<registers::field_sets::SystemConfig, Error<B::Error>> {
         // This is a controller-level command, shouldn't matter which port we use
@@ -358,6 +602,22 @@ impl<B: I2c> Tps6699x<B> {
             .await
     }
 
+    /// Modify global system config
+    ///
+    /// The register is read, passed to `f` and the result written back, so fields that `f` doesn't touch keep their
+    /// current values. Returns the value written.
+    pub async fn modify_system_config(
+        &mut self,
+        f: impl FnOnce(&mut registers::field_sets::SystemConfig) -> registers::field_sets::SystemConfig,
+    ) -> Result<registers::field_sets::SystemConfig, Error<B::Error>> {
+        // This is a controller-level command, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .system_config()
+            .modify_async(|r| f(r))
+            .await
+    }
+
     /// Enable/disable sourcing on a given port
     pub async fn enable_source(&mut self, port: LocalPortId, enable: bool) -> Result<(), Error<B::Error>> {
         let mut config = self.get_system_config().await?;
@@ -393,6 +653,18 @@ impl<B: I2c> Tps6699x<B> {
         Ok(registers::boot_flags::BootFlagsRaw(buf))
     }
 
+    /// Get the version and validity of each firmware bank
+    pub async fn get_bank_versions(
+        &mut self,
+    ) -> Result<[registers::boot_flags::BankInfo; registers::boot_flags::NUM_BANKS], Error<B::Error>> {
+        Ok(self.get_boot_flags().await?.bank_info())
+    }
+
+    /// Get the active bank and the validity of both firmware banks
+    pub async fn get_bank_status(&mut self) -> Result<registers::boot_flags::BankStatus, Error<B::Error>> {
+        Ok(self.get_boot_flags().await?.bank_status())
+    }
+
     /// Get DP status
     pub async fn get_dp_status(
         &mut self,
@@ -810,12 +1082,109 @@ impl<B: I2c> Tps6699x<B> {
     }
 }
 
+/// Two address sets resolved to the same physical controller, see [`probe_unique`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AddressConflict {
+    /// Index of the first conflicting address set
+    pub first: usize,
+    /// Index of the second conflicting address set
+    pub second: usize,
+}
+
+/// Base pattern written to the customer use register while probing
+const PROBE_PATTERN: u64 = u64::from_le_bytes(*b"TPSPROBE");
+
+/// Probing uses plain register accesses, without retries or PEC
+async fn read_customer_use<B: I2c>(bus: &mut B, addr: u8) -> Result<u64, Error<B::Error>> {
+    Port::new(bus, addr, RetryConfig::default(), &mut NoDelay, false)
+        .into_registers()
+        .customer_use()
+        .read_async()
+        .await
+        .map(|r| r.customer_use())
+}
+
+async fn write_customer_use<B: I2c>(bus: &mut B, addr: u8, value: u64) -> Result<(), Error<B::Error>> {
+    Port::new(bus, addr, RetryConfig::default(), &mut NoDelay, false)
+        .into_registers()
+        .customer_use()
+        .write_async(|r| r.set_customer_use(value))
+        .await
+}
+
+/// Write `pattern` through `addr` and look for it through every other address set
+///
+/// Returns [`PdError::Failed`] if the pattern doesn't read back through `addr` itself, since the other reads would
+/// then prove nothing.
+async fn probe_set<B: I2c>(
+    bus: &mut B,
+    addr_sets: &[[u8; MAX_SUPPORTED_PORTS]],
+    first: usize,
+    addr: u8,
+    pattern: u64,
+) -> Result<Option<AddressConflict>, Error<B::Error>> {
+    write_customer_use(bus, addr, pattern).await?;
+    if read_customer_use(bus, addr).await? != pattern {
+        return Err(Error::Pd(PdError::Failed));
+    }
+
+    for (second, other) in addr_sets.iter().enumerate() {
+        if second != first && read_customer_use(bus, other[0]).await? == pattern {
+            return Ok(Some(AddressConflict {
+                first: first.min(second),
+                second: first.max(second),
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Confirm that each address set refers to a distinct controller
+///
+/// Controllers strapped to the same address, or an address set listed twice, would otherwise
+/// silently alias each other. For each set, a distinguishing pattern is written to the controller's
+/// customer use register through its port 0 address, checked through that same address and then
+/// read back through every other set. Seeing the pattern from another set means both sets reach the
+/// same device. The original register contents are restored on every exit path before moving on.
+pub async fn probe_unique<B: I2c>(
+    bus: &mut B,
+    addr_sets: &[[u8; MAX_SUPPORTED_PORTS]],
+) -> Result<(), DeviceError<B::Error, AddressConflict>> {
+    for (first, set) in addr_sets.iter().enumerate() {
+        for (second, other) in addr_sets.iter().enumerate().skip(first + 1) {
+            if set[0] == other[0] {
+                return Err(DeviceError::Other(AddressConflict { first, second }));
+            }
+        }
+    }
+
+    for (first, set) in addr_sets.iter().enumerate() {
+        let addr = set[0];
+        let pattern = PROBE_PATTERN.wrapping_add(first as u64);
+        let original = read_customer_use(bus, addr).await?;
+
+        let probed = probe_set(bus, addr_sets, first, addr, pattern).await;
+        let restored = write_customer_use(bus, addr, original).await;
+
+        // A probe error is more relevant than a failed restore
+        if let Some(conflict) = probed? {
+            return Err(DeviceError::Other(conflict));
+        }
+        restored?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     extern crate std;
     use std::vec::Vec;
 
     use device_driver::AsyncRegisterInterface;
+    use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
     use embedded_hal_async::i2c::ErrorType;
     use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
     use embedded_usb_pd::pdo::source::Pdo;
@@ -855,7 +1224,7 @@ mod test {
         Ok(())
     }
 
-    /// Test that attempting to read more than the available number of bytes fails
+    /// Test that attempting to read more than the available number of bytes succeeds with the remainder zeroed
     async fn test_read_port_overread<const N: usize>(
         tps6699x: &mut Tps6699x<Mock>,
         port_id: LocalPortId,
@@ -867,17 +1236,22 @@ mod test {
         let mut response = Vec::with_capacity(N + 2);
         response.push(N as u8);
         response.splice(1..1, expected.iter().cloned());
-        response.push(0x00);
+        // Garbage past the reported length
+        response.push(0xff);
 
         tps6699x
             .bus
             .update_expectations(&[Transaction::write_read(expected_addr, std::vec![reg], response)]);
 
         let mut port = tps6699x.borrow_port(port_id)?;
-        let mut result = std::vec![0; N + 1];
+        let mut result = std::vec![0xaa; N + 1];
         let r = port.read_register(reg, (expected.len() * 8) as u32, &mut result).await;
         tps6699x.bus.done();
-        r
+        r?;
+
+        assert_eq!(&result[..N], &expected);
+        assert_eq!(result[N], 0);
+        Ok(())
     }
 
     /// Test that attempting to read less than the available number of bytes succeeds
@@ -952,6 +1326,56 @@ mod test {
             .unwrap();
     }
 
+    #[test]
+    fn test_borrow_port_out_of_range() {
+        let mut tps66994: Tps6699x<Mock> = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        assert!(tps66994.borrow_port(PORT0).is_ok());
+        assert!(tps66994.borrow_port(PORT1).is_ok());
+        assert!(matches!(
+            tps66994.borrow_port(LocalPortId(2)),
+            Err(Error::Pd(PdError::InvalidPort))
+        ));
+        tps66994.bus.done();
+
+        let mut tps66993: Tps6699x<Mock> = Tps6699x::new_tps66993(Mock::new(&[]), PORT0_ADDR0);
+        assert!(tps66993.borrow_port(PORT0).is_ok());
+        assert!(matches!(
+            tps66993.borrow_port(PORT1),
+            Err(Error::Pd(PdError::InvalidPort))
+        ));
+        assert!(matches!(
+            tps66993.borrow_port(LocalPortId(2)),
+            Err(Error::Pd(PdError::InvalidPort))
+        ));
+        tps66993.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_set_port_addr() {
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+
+        // Move port 0 to the second address set, port 1 is unchanged
+        assert_eq!(tps6699x.set_port_addr(PORT0, PORT0_ADDR1), Ok(()));
+        assert_eq!(tps6699x.addr, [PORT0_ADDR1, PORT1_ADDR0]);
+        test_rw_ports(&mut tps6699x, PORT0, PORT0_ADDR1).await;
+        test_rw_ports(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+
+        assert_eq!(tps6699x.set_port_addr(PORT1, 0), Err(Error::Pd(PdError::InvalidParams)));
+        assert_eq!(
+            tps6699x.set_port_addr(LocalPortId(2), PORT1_ADDR1),
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+        assert_eq!(tps6699x.addr, [PORT0_ADDR1, PORT1_ADDR0]);
+
+        let mut tps66993: Tps6699x<Mock> = Tps6699x::new_tps66993(Mock::new(&[]), PORT0_ADDR0);
+        assert_eq!(
+            tps66993.set_port_addr(PORT1, PORT1_ADDR0),
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+        assert_eq!(tps66993.addr, [PORT0_ADDR0, 0]);
+        tps66993.bus.done();
+    }
+
     /// Test on the first set of I2C addresses
     #[tokio::test]
     async fn test_rw_ports_0() {
@@ -972,6 +1396,182 @@ mod test {
         test_rw_ports(&mut tps6699x, PORT1, PORT1_ADDR1).await;
     }
 
+    /// Bus error reported when the controller NAKs its address
+    const NAK: ErrorKind = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+
+    #[tokio::test]
+    async fn test_detect_num_ports() {
+        let version = create_register_read(PORT0_ADDR0, 0x0F, TEST_FW_VERSION.to_le_bytes());
+        let version1 = create_register_read(PORT1_ADDR0, 0x0F, TEST_FW_VERSION.to_le_bytes());
+
+        // Both addresses respond with the same controller
+        let mut tps6699x = Tps6699x::new_tps66993(Mock::new(&[]), PORT0_ADDR0);
+        tps6699x.addr = ADDR0;
+        tps6699x.bus.update_expectations(&[version.clone(), version1.clone()]);
+        assert_eq!(tps6699x.detect_num_ports().await, Ok(TPS66994_NUM_PORTS));
+        assert_eq!(tps6699x.num_ports(), TPS66994_NUM_PORTS);
+        tps6699x.bus.done();
+
+        // Second address NAKs
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        tps6699x
+            .bus
+            .update_expectations(&[version.clone(), version1.clone().with_error(NAK)]);
+        assert_eq!(tps6699x.detect_num_ports().await, Ok(TPS66993_NUM_PORTS));
+        assert_eq!(tps6699x.num_ports(), TPS66993_NUM_PORTS);
+        assert_eq!(
+            tps6699x.get_port_status(PORT1).await,
+            Err(Error::Pd(PdError::InvalidPort))
+        );
+        tps6699x.bus.done();
+
+        // Second address belongs to a different device
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        tps6699x.bus.update_expectations(&[
+            version.clone(),
+            create_register_read(PORT1_ADDR0, 0x0F, (!TEST_FW_VERSION).to_le_bytes()),
+        ]);
+        assert_eq!(
+            tps6699x.detect_num_ports().await,
+            Err(Error::Pd(PdError::InvalidParams))
+        );
+        assert_eq!(tps6699x.num_ports(), TPS66994_NUM_PORTS);
+        tps6699x.bus.done();
+
+        // No second address to probe
+        let mut tps6699x = Tps6699x::new_tps66993(Mock::new(&[]), PORT0_ADDR0);
+        tps6699x.bus.update_expectations(&[version.clone()]);
+        assert_eq!(tps6699x.detect_num_ports().await, Ok(TPS66993_NUM_PORTS));
+        tps6699x.bus.done();
+
+        // Other bus errors are returned
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        tps6699x
+            .bus
+            .update_expectations(&[version, version1.with_error(ErrorKind::Other)]);
+        assert_eq!(tps6699x.detect_num_ports().await, Err(Error::Bus(ErrorKind::Other)));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_retry_read() {
+        use registers::field_sets::Status;
+
+        let read = create_register_read(PORT0_ADDR0, 0x1A, Status::new_zero());
+        let mock = Mock::new(&[read.clone().with_error(NAK), read.clone().with_error(NAK), read]);
+        let mut tps6699x = Tps6699x::new_tps66993(mock, PORT0_ADDR0).with_retry(
+            RetryConfig {
+                count: 2,
+                delay_us: 100,
+            },
+            Delay {},
+        );
+
+        assert_eq!(tps6699x.get_port_status(PORT0).await.unwrap(), Status::new_zero());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_retry_write() {
+        let data = [0x12, 0x34, 0x56, 0x78];
+        let write = create_register_write(PORT0_ADDR0, 0x08, data);
+        let mock = Mock::new(&[write.clone().with_error(NAK), write.clone().with_error(NAK), write]);
+        let mut tps6699x = Tps6699x::new_tps66993(mock, PORT0_ADDR0).with_retry(
+            RetryConfig {
+                count: 2,
+                delay_us: 100,
+            },
+            Delay {},
+        );
+
+        tps6699x
+            .borrow_port(PORT0)
+            .unwrap()
+            .write_register(0x08, 32, &data)
+            .await
+            .unwrap();
+        tps6699x.bus.done();
+    }
+
+    /// Test that the error is returned once all retries have been used
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        use registers::field_sets::Status;
+
+        let read = create_register_read(PORT0_ADDR0, 0x1A, Status::new_zero());
+        let mock = Mock::new(&[read.clone().with_error(NAK), read.with_error(NAK)]);
+        let mut tps6699x = Tps6699x::new_tps66993(mock, PORT0_ADDR0).with_retry(
+            RetryConfig {
+                count: 1,
+                delay_us: 100,
+            },
+            Delay {},
+        );
+
+        assert_eq!(tps6699x.get_port_status(PORT0).await, Err(Error::Bus(NAK)));
+        tps6699x.bus.done();
+    }
+
+    /// Test that bus errors are not retried by default
+    #[tokio::test]
+    async fn test_retry_default() {
+        use registers::field_sets::Status;
+
+        let read = create_register_read(PORT0_ADDR0, 0x1A, Status::new_zero()).with_error(NAK);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66993(Mock::new(&[read]), PORT0_ADDR0);
+
+        assert_eq!(tps6699x.retry_config(), RetryConfig::default());
+        assert_eq!(tps6699x.get_port_status(PORT0).await, Err(Error::Bus(NAK)));
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_pec_read() {
+        use registers::field_sets::Status;
+
+        // Zeroed status register followed by its PEC
+        let response = std::vec![5, 0, 0, 0, 0, 0, 0x84];
+        let mock = Mock::new(&[Transaction::write_read(PORT0_ADDR0, std::vec![0x1A], response)]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66993(mock, PORT0_ADDR0);
+        tps6699x.set_pec_enabled(true);
+        assert!(tps6699x.pec_enabled());
+
+        assert_eq!(tps6699x.get_port_status(PORT0).await.unwrap(), Status::new_zero());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_pec_read_mismatch() {
+        let response = std::vec![5, 0, 0, 0, 0, 0, 0x85];
+        let mock = Mock::new(&[Transaction::write_read(PORT0_ADDR0, std::vec![0x1A], response)]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66993(mock, PORT0_ADDR0);
+        tps6699x.set_pec_enabled(true);
+
+        assert_eq!(
+            tps6699x.get_port_status(PORT0).await,
+            Err(Error::Pd(PdError::InvalidResponse))
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_pec_write() {
+        let mock = Mock::new(&[Transaction::write(
+            PORT0_ADDR0,
+            std::vec![0x08, 4, 0x12, 0x34, 0x56, 0x78, 0x99],
+        )]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66993(mock, PORT0_ADDR0);
+        tps6699x.set_pec_enabled(true);
+
+        tps6699x
+            .borrow_port(PORT0)
+            .unwrap()
+            .write_register(0x08, 32, &[0x12, 0x34, 0x56, 0x78])
+            .await
+            .unwrap();
+        tps6699x.bus.done();
+    }
+
     async fn run_clear_interrupt(tps6699x: &mut Tps6699x<Mock>, port: LocalPortId, expected_addr: u8) {
         use registers::field_sets::IntEventBus1;
 
@@ -1086,6 +1686,21 @@ mod test {
         run_get_mode(&mut tps6699x, PORT0_ADDR0, Mode::Wtpr).await;
     }
 
+    #[tokio::test]
+    async fn test_get_mode_unknown() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+        let unknown = u32::from_le_bytes(*b"PTCH");
+
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x03, unknown.to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x03, unknown.to_le_bytes()),
+        ]);
+        assert_eq!(tps6699x.get_mode().await, Err(Error::Pd(PdError::InvalidParams)));
+        assert_eq!(tps6699x.get_mode_raw().await, Ok(unknown));
+        tps6699x.bus.done();
+    }
+
     #[tokio::test]
     async fn test_get_fw_version() {
         let mock = Mock::new(&[]);
@@ -1384,6 +1999,77 @@ mod test {
         tps6699x.bus.done();
     }
 
+    async fn run_modify_port_control(tps6699x: &mut Tps6699x<Mock>, port: LocalPortId, expected_addr: u8) {
+        use registers::field_sets::PortControl;
+
+        // Every bit set so that any unrelated bit that gets cleared shows up in the write
+        let initial = PortControl::from([0xFF; 8]);
+        let mut expected = initial;
+        expected.set_retimer_fw_update(false);
+
+        tps6699x.bus.update_expectations(&[
+            create_register_read(expected_addr, 0x29, initial),
+            create_register_write(expected_addr, 0x29, expected),
+        ]);
+
+        let result = tps6699x
+            .modify_port_control(port, |r| {
+                r.set_retimer_fw_update(false);
+                *r
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result, expected);
+        assert!(!result.retimer_fw_update());
+        assert!(result.fr_swap_enabled());
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_modify_port_control() {
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        run_modify_port_control(&mut tps6699x, PORT0, PORT0_ADDR0).await;
+        run_modify_port_control(&mut tps6699x, PORT1, PORT1_ADDR0).await;
+    }
+
+    #[tokio::test]
+    async fn test_modify_system_config() {
+        use registers::I2CTimeout;
+        use registers::field_sets::SystemConfig;
+
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        // Every bit set so that any unrelated bit that gets cleared shows up in the write
+        let initial = SystemConfig::from([0xFF; 15]);
+        let mut expected = initial;
+        expected.set_i_2_c_timeout(I2CTimeout::Timeout50Ms);
+        expected.set_enable_spm(false);
+
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x27, initial),
+            create_register_write(PORT0_ADDR0, 0x27, expected),
+        ]);
+
+        let result = tps6699x
+            .modify_system_config(|r| {
+                r.set_i_2_c_timeout(I2CTimeout::Timeout50Ms);
+                r.set_enable_spm(false);
+                *r
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result, expected);
+        assert_eq!(result.i_2_c_timeout(), I2CTimeout::Timeout50Ms);
+        assert!(!result.enable_spm());
+        assert!(result.enable_one_ufp_policy());
+        tps6699x.bus.done();
+    }
+
     #[tokio::test]
     async fn test_get_boot_flags() {
         use registers::boot_flags;
@@ -1401,6 +2087,83 @@ mod test {
         tps6699x.bus.done();
     }
 
+    #[tokio::test]
+    async fn test_get_bank_versions() {
+        use registers::boot_flags;
+
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        let mut flags: boot_flags::BootFlags = boot_flags::BootFlagsRaw([0u8; boot_flags::LEN]);
+        flags.set_bank0_valid(1);
+        flags.set_bank0_fw_version(0x01020304);
+        flags.set_bank1_fw_version(0x05060708);
+        tps6699x
+            .bus
+            .update_expectations(&[create_register_read(PORT0_ADDR0, 0x2D, flags.0)]);
+
+        let result = tps6699x.get_bank_versions().await.unwrap();
+        assert_eq!(
+            result,
+            [
+                boot_flags::BankInfo {
+                    version: 0x01020304,
+                    valid: true,
+                },
+                boot_flags::BankInfo {
+                    version: 0x05060708,
+                    valid: false,
+                },
+            ]
+        );
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_bank_status() {
+        use registers::boot_flags;
+
+        let mock = Mock::new(&[]);
+        let mut tps6699x: Tps6699x<Mock> = Tps6699x::new_tps66994(mock, ADDR0);
+
+        // Both banks valid, booted from bank 1
+        let mut flags: boot_flags::BootFlags = boot_flags::BootFlagsRaw([0u8; boot_flags::LEN]);
+        flags.set_active_bank(1);
+        flags.set_bank0_valid(1);
+        flags.set_bank1_valid(1);
+        tps6699x
+            .bus
+            .update_expectations(&[create_register_read(PORT0_ADDR0, 0x2D, flags.0)]);
+        assert_eq!(
+            tps6699x.get_bank_status().await.unwrap(),
+            boot_flags::BankStatus {
+                active_bank: 1,
+                bank0_valid: true,
+                bank1_valid: true,
+            }
+        );
+        tps6699x.bus.done();
+
+        // Bank 1 corrupt, controller is held in F211 mode
+        let mut flags: boot_flags::BootFlags = boot_flags::BootFlagsRaw([0u8; boot_flags::LEN]);
+        flags.set_active_bank(1);
+        flags.set_bank0_valid(1);
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x03, Mode::F211),
+            create_register_read(PORT0_ADDR0, 0x2D, flags.0),
+        ]);
+        assert_eq!(tps6699x.get_mode().await.unwrap(), Mode::F211);
+        assert_eq!(
+            tps6699x.get_bank_status().await.unwrap(),
+            boot_flags::BankStatus {
+                active_bank: 1,
+                bank0_valid: true,
+                bank1_valid: false,
+            }
+        );
+        tps6699x.bus.done();
+    }
+
     async fn run_get_dp_status(tps6699x: &mut Tps6699x<Mock>, port: LocalPortId, expected_addr: u8) {
         use registers::dp_status;
 
@@ -1783,4 +2546,149 @@ mod test {
         run_set_tx_identity(&mut tps6699x, PORT0, PORT0_ADDR0).await;
         run_set_tx_identity(&mut tps6699x, PORT1, PORT1_ADDR0).await;
     }
+
+    #[tokio::test]
+    async fn test_set_customer_use() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        tps6699x.bus.update_expectations(&[
+            create_register_write(PORT0_ADDR0, 0x06, TEST_CUSTOMER_USE.to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x06, TEST_CUSTOMER_USE.to_le_bytes()),
+        ]);
+
+        tps6699x.set_customer_use(TEST_CUSTOMER_USE).await.unwrap();
+        assert_eq!(tps6699x.get_customer_use().await.unwrap(), TEST_CUSTOMER_USE);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_get_device_info() {
+        const TEST_FW_VERSION: u32 = 0x00010203;
+
+        let mut device_info = [0u8; registers::device_info::DEVICE_INFO_LEN];
+        device_info[..14].copy_from_slice(b"TPS66994 HW001");
+        let mut build_description = [0u8; registers::device_info::BUILD_DESCRIPTION_LEN];
+        build_description[..9].copy_from_slice(b"TEST-BLD1");
+
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x2F, device_info),
+            create_register_read(PORT0_ADDR0, 0x2E, build_description),
+            create_register_read(PORT0_ADDR0, 0x0F, TEST_FW_VERSION.to_le_bytes()),
+            create_register_read(PORT0_ADDR0, 0x06, TEST_CUSTOMER_USE.to_le_bytes()),
+        ]);
+
+        let info = tps6699x.get_device_info().await.unwrap();
+        assert_eq!(info.device_info.as_str(), Ok("TPS66994 HW001"));
+        assert_eq!(info.build_description.as_str(), Ok("TEST-BLD1"));
+        assert_eq!(info.fw_version, TEST_FW_VERSION);
+        assert_eq!(info.customer_use, TEST_CUSTOMER_USE);
+        tps6699x.bus.done();
+    }
+
+    /// Test writing a crafted Tx Identity and reading it back
+    #[tokio::test]
+    async fn test_tx_identity_roundtrip() {
+        use registers::tx_identity::{self, ProductTypeDfp, TxIdentity};
+
+        let mut identity = TxIdentity::default();
+        identity
+            .set_vendor_id(0x1234)
+            .set_product_type_dfp(ProductTypeDfp::PdUsbHost)
+            .set_certification_test_id(0xAABBCCDD)
+            .set_usb_product_id(0x9ABC)
+            .set_dfp1_vdo(0x55667788);
+        let bytes: [u8; tx_identity::LEN] = *identity.as_bytes();
+
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        tps6699x.bus.update_expectations(&[
+            create_register_write(PORT1_ADDR0, 0x47, bytes),
+            create_register_read(PORT1_ADDR0, 0x47, bytes),
+        ]);
+
+        tps6699x.set_tx_identity(PORT1, identity).await.unwrap();
+        let identity = tps6699x.get_tx_identity(PORT1).await.unwrap();
+        assert_eq!(identity.as_bytes(), &bytes);
+        assert_eq!(identity.vendor_id(), 0x1234);
+        assert_eq!(identity.product_type_dfp(), ProductTypeDfp::PdUsbHost);
+        assert_eq!(identity.certification_test_id(), 0xAABBCCDD);
+        assert_eq!(identity.usb_product_id(), 0x9ABC);
+        assert_eq!(identity.dfp1_vdo(), 0x55667788);
+        tps6699x.bus.done();
+    }
+
+    #[tokio::test]
+    async fn test_probe_unique() {
+        let pattern0 = PROBE_PATTERN.to_le_bytes();
+        let pattern1 = PROBE_PATTERN.wrapping_add(1).to_le_bytes();
+        let original0 = TEST_CUSTOMER_USE.to_le_bytes();
+        let original1 = 0u64.to_le_bytes();
+
+        let mut mock = Mock::new(&[
+            create_register_read(PORT0_ADDR0, 0x06, original0),
+            create_register_write(PORT0_ADDR0, 0x06, pattern0),
+            create_register_read(PORT0_ADDR0, 0x06, pattern0),
+            create_register_read(PORT0_ADDR1, 0x06, original1),
+            create_register_write(PORT0_ADDR0, 0x06, original0),
+            create_register_read(PORT0_ADDR1, 0x06, original1),
+            create_register_write(PORT0_ADDR1, 0x06, pattern1),
+            create_register_read(PORT0_ADDR1, 0x06, pattern1),
+            create_register_read(PORT0_ADDR0, 0x06, original0),
+            create_register_write(PORT0_ADDR1, 0x06, original1),
+        ]);
+
+        probe_unique(&mut mock, &[ADDR0, ADDR1]).await.unwrap();
+        mock.done();
+    }
+
+    #[tokio::test]
+    async fn test_probe_unique_aliased() {
+        let pattern0 = PROBE_PATTERN.to_le_bytes();
+        let original0 = TEST_CUSTOMER_USE.to_le_bytes();
+
+        // Both sets reach the same device, so the second address reads back the first pattern
+        let mut mock = Mock::new(&[
+            create_register_read(PORT0_ADDR0, 0x06, original0),
+            create_register_write(PORT0_ADDR0, 0x06, pattern0),
+            create_register_read(PORT0_ADDR0, 0x06, pattern0),
+            create_register_read(PORT0_ADDR1, 0x06, pattern0),
+            create_register_write(PORT0_ADDR0, 0x06, original0),
+        ]);
+
+        assert_eq!(
+            probe_unique(&mut mock, &[ADDR0, ADDR1]).await,
+            Err(DeviceError::Other(AddressConflict { first: 0, second: 1 }))
+        );
+        mock.done();
+    }
+
+    #[tokio::test]
+    async fn test_probe_unique_readback_mismatch() {
+        let pattern0 = PROBE_PATTERN.to_le_bytes();
+        let original0 = TEST_CUSTOMER_USE.to_le_bytes();
+
+        // The pattern didn't stick on the probed address, the original value must still be restored
+        let mut mock = Mock::new(&[
+            create_register_read(PORT0_ADDR0, 0x06, original0),
+            create_register_write(PORT0_ADDR0, 0x06, pattern0),
+            create_register_read(PORT0_ADDR0, 0x06, original0),
+            create_register_write(PORT0_ADDR0, 0x06, original0),
+        ]);
+
+        assert_eq!(
+            probe_unique(&mut mock, &[ADDR0, ADDR1]).await,
+            Err(DeviceError::Error(Error::Pd(PdError::Failed)))
+        );
+        mock.done();
+    }
+
+    #[tokio::test]
+    async fn test_probe_unique_duplicate_addr() {
+        let mut mock = Mock::new(&[]);
+
+        assert_eq!(
+            probe_unique(&mut mock, &[ADDR0, ADDR1, ADDR0]).await,
+            Err(DeviceError::Other(AddressConflict { first: 0, second: 2 }))
+        );
+        mock.done();
+    }
 }
@@ -0,0 +1,401 @@
+//! Blocking, low-level TPS6699x driver. This module mirrors [`crate::asynchronous::internal`] for the core
+//! primitives, with register framing and command encoding shared between the two.
+use device_driver::RegisterInterface;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+use embedded_usb_pd::{Error, LocalPortId, PdError};
+
+use crate::command::*;
+use crate::{MAX_SUPPORTED_PORTS, Mode, PORT0, TPS66993_NUM_PORTS, TPS66994_NUM_PORTS, debug, registers};
+
+/// Wrapper to allow implementing device_driver traits on our I2C bus
+pub struct Port<'a, B: I2c> {
+    bus: &'a mut B,
+    addr: u8,
+    pec: bool,
+}
+
+impl<'a, B: I2c> Port<'a, B> {
+    pub fn into_registers(self) -> registers::Registers<Port<'a, B>> {
+        registers::Registers::new(self)
+    }
+}
+
+impl<B: I2c> RegisterInterface for Port<'_, B> {
+    type Error = Error<B::Error>;
+
+    type AddressType = u8;
+
+    fn write_register(&mut self, address: Self::AddressType, _size_bits: u32, data: &[u8]) -> Result<(), Self::Error> {
+        let mut buf = [0u8; registers::REGISTER_WRITE_BUF_LEN];
+        let buf = if self.pec {
+            registers::encode_register_write_pec(self.addr, address, data, &mut buf)?
+        } else {
+            registers::encode_register_write(address, data, &mut buf)?
+        };
+        self.bus.write(self.addr, buf).map_err(Error::Bus)
+    }
+
+    fn read_register(
+        &mut self,
+        address: Self::AddressType,
+        _size_bits: u32,
+        data: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let mut buf = [0u8; registers::REGISTER_READ_BUF_LEN];
+        // +1 for the length byte, +1 for the PEC byte if enabled
+        let full_len = data.len() + 1 + usize::from(self.pec);
+        let reg = [address];
+
+        if data.is_empty() {
+            return Err(PdError::InvalidParams.into());
+        }
+
+        self.bus
+            .write_read(self.addr, &reg, buf.get_mut(..full_len).ok_or(PdError::InvalidParams)?)
+            .map_err(Error::Bus)?;
+
+        if self.pec {
+            registers::decode_register_read_pec(self.addr, address, &buf, data).map_err(Error::Pd)
+        } else {
+            registers::decode_register_read(address, &buf, data).map_err(Error::Pd)
+        }
+    }
+}
+
+/// Low-level TSP6699x driver, generic over I2C bus (B)
+pub struct Tps6699x<B: I2c> {
+    bus: B,
+    /// I2C addresses for ports
+    addr: [u8; MAX_SUPPORTED_PORTS],
+    num_ports: usize,
+    /// Append and verify SMBus PEC bytes on register accesses
+    pec: bool,
+}
+
+impl<B: I2c> Tps6699x<B> {
+    fn new(bus: B, addr: [u8; MAX_SUPPORTED_PORTS], num_ports: usize) -> Self {
+        Self {
+            bus,
+            addr,
+            num_ports,
+            pec: false,
+        }
+    }
+
+    pub fn new_tps66993(bus: B, addr: u8) -> Self {
+        Self::new(bus, [addr, 0x00], TPS66993_NUM_PORTS)
+    }
+
+    pub fn new_tps66994(bus: B, addr: [u8; 2]) -> Self {
+        Self::new(bus, addr, TPS66994_NUM_PORTS)
+    }
+
+    /// Get the I2C address for a port
+    fn port_addr(&self, port: LocalPortId) -> Result<u8, Error<B::Error>> {
+        // The address array is always MAX_SUPPORTED_PORTS long, so also check against the number of ports to reject
+        // the second port on a single-port controller
+        if port.0 as usize >= self.num_ports {
+            return Err(PdError::InvalidPort.into());
+        }
+
+        Ok(*self.addr.get(port.0 as usize).ok_or(PdError::InvalidPort)?)
+    }
+
+    /// Returns number of ports
+    pub fn num_ports(&self) -> usize {
+        self.num_ports
+    }
+
+    /// Enable or disable SMBus packet error checking on register accesses, see
+    /// [`crate::asynchronous::internal::Tps6699x::set_pec_enabled`]
+    pub fn set_pec_enabled(&mut self, enabled: bool) {
+        self.pec = enabled;
+    }
+
+    /// Returns true if SMBus packet error checking is enabled
+    pub fn pec_enabled(&self) -> bool {
+        self.pec
+    }
+
+    /// Borrows the given port, providing exclusive access to it and therefore the underlying bus object
+    pub fn borrow_port(&mut self, port: LocalPortId) -> Result<Port<'_, B>, Error<B::Error>> {
+        let addr = self.port_addr(port)?;
+        Ok(Port {
+            bus: &mut self.bus,
+            addr,
+            pec: self.pec,
+        })
+    }
+
+    /// Clear interrupts on a port, returns asserted interrupts
+    pub fn clear_interrupt(
+        &mut self,
+        port: LocalPortId,
+    ) -> Result<registers::field_sets::IntEventBus1, Error<B::Error>> {
+        let p = self.borrow_port(port)?;
+        let mut registers = p.into_registers();
+
+        let flags = registers.int_event_bus_1().read()?;
+        // Clear interrupt if anything is set
+        if flags != registers::field_sets::IntEventBus1::new_zero() {
+            registers.int_clear_bus_1().write(|r| *r = flags)?;
+        }
+
+        Ok(flags)
+    }
+
+    /// Get port status
+    pub fn get_port_status(&mut self, port: LocalPortId) -> Result<registers::field_sets::Status, Error<B::Error>> {
+        self.borrow_port(port)?.into_registers().status().read()
+    }
+
+    /// Get the current mode of the controller
+    ///
+    /// Returns [`PdError::InvalidParams`] if the mode isn't recognized, see [`Self::get_mode_raw`] to read the value
+    /// regardless.
+    pub fn get_mode(&mut self) -> Result<Mode, Error<B::Error>> {
+        let mode = self.get_mode_raw()?;
+        Mode::try_from(mode).map_err(Error::Pd)
+    }
+
+    /// Get the raw 4CC value of the current mode of the controller
+    ///
+    /// The controller can transiently report modes not covered by [`Mode`], e.g. while switching banks.
+    pub fn get_mode_raw(&mut self) -> Result<u32, Error<B::Error>> {
+        // This is a controller-level command, shouldn't matter which port we use
+        self.borrow_port(PORT0)?
+            .into_registers()
+            .mode()
+            .read()
+            .map(|r| r.mode())
+    }
+
+    /// Sends a command without verifying that it is valid
+    pub fn send_command(
+        &mut self,
+        port: LocalPortId,
+        cmd: Command,
+        data: Option<&[u8]>,
+    ) -> Result<(), Error<B::Error>> {
+        let mut registers = self.borrow_port(port)?.into_registers();
+
+        if let Some(data) = data {
+            registers
+                .interface()
+                .write_register(registers::REG_DATA1, (data.len() * 8) as u32, data)?;
+        }
+
+        registers.cmd_1().write(|r| r.set_command(cmd as u32))?;
+
+        Ok(())
+    }
+
+    /// Check if the command has completed
+    pub fn check_command_complete(&mut self, port: LocalPortId) -> Result<bool, Error<B::Error>> {
+        let status = self.borrow_port(port)?.into_registers().cmd_1().read()?.command();
+
+        decode_command_complete(status).map_err(Error::Pd)
+    }
+
+    /// Read the result of a command
+    pub fn read_command_result(
+        &mut self,
+        port: LocalPortId,
+        data: Option<&mut [u8]>,
+        has_return_value: bool,
+    ) -> Result<ReturnValue, Error<B::Error>> {
+        if !self.check_command_complete(port)? {
+            return PdError::Busy.into();
+        }
+        debug!("command completed");
+
+        if let Some(ref data) = data
+            && data.len() > max_output_len(has_return_value)
+        {
+            // Data length too long
+            return PdError::InvalidParams.into();
+        }
+
+        // Read and return value and data
+        let mut buf = [0u8; registers::REG_DATA1_LEN];
+        self.borrow_port(port)?.into_registers().interface().read_register(
+            registers::REG_DATA1,
+            (registers::REG_DATA1_LEN * 8) as u32,
+            &mut buf,
+        )?;
+
+        decode_command_result(&buf, data, has_return_value).map_err(Error::Pd)
+    }
+
+    /// Reset the controller
+    pub fn reset(&mut self, delay: &mut impl DelayNs, args: &ResetArgs) -> Result<(), Error<B::Error>> {
+        // This is a controller-level command, shouldn't matter which port we use
+        let arg_bytes = args.to_bytes().map_err(Error::Pd)?;
+        self.send_command(PORT0, Command::Gaid, Some(&arg_bytes))?;
+
+        delay.delay_ms(RESET_DELAY_MS);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
+
+    use super::*;
+    use crate::test::*;
+    use crate::{ADDR0, PORT1};
+
+    /// Value used for generic command testing, no particular significance
+    const TEST_CMD_DATA: u64 = 0x12345678abcdef;
+
+    /// Delay that returns immediately
+    struct NoopDelay;
+
+    impl DelayNs for NoopDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    fn run_clear_interrupt(tps6699x: &mut Tps6699x<Mock>, port: LocalPortId, expected_addr: u8) {
+        use registers::field_sets::IntEventBus1;
+
+        // Create a fully asserted interrupt register
+        let int = !IntEventBus1::new_zero();
+        let mut transactions = Vec::new();
+
+        // Read the interrupt register
+        transactions.push(create_register_read(expected_addr, 0x14, int));
+
+        // Write to the interrupt clear register
+        transactions.push(create_register_write(expected_addr, 0x18, int));
+        tps6699x.bus.update_expectations(&transactions);
+
+        assert_eq!(tps6699x.clear_interrupt(port).unwrap(), int);
+        tps6699x.bus.done();
+    }
+
+    #[test]
+    fn test_clear_interrupt() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+
+        run_clear_interrupt(&mut tps6699x, PORT0, PORT0_ADDR0);
+        run_clear_interrupt(&mut tps6699x, PORT1, PORT1_ADDR0);
+    }
+
+    #[test]
+    fn test_get_port_status() {
+        use registers::field_sets::Status;
+
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        tps6699x
+            .bus
+            .update_expectations(&[create_register_read(PORT1_ADDR0, 0x1A, Status::new_zero())]);
+
+        assert_eq!(tps6699x.get_port_status(PORT1).unwrap(), Status::new_zero());
+        tps6699x.bus.done();
+    }
+
+    /// Test that a short read succeeds with the remainder zeroed
+    #[test]
+    fn test_read_register_short() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        tps6699x.bus.update_expectations(&[Transaction::write_read(
+            PORT0_ADDR0,
+            vec![0x06],
+            vec![2, 0x12, 0x34, 0xff, 0xff],
+        )]);
+
+        let mut data = [0xaa; 4];
+        tps6699x
+            .borrow_port(PORT0)
+            .unwrap()
+            .read_register(0x06, 32, &mut data)
+            .unwrap();
+        assert_eq!(data, [0x12, 0x34, 0x00, 0x00]);
+        tps6699x.bus.done();
+    }
+
+    #[test]
+    fn test_borrow_port_out_of_range() {
+        let mut tps6699x = Tps6699x::new_tps66993(Mock::new(&[]), PORT0_ADDR0);
+
+        assert!(tps6699x.borrow_port(PORT0).is_ok());
+        assert_eq!(tps6699x.borrow_port(PORT1).err(), Some(PdError::InvalidPort.into()));
+        tps6699x.bus.done();
+    }
+
+    #[test]
+    fn test_send_command() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let data = TEST_CMD_DATA.to_le_bytes();
+
+        tps6699x.bus.update_expectations(&[
+            create_register_write(PORT1_ADDR0, registers::REG_DATA1, data),
+            create_register_write(PORT1_ADDR0, 0x08, (Command::Invalid as u32).to_le_bytes()),
+        ]);
+
+        tps6699x.send_command(PORT1, Command::Invalid, Some(&data)).unwrap();
+        tps6699x.bus.done();
+    }
+
+    #[test]
+    fn test_read_command_result() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let mut reg_data = [0u8; registers::REG_DATA1_LEN];
+        reg_data[0] = ReturnValue::Rejected as u8;
+        reg_data[1..9].copy_from_slice(&TEST_CMD_DATA.to_le_bytes());
+
+        tps6699x.bus.update_expectations(&[
+            create_register_read(PORT0_ADDR0, 0x08, (Command::Success as u32).to_le_bytes()),
+            create_register_read(PORT0_ADDR0, registers::REG_DATA1, reg_data),
+        ]);
+
+        let mut data = [0u8; 8];
+        assert_eq!(
+            tps6699x.read_command_result(PORT0, Some(&mut data), true).unwrap(),
+            ReturnValue::Rejected
+        );
+        assert_eq!(u64::from_le_bytes(data), TEST_CMD_DATA);
+        tps6699x.bus.done();
+    }
+
+    #[test]
+    fn test_read_command_result_busy() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+
+        tps6699x.bus.update_expectations(&[create_register_read(
+            PORT0_ADDR0,
+            0x08,
+            (Command::Gaid as u32).to_le_bytes(),
+        )]);
+
+        assert_eq!(
+            tps6699x.read_command_result(PORT0, None, false),
+            Err(PdError::Busy.into())
+        );
+        tps6699x.bus.done();
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut tps6699x = Tps6699x::new_tps66994(Mock::new(&[]), ADDR0);
+        let args = ResetArgs {
+            switch_banks: true,
+            copy_bank: false,
+        };
+
+        tps6699x.bus.update_expectations(&[
+            create_register_write(PORT0_ADDR0, registers::REG_DATA1, args.to_bytes().unwrap()),
+            create_register_write(PORT0_ADDR0, 0x08, (Command::Gaid as u32).to_le_bytes()),
+        ]);
+
+        tps6699x.reset(&mut NoopDelay, &args).unwrap();
+        tps6699x.bus.done();
+    }
+}
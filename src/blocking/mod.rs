@@ -0,0 +1,2 @@
+//! Blocking driver, for use on platforms without an async executor
+pub mod internal;
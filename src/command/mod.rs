@@ -2,9 +2,10 @@ use bincode::de::Decoder;
 use bincode::enc::Encoder;
 use bincode::error::{DecodeError, EncodeError};
 use bincode::{Decode, Encode};
-use embedded_usb_pd::PdError;
+use embedded_usb_pd::{LocalPortId, PdError};
 
-use crate::u32_from_str;
+use crate::registers::REG_DATA1_LEN;
+use crate::{debug, fmt_4cc, u32_from_str, u32_to_str};
 
 pub mod gcdm;
 pub mod muxr;
@@ -24,6 +25,14 @@ pub enum Command {
     Invalid = u32_from_str(*b"!CMD"),
     /// Reset command
     Gaid = u32_from_str(*b"GAID"),
+    /// Abort the currently executing command
+    ///
+    /// # Input
+    /// None
+    ///
+    /// # Output
+    /// None, the aborted command completes with [`ReturnValue::Abort`]
+    Abrt = u32_from_str(*b"ABRT"),
 
     /// Simulate a port disconnect.
     ///
@@ -113,6 +122,42 @@ pub enum Command {
     /// [`ReturnValue`]
     HRST = u32_from_str(*b"HRST"),
 
+    /// Initiate a data role swap to DFP
+    ///
+    /// # Input
+    /// None
+    ///
+    /// # Output
+    /// [`ReturnValue`]
+    SwapToDfp = u32_from_str(*b"SWDF"),
+
+    /// Initiate a data role swap to UFP
+    ///
+    /// # Input
+    /// None
+    ///
+    /// # Output
+    /// [`ReturnValue`]
+    SwapToUfp = u32_from_str(*b"SWUF"),
+
+    /// Initiate a power role swap to source
+    ///
+    /// # Input
+    /// None
+    ///
+    /// # Output
+    /// [`ReturnValue`]
+    SwapToSource = u32_from_str(*b"SWSr"),
+
+    /// Initiate a power role swap to sink
+    ///
+    /// # Input
+    /// None
+    ///
+    /// # Output
+    /// [`ReturnValue`]
+    SwapToSink = u32_from_str(*b"SWSk"),
+
     /// Send VDM.
     ///
     /// # Input
@@ -151,6 +196,8 @@ impl TryFrom<u32> for Command {
             Ok(Command::Invalid)
         } else if Command::Gaid == value {
             Ok(Command::Gaid)
+        } else if Command::Abrt == value {
+            Ok(Command::Abrt)
         } else if Command::DISC == value {
             Ok(Command::DISC)
         } else if Command::Tfus == value {
@@ -181,10 +228,20 @@ impl TryFrom<u32> for Command {
             Ok(Command::Drst)
         } else if Command::HRST == value {
             Ok(Command::HRST)
+        } else if Command::SwapToDfp == value {
+            Ok(Command::SwapToDfp)
+        } else if Command::SwapToUfp == value {
+            Ok(Command::SwapToUfp)
+        } else if Command::SwapToSource == value {
+            Ok(Command::SwapToSource)
+        } else if Command::SwapToSink == value {
+            Ok(Command::SwapToSink)
         } else if Command::VDMs == value {
             Ok(Command::VDMs)
         } else if Command::Ucsi == value {
             Ok(Command::Ucsi)
+        } else if Command::GCdm == value {
+            Ok(Command::GCdm)
         } else {
             Err(PdError::InvalidParams)
         }
@@ -192,6 +249,16 @@ impl TryFrom<u32> for Command {
 }
 
 impl Command {
+    /// Look up the command with the given 4CC, e.g. `*b"TFUi"`, returns `None` if it isn't covered by [`Command`]
+    pub fn from_fourcc(fourcc: [u8; 4]) -> Option<Self> {
+        Self::try_from(u32_from_str(fourcc)).ok()
+    }
+
+    /// Returns the 4CC of this command as written to the command register
+    pub const fn as_fourcc(self) -> [u8; 4] {
+        u32_to_str(self as u32)
+    }
+
     /// Returns the delay in microseconds before checking that the command was valid
     pub fn valid_check_delay_us(self) -> u32 {
         match self {
@@ -205,7 +272,21 @@ impl Command {
         }
     }
 
-    /// Returns the timeout in milliseconds for the command to complete.
+    /// Returns the default timeout in milliseconds for the command to complete.
+    ///
+    /// | Command | Timeout |
+    /// |---|---|
+    /// | `Tfus` | 600 ms, the reset delay plus margin |
+    /// | `Gaid`, `Tfuc` | 1700 ms, the reset delay plus margin |
+    /// | `Aneg` and anything not listed | 1000 ms |
+    /// | `Trig` | 500 ms |
+    /// | `Srdy`, `Sryr` | 250 ms |
+    /// | `Tfui`, `Tfue`, `Tfud`, `Tfuq` | 200 ms |
+    /// | `Drst` | 100 ms |
+    ///
+    /// `Gaid`, `Tfus` and `Tfuc` reset the controller so they don't complete through the command complete interrupt,
+    /// their timeouts bound the whole reset sequence instead. The embassy driver allows overriding the timeout of
+    /// a single command, see `Tps6699x::execute_command_with_timeout`.
     pub const fn timeout_ms(self) -> u32 {
         match self {
             Command::Tfus => TFUS_DELAY_MS + 100,
@@ -237,12 +318,23 @@ impl PartialEq<u32> for Command {
     }
 }
 
+impl core::fmt::Display for Command {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            // The command register reads back as zero once a command has completed, this isn't a 4CC
+            Command::Success => write!(f, "Success"),
+            _ => fmt_4cc(f, *self as u32),
+        }
+    }
+}
+
 /// A status code, often in the first byte of the `DATAX` register after a command is executed.
 ///
 /// [`ReturnValue::Task0`] through [`ReturnValue::Task10`] are reserved for standard tasks and may be used by certain
 /// tasks for task-specific error codes. These should be treated as an error when encountered.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ReturnValue {
     /// Task completed successfully.
@@ -290,6 +382,19 @@ impl ReturnValue {
             _ => Err(error),
         }
     }
+
+    /// Map generic failures to the corresponding [`PdError`], passing through success and task-specific values
+    ///
+    /// [`ReturnValue::Abort`] maps to [`PdError::Timeout`], [`ReturnValue::Rejected`] to [`PdError::Rejected`] and
+    /// [`ReturnValue::RxLocked`] to [`PdError::Busy`].
+    pub fn checked(self) -> Result<Self, PdError> {
+        match self {
+            ReturnValue::Abort => Err(PdError::Timeout),
+            ReturnValue::Rejected => Err(PdError::Rejected),
+            ReturnValue::RxLocked => Err(PdError::Busy),
+            _ => Ok(self),
+        }
+    }
 }
 
 impl TryFrom<u8> for ReturnValue {
@@ -317,6 +422,19 @@ impl TryFrom<u8> for ReturnValue {
     }
 }
 
+impl core::fmt::Display for ReturnValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ReturnValue::Success => write!(f, "Success"),
+            ReturnValue::Abort => write!(f, "Aborted"),
+            ReturnValue::Rejected => write!(f, "Rejected"),
+            ReturnValue::RxLocked => write!(f, "Rx buffer locked"),
+            // Task-specific codes start right after RxLocked
+            _ => write!(f, "Task-specific result {}", *self as u8 - ReturnValue::Task0 as u8),
+        }
+    }
+}
+
 #[allow(clippy::from_over_into)]
 impl Into<Result<(), PdError>> for ReturnValue {
     fn into(self) -> Result<(), PdError> {
@@ -327,8 +445,59 @@ impl Into<Result<(), PdError>> for ReturnValue {
     }
 }
 
+/// Decode the command register, returns true if the last command has completed
+pub(crate) fn decode_command_complete(status: u32) -> Result<bool, PdError> {
+    match Command::from_fourcc(u32_to_str(status)) {
+        Some(Command::Success) => Ok(true),
+        Some(Command::Invalid) => Err(PdError::UnrecognizedCommand),
+        Some(cmd) => {
+            debug!("Command {:?} still in progress", cmd);
+            Ok(false)
+        }
+        // Raw command not covered by `Command`, still in progress
+        None => Ok(false),
+    }
+}
+
+/// Maximum length of the output data of a command
+pub(crate) const fn max_output_len(has_return_value: bool) -> usize {
+    if has_return_value {
+        // -1 because one byte is used for the return value
+        REG_DATA1_LEN - 1
+    } else {
+        REG_DATA1_LEN
+    }
+}
+
+/// Decode the contents of the data register after a command has completed, copying any output into `data`
+pub(crate) fn decode_command_result(
+    buf: &[u8],
+    data: Option<&mut [u8]>,
+    has_return_value: bool,
+) -> Result<ReturnValue, PdError> {
+    if has_return_value {
+        let return_code = buf.first().ok_or(PdError::InvalidParams)? & CMD_4CC_TASK_RETURN_CODE_MASK;
+        let ret = ReturnValue::try_from(return_code)?;
+        debug!("read_command_result: ret: {:?}", ret);
+        // Overwrite return value
+        if let Some(data) = data {
+            data.copy_from_slice(buf.get(1..=data.len()).ok_or(PdError::InvalidParams)?);
+        }
+        Ok(ret)
+    } else {
+        // No return value to check
+        debug!("read_command_result: Done");
+        if let Some(data) = data {
+            data.copy_from_slice(buf.get(..data.len()).ok_or(PdError::InvalidParams)?);
+        }
+        Ok(ReturnValue::Success)
+    }
+}
+
 /// Delay to wait for the device to restart
 pub(crate) const RESET_DELAY_MS: u32 = 1600;
+/// Interval between mode polls while waiting for the controller to become ready after a reset
+pub(crate) const READY_POLL_INTERVAL_MS: u32 = 50;
 /// Length of arguments for the reset command
 pub(crate) const RESET_ARGS_LEN: usize = 2;
 /// Constant to enable a feature in the command args
@@ -337,12 +506,30 @@ pub(crate) const RESET_FEATURE_ENABLE: u8 = 0xAC;
 /// Arugments to reset-like commands
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResetArgs {
-    /// True to swap banks on reset
+    /// True to boot from the inactive bank on reset, leaving the contents of both banks untouched
     pub switch_banks: bool,
-    /// True to copy the backup bank to the active bank
+    /// True to overwrite the active bank with the contents of the backup bank before booting
+    ///
+    /// Unlike [`Self::switch_banks`] this modifies flash, the previous contents of the active bank are lost.
     pub copy_bank: bool,
 }
+
+impl ResetArgs {
+    /// Encode the arguments as sent in the data register
+    pub(crate) fn to_bytes(self) -> Result<[u8; RESET_ARGS_LEN], PdError> {
+        let mut arg_bytes = [0u8; RESET_ARGS_LEN];
+        bincode::encode_into_slice(
+            self,
+            &mut arg_bytes,
+            bincode::config::standard().with_fixed_int_encoding(),
+        )
+        .map_err(|_| PdError::Serialize)?;
+        Ok(arg_bytes)
+    }
+}
+
 impl Encode for ResetArgs {
     fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
         let switch_banks = if self.switch_banks { RESET_FEATURE_ENABLE } else { 0 };
@@ -352,6 +539,39 @@ impl Encode for ResetArgs {
     }
 }
 
+/// How to wait for the controller to restart after a reset, see [`ResetConfig`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResetPollMode {
+    /// Wait for the full reset delay
+    #[default]
+    Fixed,
+    /// Poll the mode register at the given interval, returning as soon as the controller reports an application mode
+    Poll {
+        /// Interval between polls
+        interval_ms: u32,
+    },
+}
+
+/// Configuration for waiting on a reset to complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ResetConfig {
+    /// Time to wait for the reset, this is the timeout when polling
+    pub wait_ms: u32,
+    /// How to wait for the reset to complete
+    pub poll_mode: ResetPollMode,
+}
+
+impl Default for ResetConfig {
+    fn default() -> Self {
+        Self {
+            wait_ms: RESET_DELAY_MS,
+            poll_mode: ResetPollMode::Fixed,
+        }
+    }
+}
+
 /// Delay for completion of TFUs command
 pub(crate) const TFUS_DELAY_MS: u32 = 500;
 /// Length of TFUi arguments
@@ -361,6 +581,7 @@ pub(crate) const TFUI_ARGS_LEN: usize = 8;
 /// Arguments for TFUi command
 #[derive(Debug, Clone, Copy, Decode, Encode, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TfuiArgs {
     pub num_data_blocks_tx: u16,
     pub data_len: u16,
@@ -404,6 +625,7 @@ impl Encode for TfuqStatusQuery {
 /// Status of a block supplied to device
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum TfuqBlockStatus {
     Success = 0x0,
@@ -499,8 +721,25 @@ pub(crate) const TFUQ_RETURN_LEN: usize = 40;
 pub(crate) const TFUQ_RETURN_BLOCK_STATUS_LEN: usize = 13;
 
 /// Return data from TFUq command
+///
+/// Byte layout of the return data, the remaining bytes up to [`TFUQ_RETURN_LEN`] are reserved:
+///
+/// | Bytes  | Field                          |
+/// |--------|--------------------------------|
+/// | 0      | `active_host`                  |
+/// | 1      | `current_state`                |
+/// | 2-3    | Reserved                       |
+/// | 4      | `image_write_status`           |
+/// | 5-6    | `blocks_written_bitfield`      |
+/// | 7-19   | `block_status`                 |
+/// | 20-23  | `num_of_header_bytes_received` |
+/// | 24-25  | Reserved                       |
+/// | 26-29  | `num_of_data_bytes_received`   |
+/// | 30-31  | Reserved                       |
+/// | 32-33  | `num_of_app_config_updates`    |
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TfuqReturnValue {
     pub active_host: u8,
     pub current_state: u8,
@@ -514,19 +753,30 @@ pub struct TfuqReturnValue {
 
 impl<Context> Decode<Context> for TfuqReturnValue {
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+        // Byte 0
         let active_host = Decode::decode(decoder)?;
+        // Byte 1
         let current_state = Decode::decode(decoder)?;
+        // Bytes 2-3
         let _reserved: u16 = Decode::decode(decoder)?;
+        // Byte 4
         let image_write_status = Decode::decode(decoder)?;
+        // Bytes 5-6
         let blocks_written_bitfield = Decode::decode(decoder)?;
+        // Bytes 7-19
         let mut block_status = [TfuqBlockStatus::Success; TFUQ_RETURN_BLOCK_STATUS_LEN];
         for status in block_status.iter_mut() {
             *status = Decode::decode(decoder)?;
         }
+        // Bytes 20-23
         let num_of_header_bytes_received = Decode::decode(decoder)?;
+        // Bytes 24-25
         let _reserved: u16 = Decode::decode(decoder)?;
+        // Bytes 26-29
         let num_of_data_bytes_received = Decode::decode(decoder)?;
+        // Bytes 30-31
         let _reserved: u16 = Decode::decode(decoder)?;
+        // Bytes 32-33, the rest of the return data is reserved
         let num_of_app_config_updates = Decode::decode(decoder)?;
 
         Ok(TfuqReturnValue {
@@ -542,17 +792,20 @@ impl<Context> Decode<Context> for TfuqReturnValue {
     }
 }
 
-/// Srdy switch to enable
+/// Power switch selected by the `SRDY` command
+///
+/// Each port has its own 5V and external power path. Use [`SrdySwitch::for_port`] to select the switch
+/// belonging to a given port.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SrdySwitch {
-    /// PP 5V1
+    /// PP_5V1, 5V power path for port 1
     Pp5V1,
-    /// PP 5V2
+    /// PP_5V2, 5V power path for port 2
     Pp5V2,
-    /// PP Ext 1
+    /// PP_EXT1, external power path for port 1
     PpExt1,
-    /// PP Ext 2
+    /// PP_EXT2, external power path for port 2
     PpExt2,
     /// Automatically based on global config register
     AutoConfig,
@@ -560,6 +813,29 @@ pub enum SrdySwitch {
     AutoPolicy,
 }
 
+/// Kind of power switch, used to select a port-specific [`SrdySwitch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SrdySwitchKind {
+    /// PP_5V power path
+    Pp5V,
+    /// PP_EXT power path
+    PpExt,
+}
+
+impl SrdySwitch {
+    /// Returns the switch of the given kind for the given port
+    pub fn for_port(port: LocalPortId, kind: SrdySwitchKind) -> Result<Self, PdError> {
+        match (port.0, kind) {
+            (0, SrdySwitchKind::Pp5V) => Ok(SrdySwitch::Pp5V1),
+            (1, SrdySwitchKind::Pp5V) => Ok(SrdySwitch::Pp5V2),
+            (0, SrdySwitchKind::PpExt) => Ok(SrdySwitch::PpExt1),
+            (1, SrdySwitchKind::PpExt) => Ok(SrdySwitch::PpExt2),
+            _ => Err(PdError::InvalidPort),
+        }
+    }
+}
+
 impl From<SrdySwitch> for u8 {
     fn from(value: SrdySwitch) -> Self {
         match value {
@@ -573,11 +849,28 @@ impl From<SrdySwitch> for u8 {
     }
 }
 
+impl TryFrom<u8> for SrdySwitch {
+    type Error = PdError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x0 => Ok(SrdySwitch::Pp5V1),
+            0x1 => Ok(SrdySwitch::Pp5V2),
+            0x2 => Ok(SrdySwitch::PpExt1),
+            0x3 => Ok(SrdySwitch::PpExt2),
+            0x6 => Ok(SrdySwitch::AutoConfig),
+            0x7 => Ok(SrdySwitch::AutoPolicy),
+            _ => Err(PdError::InvalidParams),
+        }
+    }
+}
+
 /// Arguments for TFUd command
 #[allow(dead_code)]
 pub(crate) const TFUD_ARGS_LEN: usize = 8;
 #[derive(Debug, Decode, Encode, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TfudArgs {
     pub block_number: u16,
     pub data_len: u16,
@@ -709,6 +1002,54 @@ mod test {
         assert_eq!(decoded, args);
     }
 
+    /// Test decoding a full-length TFUq return with nonzero reserved bytes, which must not leak into any field
+    #[test]
+    fn test_tfuq_return_value_decode_full() {
+        let mut buf = [0xffu8; TFUQ_RETURN_LEN];
+        buf[0] = 0x11;
+        buf[1] = 0x22;
+        buf[4] = 0x33;
+        buf[5..7].copy_from_slice(&0x1234u16.to_le_bytes());
+        for (i, status) in buf[7..20].iter_mut().enumerate() {
+            // Statuses 0x0E to 0x1A
+            *status = 0x0E + i as u8;
+        }
+        buf[20..24].copy_from_slice(&0x44556677u32.to_le_bytes());
+        buf[26..30].copy_from_slice(&0x8899aabbu32.to_le_bytes());
+        buf[32..34].copy_from_slice(&0xccddu16.to_le_bytes());
+
+        let (decoded, len): (TfuqReturnValue, _) =
+            bincode::decode_from_slice(&buf, config::standard().with_fixed_int_encoding()).unwrap();
+        assert_eq!(len, 34);
+        assert_eq!(
+            decoded,
+            TfuqReturnValue {
+                active_host: 0x11,
+                current_state: 0x22,
+                image_write_status: 0x33,
+                blocks_written_bitfield: 0x1234,
+                block_status: [
+                    TfuqBlockStatus::DataRxInProgress,
+                    TfuqBlockStatus::DataValidAndAuthentic,
+                    TfuqBlockStatus::DataValidButRepeated,
+                    TfuqBlockStatus::DataNotValid,
+                    TfuqBlockStatus::DataInvalidId,
+                    TfuqBlockStatus::DataAuthFailure,
+                    TfuqBlockStatus::F911IdNotValid,
+                    TfuqBlockStatus::F911DataNotValid,
+                    TfuqBlockStatus::F911AuthFailure,
+                    TfuqBlockStatus::ImageDownloadTimeout,
+                    TfuqBlockStatus::BlockDownloadTimeout,
+                    TfuqBlockStatus::BlockWriteFailed,
+                    TfuqBlockStatus::SpecialCmdFailed,
+                ],
+                num_of_header_bytes_received: 0x44556677,
+                num_of_data_bytes_received: 0x8899aabb,
+                num_of_app_config_updates: 0xccdd,
+            }
+        );
+    }
+
     #[test]
     fn test_tfud_args_encode_decode() {
         let args = TfudArgs {
@@ -730,11 +1071,35 @@ mod test {
         assert_eq!(decoded, args);
     }
 
+    #[test]
+    fn test_timeout_ms() {
+        assert_eq!(Command::Tfus.timeout_ms(), 600);
+        assert_eq!(Command::Gaid.timeout_ms(), 1700);
+        assert_eq!(Command::Tfuc.timeout_ms(), 1700);
+        assert_eq!(Command::Aneg.timeout_ms(), 1000);
+        assert_eq!(Command::Srdy.timeout_ms(), 250);
+        assert_eq!(Command::Tfud.timeout_ms(), 200);
+        assert_eq!(Command::Drst.timeout_ms(), 100);
+        // Default
+        assert_eq!(Command::VDMs.timeout_ms(), 1000);
+
+        // Renegotiating takes longer than switching a power path
+        assert!(Command::Aneg.timeout_ms() > Command::Srdy.timeout_ms());
+    }
+
+    #[cfg(feature = "embassy")]
+    #[test]
+    fn test_timeout() {
+        assert_eq!(Command::Aneg.timeout(), embassy_time::Duration::from_millis(1000));
+        assert_eq!(Command::Tfus.timeout(), embassy_time::Duration::from_millis(600));
+    }
+
     #[test]
     fn test_try_from_u32_command() {
         assert_eq!(Command::try_from(Command::Success as u32).unwrap(), Command::Success);
         assert_eq!(Command::try_from(Command::Invalid as u32).unwrap(), Command::Invalid);
         assert_eq!(Command::try_from(Command::Gaid as u32).unwrap(), Command::Gaid);
+        assert_eq!(Command::try_from(Command::Abrt as u32).unwrap(), Command::Abrt);
         assert_eq!(Command::try_from(Command::DISC as u32).unwrap(), Command::DISC);
         assert_eq!(Command::try_from(Command::Tfus as u32).unwrap(), Command::Tfus);
         assert_eq!(Command::try_from(Command::Tfui as u32).unwrap(), Command::Tfui);
@@ -750,6 +1115,22 @@ mod test {
         assert_eq!(Command::try_from(Command::Muxr as u32).unwrap(), Command::Muxr);
         assert_eq!(Command::try_from(Command::Drst as u32).unwrap(), Command::Drst);
         assert_eq!(Command::try_from(Command::HRST as u32).unwrap(), Command::HRST);
+        assert_eq!(
+            Command::try_from(Command::SwapToDfp as u32).unwrap(),
+            Command::SwapToDfp
+        );
+        assert_eq!(
+            Command::try_from(Command::SwapToUfp as u32).unwrap(),
+            Command::SwapToUfp
+        );
+        assert_eq!(
+            Command::try_from(Command::SwapToSource as u32).unwrap(),
+            Command::SwapToSource
+        );
+        assert_eq!(
+            Command::try_from(Command::SwapToSink as u32).unwrap(),
+            Command::SwapToSink
+        );
         assert_eq!(Command::try_from(Command::VDMs as u32).unwrap(), Command::VDMs);
         assert_eq!(Command::try_from(Command::Ucsi as u32).unwrap(), Command::Ucsi);
         assert_eq!(Command::try_from(0xFFFFFFFFu32), Err(PdError::InvalidParams));
@@ -778,6 +1159,15 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_return_value_checked() {
+        assert_eq!(ReturnValue::Success.checked(), Ok(ReturnValue::Success));
+        assert_eq!(ReturnValue::Abort.checked(), Err(PdError::Timeout));
+        assert_eq!(ReturnValue::Rejected.checked(), Err(PdError::Rejected));
+        assert_eq!(ReturnValue::RxLocked.checked(), Err(PdError::Busy));
+        assert_eq!(ReturnValue::Task3.checked(), Ok(ReturnValue::Task3));
+    }
+
     #[test]
     fn test_try_from_u8_tfuq_block_status() {
         assert_eq!(TfuqBlockStatus::try_from(0x00).unwrap(), TfuqBlockStatus::Success);
@@ -883,4 +1273,209 @@ mod test {
             assert_eq!(TfuqBlockStatus::try_from(invalid), Err(PdError::InvalidParams));
         }
     }
+
+    #[test]
+    fn test_srdy_switch_u8_roundtrip() {
+        for value in [0x0, 0x1, 0x2, 0x3, 0x6, 0x7] {
+            let switch = SrdySwitch::try_from(value).unwrap();
+            assert_eq!(u8::from(switch), value);
+        }
+        for invalid in [0x4, 0x5].into_iter().chain(0x8..=0xFFu8) {
+            assert_eq!(SrdySwitch::try_from(invalid), Err(PdError::InvalidParams));
+        }
+    }
+
+    #[test]
+    fn test_srdy_switch_for_port() {
+        let port0 = LocalPortId(0);
+        let port1 = LocalPortId(1);
+        assert_eq!(SrdySwitch::for_port(port0, SrdySwitchKind::Pp5V), Ok(SrdySwitch::Pp5V1));
+        assert_eq!(SrdySwitch::for_port(port1, SrdySwitchKind::Pp5V), Ok(SrdySwitch::Pp5V2));
+        assert_eq!(
+            SrdySwitch::for_port(port0, SrdySwitchKind::PpExt),
+            Ok(SrdySwitch::PpExt1)
+        );
+        assert_eq!(
+            SrdySwitch::for_port(port1, SrdySwitchKind::PpExt),
+            Ok(SrdySwitch::PpExt2)
+        );
+        assert_eq!(
+            SrdySwitch::for_port(LocalPortId(2), SrdySwitchKind::PpExt),
+            Err(PdError::InvalidPort)
+        );
+    }
+
+    /// Every [`Command`] variant, the exhaustive match in the test below is a reminder to update this when adding one
+    const ALL_COMMANDS: [Command; 26] = [
+        Command::Success,
+        Command::Invalid,
+        Command::Gaid,
+        Command::Abrt,
+        Command::DISC,
+        Command::Tfus,
+        Command::Tfui,
+        Command::Tfuq,
+        Command::Tfue,
+        Command::Tfud,
+        Command::Tfuc,
+        Command::Srdy,
+        Command::Sryr,
+        Command::Aneg,
+        Command::Trig,
+        Command::Dbfg,
+        Command::Muxr,
+        Command::Drst,
+        Command::HRST,
+        Command::SwapToDfp,
+        Command::SwapToUfp,
+        Command::SwapToSource,
+        Command::SwapToSink,
+        Command::VDMs,
+        Command::Ucsi,
+        Command::GCdm,
+    ];
+
+    #[test]
+    fn test_command_fourcc_roundtrip() {
+        for command in ALL_COMMANDS {
+            match command {
+                Command::Success
+                | Command::Invalid
+                | Command::Gaid
+                | Command::Abrt
+                | Command::DISC
+                | Command::Tfus
+                | Command::Tfui
+                | Command::Tfuq
+                | Command::Tfue
+                | Command::Tfud
+                | Command::Tfuc
+                | Command::Srdy
+                | Command::Sryr
+                | Command::Aneg
+                | Command::Trig
+                | Command::Dbfg
+                | Command::Muxr
+                | Command::Drst
+                | Command::HRST
+                | Command::SwapToDfp
+                | Command::SwapToUfp
+                | Command::SwapToSource
+                | Command::SwapToSink
+                | Command::VDMs
+                | Command::Ucsi
+                | Command::GCdm => {}
+            }
+
+            assert_eq!(Command::from_fourcc(command.as_fourcc()), Some(command));
+            assert_eq!(Command::try_from(u32::from_le_bytes(command.as_fourcc())), Ok(command));
+        }
+
+        assert_eq!(Command::Tfui.as_fourcc(), *b"TFUi");
+        assert_eq!(Command::from_fourcc(*b"GCdm"), Some(Command::GCdm));
+        assert_eq!(Command::from_fourcc(*b"XXXX"), None);
+    }
+
+    #[test]
+    fn test_decode_command_complete() {
+        assert_eq!(decode_command_complete(Command::Success as u32), Ok(true));
+        assert_eq!(
+            decode_command_complete(Command::Invalid as u32),
+            Err(PdError::UnrecognizedCommand)
+        );
+        assert_eq!(decode_command_complete(Command::Tfui as u32), Ok(false));
+        // Raw commands not covered by `Command` are still reported as in progress
+        assert_eq!(decode_command_complete(u32_from_str(*b"XXXX")), Ok(false));
+    }
+
+    #[test]
+    fn test_command_display() {
+        extern crate std;
+        use std::format;
+
+        for (command, s) in [
+            (Command::Invalid, "!CMD"),
+            (Command::Tfui, "TFUi"),
+            (Command::Aneg, "ANeg"),
+            (Command::SwapToSource, "SWSr"),
+            (Command::Ucsi, "UCSI"),
+        ] {
+            assert_eq!(format!("{command}"), s);
+            let bytes: [u8; 4] = s.as_bytes().try_into().unwrap();
+            assert_eq!(Command::try_from(u32_from_str(bytes)), Ok(command));
+        }
+        assert_eq!(format!("{}", Command::Success), "Success");
+    }
+
+    #[test]
+    fn test_return_value_display() {
+        extern crate std;
+        use std::format;
+
+        assert_eq!(format!("{}", ReturnValue::Success), "Success");
+        assert_eq!(format!("{}", ReturnValue::Abort), "Aborted");
+        assert_eq!(format!("{}", ReturnValue::Rejected), "Rejected");
+        assert_eq!(format!("{}", ReturnValue::RxLocked), "Rx buffer locked");
+        assert_eq!(format!("{}", ReturnValue::Task0), "Task-specific result 0");
+        assert_eq!(format!("{}", ReturnValue::Task10), "Task-specific result 10");
+    }
+
+    #[cfg(feature = "serde")]
+    fn test_serde_roundtrip<T>(value: T)
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + core::fmt::Debug,
+    {
+        let mut buf = [0u8; 64];
+        let len = bincode::serde::encode_into_slice(&value, &mut buf, config::standard()).unwrap();
+        let (decoded, decoded_len): (T, usize) =
+            bincode::serde::decode_from_slice(&buf[..len], config::standard()).unwrap();
+        assert_eq!(decoded_len, len);
+        assert_eq!(decoded, value);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        test_serde_roundtrip(ReturnValue::Rejected);
+        test_serde_roundtrip(crate::Mode::App0);
+        test_serde_roundtrip(ResetArgs {
+            switch_banks: true,
+            copy_bank: false,
+        });
+        test_serde_roundtrip(TfuiArgs {
+            num_data_blocks_tx: 1,
+            data_len: 0x1234,
+            timeout_secs: 5,
+            broadcast_u16_address: 0x4321,
+        });
+        test_serde_roundtrip(TfudArgs {
+            block_number: 2,
+            data_len: 0x5678,
+            timeout_secs: 10,
+            broadcast_u16_address: 0x8765,
+        });
+        test_serde_roundtrip(TfuqReturnValue {
+            active_host: 1,
+            current_state: 2,
+            image_write_status: 3,
+            blocks_written_bitfield: 0x0003,
+            block_status: [TfuqBlockStatus::Success; TFUQ_RETURN_BLOCK_STATUS_LEN],
+            num_of_header_bytes_received: 0x800,
+            num_of_data_bytes_received: 0x4000,
+            num_of_app_config_updates: 1,
+        });
+
+        // Serde support must not affect the on-wire encoding used for the device
+        let mut buf = [0; RESET_ARGS_LEN];
+        bincode::encode_into_slice(
+            ResetArgs {
+                switch_banks: true,
+                copy_bank: true,
+            },
+            &mut buf,
+            config::standard().with_fixed_int_encoding(),
+        )
+        .unwrap();
+        assert_eq!(buf, [RESET_FEATURE_ENABLE, RESET_FEATURE_ENABLE]);
+    }
 }
@@ -1,6 +1,7 @@
 //! `VDMs`: Repeat transactions on I2C3m under certain conditions.
 
 use bitfield::bitfield;
+use embedded_usb_pd::PdError;
 
 use crate::error;
 
@@ -130,6 +131,64 @@ impl Input {
     }
 }
 
+impl Input {
+    /// Create an initiator VDM for `sop_target` from a VDM header followed by its VDOs
+    ///
+    /// Returns [`PdError::InvalidParams`] if `vdos` is empty or holds more than [`MAX_NUM_DATA_OBJECTS`] objects.
+    pub fn from_vdos(sop_target: SopTarget, vdos: &[u32]) -> Result<Self, PdError> {
+        if vdos.is_empty() || vdos.len() > MAX_NUM_DATA_OBJECTS {
+            return Err(PdError::InvalidParams);
+        }
+
+        let mut input = Self::new();
+        input.set_num_vdo(vdos.len() as u8);
+        input.set_version(Version::Two);
+        input.set_sop_target(sop_target);
+        input.set_initiator(true);
+        input.set_initiator_wait_timer(INITIATOR_WAIT_TIME_MS);
+        for (index, vdo) in vdos.iter().enumerate() {
+            input.set_vdo(index, *vdo);
+        }
+        Ok(input)
+    }
+}
+
+/// DisplayPort SVID
+pub const DP_SVID: u16 = 0xFF01;
+
+/// HPD state reported to a DisplayPort partner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HpdState {
+    /// HPD deasserted
+    Low,
+    /// HPD asserted
+    High,
+}
+
+impl Input {
+    /// Create a DisplayPort Attention VDM reporting `hpd`, with IRQ_HPD set if `irq` is true
+    ///
+    /// The DP Status VDO reports the port as a connected and enabled UFP_D, as defined in the VESA DisplayPort Alt Mode
+    /// specification. `object_position` is the position of the entered DP mode.
+    pub fn dp_hpd_attention(object_position: u8, hpd: HpdState, irq: bool) -> Self {
+        // SVID, structured VDM, SVDM version 2.0, object position, Attention command
+        let header = ((DP_SVID as u32) << 16) | (1 << 15) | (1 << 13) | ((object_position as u32 & 0x7) << 8) | 6;
+        // Connected as UFP_D, enabled, HPD state and IRQ_HPD
+        let status = 0b10 | (1 << 3) | (((hpd == HpdState::High) as u32) << 7) | ((irq as u32) << 8);
+
+        let mut input = Self::new();
+        input.set_num_vdo(2);
+        input.set_version(Version::Two);
+        input.set_sop_target(SopTarget::Sop);
+        // Attention doesn't have a response
+        input.set_initiator(false);
+        input.set_vdo(0, header);
+        input.set_vdo(1, status);
+        input
+    }
+}
+
 impl From<Input> for [u8; INPUT_LEN] {
     fn from(value: Input) -> Self {
         value.0.0
@@ -194,6 +253,57 @@ mod tests {
         assert_eq!(input2.0.initiator_wait_timer(), 0xAB);
     }
 
+    #[test]
+    fn test_from_vdos() {
+        // Discover Identity request to SOP'
+        const DISCOVER_IDENTITY: u32 = 0xFF00A001;
+
+        let input = Input::from_vdos(SopTarget::SopPrime, &[DISCOVER_IDENTITY]).unwrap();
+        assert_eq!(input.0.num_vdo(), 1);
+        assert!(input.0.version());
+        assert_eq!(input.0.sop_target(), 1);
+        assert!(input.0.initiator());
+        assert_eq!(input.0.initiator_wait_timer(), INITIATOR_WAIT_TIME_MS);
+        assert_eq!(input.0.vdo1(), DISCOVER_IDENTITY);
+        assert_eq!(input.0.vdo2(), 0);
+
+        let input = Input::from_vdos(SopTarget::SopDoublePrime, &[1, 2, 3, 4, 5, 6, 7]).unwrap();
+        assert_eq!(input.0.num_vdo(), 7);
+        assert_eq!(input.0.sop_target(), 2);
+        assert_eq!(input.0.vdo1(), 1);
+        assert_eq!(input.0.vdo7(), 7);
+
+        let input = Input::from_vdos(SopTarget::Sop, &[1]).unwrap();
+        assert_eq!(input.0.sop_target(), 0);
+
+        assert!(matches!(
+            Input::from_vdos(SopTarget::Sop, &[]),
+            Err(PdError::InvalidParams)
+        ));
+        assert!(matches!(
+            Input::from_vdos(SopTarget::Sop, &[0; MAX_NUM_DATA_OBJECTS + 1]),
+            Err(PdError::InvalidParams)
+        ));
+    }
+
+    #[test]
+    fn test_dp_hpd_attention() {
+        let input = Input::dp_hpd_attention(1, HpdState::High, false);
+        assert_eq!(input.0.num_vdo(), 2);
+        assert!(input.0.version());
+        assert_eq!(input.0.sop_target(), 0);
+        assert!(!input.0.initiator());
+        assert_eq!(input.0.vdo1(), 0xFF01A106);
+        assert_eq!(input.0.vdo2(), 0x8A);
+
+        let input = Input::dp_hpd_attention(1, HpdState::High, true);
+        assert_eq!(input.0.vdo2(), 0x18A);
+
+        let input = Input::dp_hpd_attention(2, HpdState::Low, false);
+        assert_eq!(input.0.vdo1(), 0xFF01A206);
+        assert_eq!(input.0.vdo2(), 0x0A);
+    }
+
     #[test]
     fn test_sop_target_from_conversions() {
         assert_eq!(u8::from(SopTarget::Sop), 0);
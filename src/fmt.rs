@@ -79,3 +79,19 @@ macro_rules! error {
         }
     };
 }
+
+#[cfg(test)]
+mod test {
+    /// Exercise every logging macro, CI builds the tests with every feature combination so this confirms the macros
+    /// compile and consume their arguments with logging disabled
+    #[test]
+    fn test_log_macros() {
+        let value = 0x12u8;
+
+        trace!("trace {}", value);
+        debug!("debug {} {}", value, value,);
+        info!("info");
+        warn!("warn {:#x}", value);
+        error!("error {:?}", value);
+    }
+}
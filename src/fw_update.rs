@@ -1,9 +1,10 @@
+use bincode::config;
 use embedded_usb_pd::PdError;
 
 use crate::asynchronous::fw_update::{app_config_block_metadata_offset, data_block_metadata_offset};
-use crate::command::{TfudArgs, TfuiArgs};
+use crate::command::{TfudArgs, TfuiArgs, TfuqBlockStatus, TfuqReturnValue};
 use crate::stream::{ReadOperation, SeekOperation};
-use crate::trace;
+use crate::{error, trace};
 
 /// Header block index
 pub const HEADER_BLOCK_INDEX: usize = 0;
@@ -42,18 +43,77 @@ pub const APP_CONFIG_METADATA_LEN: usize = 8;
 /// Maximum metadata length
 pub const MAX_METADATA_LEN: usize = 8;
 
+/// Maximum number of data blocks in an update image
+pub const MAX_DATA_BLOCKS: usize = APP_CONFIG_BLOCK_INDEX - DATA_BLOCK_START_INDEX;
+/// Maximum size of an update image
+///
+/// Consists of the image ID, header, the maximum number of full data blocks and an app config block, each
+/// with their metadata. The TPS66993 and TPS66994 use the same image layout so this value applies to both.
+pub const MAX_FW_IMAGE_SIZE: usize = HEADER_BLOCK_OFFSET
+    + HEADER_BLOCK_LEN
+    + MAX_DATA_BLOCKS * (DATA_BLOCK_METADATA_LEN + DATA_BLOCK_LEN)
+    + APP_CONFIG_METADATA_LEN
+    + DATA_BLOCK_LEN;
+
+/// Offset of the customer use value in an update image
+pub const CUSTOMER_USE_OFFSET: usize = 0x2A0AE;
+/// Length of the customer use value
+pub const CUSTOMER_USE_LEN: usize = 8;
+
 /// Delay after sending burst write for TFUi command
 pub const TFUI_BURST_WRITE_DELAY_MS: u32 = 250;
 /// Delay after sending burst write for TFUd command
 pub const TFUD_BURST_WRITE_DELAY_MS: u32 = 150;
 /// Default PD FW chunking size
 pub const UPDATE_CHUNK_LENGTH: usize = 1024;
+/// Maximum caller-set length of a single burst write, see [`FwUpdateTiming::chunk_size`]
+pub const MAX_BURST_WRITE_LENGTH: usize = 256;
+
+/// Burst write chunking and settling delays, see [`UpdateConfig::with_timing`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FwUpdateTiming {
+    /// Maximum length of a single burst write
+    ///
+    /// Defaults to [`UPDATE_CHUNK_LENGTH`]. Values set through [`UpdateConfig::with_timing`] must be between 1 and
+    /// [`MAX_BURST_WRITE_LENGTH`].
+    pub chunk_size: usize,
+    /// Delay after the header burst write before it's validated
+    pub tfui_delay_ms: u32,
+    /// Delay after each data or app config block burst write before it's validated
+    pub tfud_delay_ms: u32,
+}
+
+impl FwUpdateTiming {
+    /// Check that a caller-set chunk size is within 1 and [`MAX_BURST_WRITE_LENGTH`]
+    pub fn validate(&self) -> Result<(), PdError> {
+        if self.chunk_size == 0 || self.chunk_size > MAX_BURST_WRITE_LENGTH {
+            error!("Invalid burst write chunk size: {}", self.chunk_size);
+            return Err(PdError::InvalidParams);
+        }
+        Ok(())
+    }
+}
+
+impl Default for FwUpdateTiming {
+    fn default() -> Self {
+        Self {
+            chunk_size: UPDATE_CHUNK_LENGTH,
+            tfui_delay_ms: TFUI_BURST_WRITE_DELAY_MS,
+            tfud_delay_ms: TFUD_BURST_WRITE_DELAY_MS,
+        }
+    }
+}
 
 /// FW update configuration options
 #[derive(Clone, Default)]
 pub struct UpdateConfig {
     /// Optional override for the broadcast address.
     pub(crate) broadcast_addr: Option<u16>,
+    /// Burst write chunking and delays
+    pub(crate) timing: FwUpdateTiming,
+    /// Controllers are on separate buses, see [`UpdateConfig::with_separate_buses`]
+    pub(crate) separate_buses: bool,
 }
 
 impl UpdateConfig {
@@ -61,7 +121,293 @@ impl UpdateConfig {
     pub fn with_broadcast_addr(self, broadcast_addr: u16) -> Self {
         Self {
             broadcast_addr: Some(broadcast_addr),
+            ..self
+        }
+    }
+
+    /// Create a new update configuration with the given burst write timing
+    ///
+    /// Smaller chunks and longer delays may be needed on slow buses or behind I2C bridges with limited buffering.
+    /// Returns [`PdError::InvalidParams`] if the timing fails [`FwUpdateTiming::validate`].
+    pub fn with_timing(self, timing: FwUpdateTiming) -> Result<Self, PdError> {
+        timing.validate()?;
+        Ok(Self { timing, ..self })
+    }
+
+    /// Create a new update configuration for controllers that don't share a bus
+    ///
+    /// By default all controllers are assumed to be on the same bus, so each burst write is only sent through the first
+    /// controller and reaches the others through the shared broadcast address. With `separate_buses` set the burst
+    /// write is sent through every controller instead, so it goes out on each bus.
+    pub fn with_separate_buses(self, separate_buses: bool) -> Self {
+        Self { separate_buses, ..self }
+    }
+}
+
+/// FW update phase, reported through [`FwUpdateProgress`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FwUpdatePhase {
+    /// Entering FW update mode
+    EnteringMode,
+    /// Writing the header block
+    Header,
+    /// Writing data block `index` of `total`
+    DataBlock { index: usize, total: usize },
+    /// Writing the app config block
+    AppConfig,
+    /// Update committed
+    Complete,
+}
+
+/// FW update progress
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FwUpdateProgress {
+    /// Current phase
+    pub phase: FwUpdatePhase,
+    /// Number of bytes burst written so far
+    pub bytes_written: usize,
+}
+
+/// Information about an update image, see [`validate_fw_image`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FwImageInfo {
+    /// Total size of the update image
+    pub image_len: usize,
+    /// App image size from the header block
+    pub image_size: usize,
+    /// Number of data blocks
+    pub num_data_blocks: usize,
+    /// Customer use value, typically used to encode a custom FW version
+    ///
+    /// `None` if the image is too short to contain it, see [`get_customer_use_data`].
+    pub customer_use: Option<u64>,
+}
+
+/// Decode the block metadata at `metadata_offset` and check that the block data is contained in the image
+fn validate_block(image: &[u8], metadata_offset: usize) -> Result<TfudArgs, PdError> {
+    let data_offset = metadata_offset
+        .checked_add(DATA_BLOCK_METADATA_LEN)
+        .ok_or(PdError::InvalidParams)?;
+    let metadata = image.get(metadata_offset..data_offset).ok_or(PdError::InvalidParams)?;
+    let (args, _): (TfudArgs, _) = bincode::decode_from_slice(metadata, config::standard().with_fixed_int_encoding())
+        .map_err(|_| PdError::Serialize)?;
+
+    let data_end = data_offset
+        .checked_add(usize::from(args.data_len))
+        .ok_or(PdError::InvalidParams)?;
+    if data_end > image.len() {
+        error!("Block {} extends past the end of the image", args.block_number);
+        return Err(PdError::InvalidParams);
+    }
+
+    Ok(args)
+}
+
+/// Decode the update header metadata
+fn decode_header_args(image: &[u8]) -> Result<TfuiArgs, PdError> {
+    let metadata = image
+        .get(HEADER_METADATA_OFFSET..HEADER_METADATA_OFFSET + HEADER_METADATA_LEN)
+        .ok_or(PdError::InvalidParams)?;
+    let (args, _): (TfuiArgs, _) = bincode::decode_from_slice(metadata, config::standard().with_fixed_int_encoding())
+        .map_err(|_| PdError::Serialize)?;
+    Ok(args)
+}
+
+/// Decode the app image size from the header block
+fn decode_image_size(image: &[u8]) -> Result<usize, PdError> {
+    let image_size = image
+        .get(APP_IMAGE_SIZE_OFFSET..APP_IMAGE_SIZE_OFFSET + APP_IMAGE_SIZE_LEN)
+        .ok_or(PdError::InvalidParams)?;
+    let (image_size, _): (u32, _) =
+        bincode::decode_from_slice(image_size, config::standard().with_fixed_int_encoding())
+            .map_err(|_| PdError::Serialize)?;
+    Ok(image_size as usize)
+}
+
+/// Read the customer use value from an update image
+///
+/// Returns [`PdError::InvalidParams`] if the image is too short to contain it.
+pub fn get_customer_use_data(image: &[u8]) -> Result<u64, PdError> {
+    if image.len() < CUSTOMER_USE_OFFSET + CUSTOMER_USE_LEN {
+        error!("FW image too small for customer use data: {} bytes", image.len());
+        return Err(PdError::InvalidParams);
+    }
+
+    let customer_use = image
+        .get(CUSTOMER_USE_OFFSET..CUSTOMER_USE_OFFSET + CUSTOMER_USE_LEN)
+        .ok_or(PdError::InvalidParams)?;
+    let (customer_use, _): (u64, _) =
+        bincode::decode_from_slice(customer_use, config::standard().with_fixed_int_encoding())
+            .map_err(|_| PdError::Serialize)?;
+    Ok(customer_use)
+}
+
+/// Check that an update image is well-formed before starting an update
+///
+/// This decodes the update header and checks that every block the update would send is contained in the image. It
+/// catches truncated or otherwise malformed images before any controller is put into FW update mode, but can't detect
+/// an image built for a different part; the controller validates that when the header is sent.
+pub fn validate_fw_image(image: &[u8]) -> Result<FwImageInfo, PdError> {
+    if image.len() > MAX_FW_IMAGE_SIZE {
+        error!("FW image too large: {} bytes", image.len());
+        return Err(PdError::InvalidParams);
+    }
+
+    if image.len() < APP_IMAGE_SIZE_OFFSET + APP_IMAGE_SIZE_LEN {
+        error!("FW image too small: {} bytes", image.len());
+        return Err(PdError::InvalidParams);
+    }
+
+    let args = decode_header_args(image)?;
+    let num_data_blocks = usize::from(args.num_data_blocks_tx);
+    if num_data_blocks == 0 || num_data_blocks > MAX_DATA_BLOCKS {
+        error!("Invalid number of data blocks: {}", num_data_blocks);
+        return Err(PdError::InvalidParams);
+    }
+
+    if usize::from(args.data_len) != HEADER_BLOCK_LEN {
+        error!("Invalid header block length: {}", args.data_len);
+        return Err(PdError::InvalidParams);
+    }
+
+    let image_size = decode_image_size(image)?;
+
+    for block in 0..num_data_blocks {
+        validate_block(image, data_block_metadata_offset(block))?;
+    }
+    validate_block(image, app_config_block_metadata_offset(num_data_blocks, image_size)?)?;
+
+    // Not every image carries a customer use value, so it isn't required for the image to be valid
+    let customer_use = get_customer_use_data(image).ok();
+
+    Ok(FwImageInfo {
+        image_len: image.len(),
+        image_size,
+        num_data_blocks,
+        customer_use,
+    })
+}
+
+/// Update image, checked with [`validate_fw_image`] on construction
+///
+/// The image layout is computed once, so the block accessors don't need to repeat the offset and bounds math.
+#[derive(Clone, Copy)]
+pub struct FwImage<'a> {
+    bytes: &'a [u8],
+    info: FwImageInfo,
+}
+
+impl<'a> FwImage<'a> {
+    /// Create a new image, returns [`PdError::InvalidParams`] if `bytes` isn't a well-formed update image
+    pub fn new(bytes: &'a [u8]) -> Result<Self, PdError> {
+        let info = validate_fw_image(bytes)?;
+        Ok(Self { bytes, info })
+    }
+
+    /// Raw image contents
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Image layout information
+    pub fn info(&self) -> FwImageInfo {
+        self.info
+    }
+
+    /// Number of data blocks
+    pub fn num_data_blocks(&self) -> usize {
+        self.info.num_data_blocks
+    }
+
+    /// App image size from the header block
+    pub fn image_size(&self) -> usize {
+        self.info.image_size
+    }
+
+    /// Customer use value, typically used to encode a custom FW version, `None` if the image doesn't contain one
+    pub fn customer_use(&self) -> Option<u64> {
+        self.info.customer_use
+    }
+
+    /// Offset of the update header metadata
+    pub fn header_metadata_offset(&self) -> usize {
+        HEADER_METADATA_OFFSET
+    }
+
+    /// Offset of a data block's metadata, `block` is the zero-based data block index
+    pub fn data_block_metadata_offset(&self, block: usize) -> Result<usize, PdError> {
+        if block >= self.info.num_data_blocks {
+            return Err(PdError::InvalidParams);
         }
+        Ok(data_block_metadata_offset(block))
+    }
+
+    /// Offset of the app config block's metadata
+    pub fn app_config_block_metadata_offset(&self) -> Result<usize, PdError> {
+        app_config_block_metadata_offset(self.info.num_data_blocks, self.info.image_size)
+    }
+
+    /// Decode the update header metadata
+    pub fn header_args(&self) -> Result<TfuiArgs, PdError> {
+        decode_header_args(self.bytes)
+    }
+
+    /// Decode the block metadata at `metadata_offset` and return it along with the block data
+    pub fn block(&self, metadata_offset: usize) -> Result<(TfudArgs, &'a [u8]), PdError> {
+        let args = validate_block(self.bytes, metadata_offset)?;
+        let data_offset = metadata_offset
+            .checked_add(DATA_BLOCK_METADATA_LEN)
+            .ok_or(PdError::InvalidParams)?;
+        let data_end = data_offset
+            .checked_add(usize::from(args.data_len))
+            .ok_or(PdError::InvalidParams)?;
+        let data = self.bytes.get(data_offset..data_end).ok_or(PdError::InvalidParams)?;
+        Ok((args, data))
+    }
+}
+
+/// Set of blocks a controller has accepted, used to resume an interrupted update
+///
+/// A block is only considered accepted if it's marked as written in [`TfuqReturnValue::blocks_written_bitfield`] and
+/// its status reports it as valid and authentic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AcceptedBlocks(u16);
+
+impl AcceptedBlocks {
+    /// Determine the accepted blocks from the result of a `TFUq` status query
+    pub fn from_tfuq(status: &TfuqReturnValue) -> Self {
+        let mut blocks = 0;
+        for (index, block_status) in status.block_status.iter().enumerate() {
+            let written = status.blocks_written_bitfield & (1 << index) != 0;
+            let valid = if index == HEADER_BLOCK_INDEX {
+                *block_status == TfuqBlockStatus::HeaderValidAndAuthentic
+            } else {
+                matches!(
+                    block_status,
+                    TfuqBlockStatus::DataValidAndAuthentic | TfuqBlockStatus::DataValidButRepeated
+                )
+            };
+
+            if written && valid {
+                blocks |= 1 << index;
+            }
+        }
+
+        Self(blocks)
+    }
+
+    /// Returns the blocks accepted by both `self` and `other`
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Returns true if the block at `block_index` has been accepted
+    pub fn contains(self, block_index: usize) -> bool {
+        block_index < u16::BITS as usize && self.0 & (1 << block_index) != 0
     }
 }
 
@@ -205,7 +551,7 @@ impl State {
                     Ok(SeekOperation::new(app_config_block_metadata_offset(
                         update_args.num_data_blocks_tx.into(),
                         image_size,
-                    )))
+                    )?))
                 }
             }
             _ => {
@@ -245,9 +591,273 @@ impl State {
 
 #[cfg(test)]
 mod test {
+    extern crate std;
+    use std::vec;
+
     use super::*;
+    use crate::command::TFUQ_RETURN_BLOCK_STATUS_LEN;
     use crate::test::*;
 
+    /// Test validating a well-formed image
+    #[test]
+    fn test_validate_fw_image() {
+        let mut image = generate_mock_fw();
+        image[CUSTOMER_USE_OFFSET..CUSTOMER_USE_OFFSET + CUSTOMER_USE_LEN]
+            .copy_from_slice(&0x0102030405060708u64.to_le_bytes());
+
+        assert_eq!(
+            validate_fw_image(&image),
+            Ok(FwImageInfo {
+                image_len: image.len(),
+                image_size: MOCK_APP_SIZE as usize,
+                num_data_blocks: MOCK_DEFAULT_DATA_BLOCK_COUNT as usize,
+                customer_use: Some(0x0102030405060708),
+            })
+        );
+    }
+
+    /// Test that an image too short to contain the customer use value is still valid
+    #[test]
+    fn test_validate_fw_image_no_customer_use() {
+        const BLOCK_LEN: u16 = 16;
+        let config = config::standard().with_fixed_int_encoding();
+        let app_config_offset = app_config_block_metadata_offset(1, usize::from(BLOCK_LEN)).unwrap();
+        let mut image = vec![0u8; app_config_offset + DATA_BLOCK_METADATA_LEN + usize::from(BLOCK_LEN)];
+
+        let header = TfuiArgs {
+            num_data_blocks_tx: 1,
+            data_len: HEADER_BLOCK_LEN as u16,
+            timeout_secs: MOCK_TIMEOUT_SECS,
+            broadcast_u16_address: MOCK_BROADCAST_ADDR,
+        };
+        bincode::encode_into_slice(header, &mut image[HEADER_METADATA_OFFSET..], config).unwrap();
+        image[APP_IMAGE_SIZE_OFFSET..APP_IMAGE_SIZE_OFFSET + APP_IMAGE_SIZE_LEN]
+            .copy_from_slice(&u32::from(BLOCK_LEN).to_le_bytes());
+        for (block_number, offset) in [(0, data_block_metadata_offset(0)), (1, app_config_offset)] {
+            let block = TfudArgs {
+                block_number,
+                data_len: BLOCK_LEN,
+                timeout_secs: MOCK_TIMEOUT_SECS,
+                broadcast_u16_address: MOCK_BROADCAST_ADDR,
+            };
+            bincode::encode_into_slice(block, &mut image[offset..], config).unwrap();
+        }
+
+        assert!(image.len() < CUSTOMER_USE_OFFSET + CUSTOMER_USE_LEN);
+        assert_eq!(
+            validate_fw_image(&image),
+            Ok(FwImageInfo {
+                image_len: image.len(),
+                image_size: usize::from(BLOCK_LEN),
+                num_data_blocks: 1,
+                customer_use: None,
+            })
+        );
+        assert_eq!(FwImage::new(&image).unwrap().customer_use(), None);
+    }
+
+    /// Test burst write timing validation
+    #[test]
+    fn test_update_config_with_timing() {
+        let timing = FwUpdateTiming {
+            chunk_size: 128,
+            tfui_delay_ms: 500,
+            tfud_delay_ms: 300,
+        };
+        let config = UpdateConfig::default()
+            .with_broadcast_addr(0x42)
+            .with_timing(timing)
+            .unwrap();
+        assert_eq!(config.timing, timing);
+        // Other options are preserved
+        assert_eq!(config.broadcast_addr, Some(0x42));
+
+        assert_eq!(UpdateConfig::default().timing, FwUpdateTiming::default());
+        assert_eq!(FwUpdateTiming::default().chunk_size, UPDATE_CHUNK_LENGTH);
+
+        let max = FwUpdateTiming {
+            chunk_size: MAX_BURST_WRITE_LENGTH,
+            ..timing
+        };
+        assert!(UpdateConfig::default().with_timing(max).is_ok());
+
+        // The default is larger, but caller-set chunk sizes must not exceed the limit
+        for chunk_size in [0, MAX_BURST_WRITE_LENGTH + 1, UPDATE_CHUNK_LENGTH] {
+            let invalid = FwUpdateTiming { chunk_size, ..timing };
+            assert_eq!(invalid.validate(), Err(PdError::InvalidParams));
+            assert!(UpdateConfig::default().with_timing(invalid).is_err());
+        }
+    }
+
+    /// Test reading the customer use value
+    #[test]
+    fn test_get_customer_use_data() {
+        let mut image = generate_mock_fw();
+        image[CUSTOMER_USE_OFFSET..CUSTOMER_USE_OFFSET + CUSTOMER_USE_LEN]
+            .copy_from_slice(&0x0102030405060708u64.to_le_bytes());
+        assert_eq!(get_customer_use_data(&image), Ok(0x0102030405060708));
+
+        // Exactly long enough
+        assert_eq!(
+            get_customer_use_data(&image[..CUSTOMER_USE_OFFSET + CUSTOMER_USE_LEN]),
+            Ok(0x0102030405060708)
+        );
+
+        // Too short
+        assert_eq!(
+            get_customer_use_data(&image[..CUSTOMER_USE_OFFSET + CUSTOMER_USE_LEN - 1]),
+            Err(PdError::InvalidParams)
+        );
+        assert_eq!(get_customer_use_data(&[]), Err(PdError::InvalidParams));
+    }
+
+    /// Test rejecting malformed images
+    #[test]
+    fn test_app_config_block_metadata_offset_overflow() {
+        assert_eq!(
+            app_config_block_metadata_offset(1, usize::MAX - IMAGE_ID_LEN),
+            Err(PdError::InvalidParams)
+        );
+        assert_eq!(
+            app_config_block_metadata_offset(usize::MAX / DATA_BLOCK_METADATA_LEN + 1, 0),
+            Err(PdError::InvalidParams)
+        );
+        assert_eq!(
+            app_config_block_metadata_offset(1, 0),
+            Ok(IMAGE_ID_LEN + HEADER_METADATA_LEN + HEADER_BLOCK_LEN + DATA_BLOCK_METADATA_LEN)
+        );
+    }
+
+    #[test]
+    fn test_validate_block_offset_overflow() {
+        let image = generate_mock_fw();
+
+        // Metadata offsets near the top of the address space must be rejected rather than wrap
+        for metadata_offset in [
+            u32::MAX as usize,
+            u32::MAX as usize - DATA_BLOCK_METADATA_LEN + 1,
+            usize::MAX - DATA_BLOCK_METADATA_LEN + 1,
+            usize::MAX,
+        ] {
+            assert_eq!(validate_block(&image, metadata_offset), Err(PdError::InvalidParams));
+        }
+
+        let image = FwImage::new(&image).unwrap();
+        assert!(matches!(image.block(usize::MAX), Err(PdError::InvalidParams)));
+    }
+
+    #[test]
+    fn test_validate_fw_image_malformed() {
+        let image = generate_mock_fw();
+
+        // Too short to contain the app image size
+        assert_eq!(
+            validate_fw_image(&image[..APP_IMAGE_SIZE_OFFSET + 2]),
+            Err(PdError::InvalidParams)
+        );
+
+        // Truncated in the middle of a data block
+        assert_eq!(
+            validate_fw_image(&image[..MOCK_DATA_BLOCK_HEADER_5_OFFSET + 0x100]),
+            Err(PdError::InvalidParams)
+        );
+
+        // Truncated in the app config block
+        assert_eq!(
+            validate_fw_image(&image[..APP_CONFIG_HEADER_OFFSET + 0x10]),
+            Err(PdError::InvalidParams)
+        );
+
+        // Too many data blocks
+        let mut bad_header = image.clone();
+        bad_header[HEADER_METADATA_OFFSET..HEADER_METADATA_OFFSET + 2]
+            .copy_from_slice(&(MAX_DATA_BLOCKS as u16 + 1).to_le_bytes());
+        assert_eq!(validate_fw_image(&bad_header), Err(PdError::InvalidParams));
+
+        // No data blocks
+        bad_header[HEADER_METADATA_OFFSET..HEADER_METADATA_OFFSET + 2].copy_from_slice(&0u16.to_le_bytes());
+        assert_eq!(validate_fw_image(&bad_header), Err(PdError::InvalidParams));
+
+        // App size that moves the app config block past the end of the image
+        let mut bad_size = image.clone();
+        bad_size[APP_IMAGE_SIZE_OFFSET..APP_IMAGE_SIZE_OFFSET + APP_IMAGE_SIZE_LEN]
+            .copy_from_slice(&(MOCK_APP_SIZE + 0x1000).to_le_bytes());
+        assert_eq!(validate_fw_image(&bad_size), Err(PdError::InvalidParams));
+
+        // App size from the header that would overflow the app config offset math
+        bad_size[APP_IMAGE_SIZE_OFFSET..APP_IMAGE_SIZE_OFFSET + APP_IMAGE_SIZE_LEN]
+            .copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(validate_fw_image(&bad_size), Err(PdError::InvalidParams));
+
+        // Too large
+        let mut too_large = image.clone();
+        too_large.resize(MAX_FW_IMAGE_SIZE + 1, 0);
+        assert_eq!(validate_fw_image(&too_large), Err(PdError::InvalidParams));
+    }
+
+    /// Test the image accessors
+    #[test]
+    fn test_fw_image() {
+        let bytes = generate_mock_fw();
+        let image = FwImage::new(&bytes).unwrap();
+
+        assert_eq!(image.bytes().len(), bytes.len());
+        assert_eq!(image.info(), validate_fw_image(&bytes).unwrap());
+        assert_eq!(image.num_data_blocks(), MOCK_DEFAULT_DATA_BLOCK_COUNT as usize);
+        assert_eq!(image.image_size(), MOCK_APP_SIZE as usize);
+        assert_eq!(image.header_metadata_offset(), HEADER_METADATA_OFFSET);
+        assert_eq!(image.header_args(), Ok(MOCK_UPDATE_HEADER));
+
+        assert_eq!(image.data_block_metadata_offset(0), Ok(MOCK_DATA_BLOCK_HEADER_0_OFFSET));
+        assert_eq!(
+            image.data_block_metadata_offset(10),
+            Ok(MOCK_DATA_BLOCK_HEADER_10_OFFSET)
+        );
+        assert_eq!(image.data_block_metadata_offset(11), Err(PdError::InvalidParams));
+        assert_eq!(image.app_config_block_metadata_offset(), Ok(APP_CONFIG_HEADER_OFFSET));
+
+        let (args, data) = image.block(MOCK_DATA_BLOCK_HEADER_10_OFFSET).unwrap();
+        assert_eq!(args, MOCK_DATA_BLOCK_HEADER_10);
+        assert_eq!(data.len(), MOCK_LAST_DATA_BLOCK_SIZE as usize);
+
+        let (args, data) = image.block(APP_CONFIG_HEADER_OFFSET).unwrap();
+        assert_eq!(args, APP_CONFIG_HEADER);
+        assert_eq!(data.len(), MOCK_APP_CONFIG_SIZE as usize);
+
+        assert!(FwImage::new(&bytes[..0x1000]).is_err());
+    }
+
+    /// Test determining accepted blocks from a partially completed update
+    #[test]
+    fn test_accepted_blocks() {
+        let mut block_status = [TfuqBlockStatus::Success; TFUQ_RETURN_BLOCK_STATUS_LEN];
+        block_status[HEADER_BLOCK_INDEX] = TfuqBlockStatus::HeaderValidAndAuthentic;
+        block_status[1] = TfuqBlockStatus::DataValidAndAuthentic;
+        block_status[2] = TfuqBlockStatus::DataValidButRepeated;
+        block_status[3] = TfuqBlockStatus::DataRxInProgress;
+        // Valid status, but not marked as written
+        block_status[4] = TfuqBlockStatus::DataValidAndAuthentic;
+
+        let accepted = AcceptedBlocks::from_tfuq(&mock_tfuq_status(0b1111, block_status));
+        assert!(accepted.contains(HEADER_BLOCK_INDEX));
+        assert!(accepted.contains(1));
+        assert!(accepted.contains(2));
+        assert!(!accepted.contains(3));
+        assert!(!accepted.contains(4));
+        assert!(!accepted.contains(APP_CONFIG_BLOCK_INDEX));
+        assert!(!accepted.contains(16));
+
+        // Header reporting a data status isn't accepted
+        block_status[HEADER_BLOCK_INDEX] = TfuqBlockStatus::DataValidAndAuthentic;
+        let header_invalid = AcceptedBlocks::from_tfuq(&mock_tfuq_status(0b1111, block_status));
+        assert!(!header_invalid.contains(HEADER_BLOCK_INDEX));
+
+        let both = accepted.intersection(header_invalid);
+        assert!(!both.contains(HEADER_BLOCK_INDEX));
+        assert!(both.contains(1));
+        assert!(both.contains(2));
+    }
+
     /// Test successful state transitions for [`next_read`]
     #[test]
     fn test_success_next_read() {
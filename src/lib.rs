@@ -3,6 +3,8 @@
 use embedded_usb_pd::{LocalPortId, PdError};
 
 pub mod asynchronous;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod command;
 pub mod fmt;
 pub mod fw_update;
@@ -57,6 +59,7 @@ impl<BE, T> From<DeviceError<BE, T>> for embedded_usb_pd::Error<BE> {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mode {
     /// Boot mode
     Boot = u32_from_str(*b"BOOT"),
@@ -96,6 +99,12 @@ impl TryFrom<u32> for Mode {
     }
 }
 
+impl core::fmt::Display for Mode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        fmt_4cc(f, *self as u32)
+    }
+}
+
 #[allow(clippy::from_over_into)]
 impl Into<[u8; 4]> for Mode {
     fn into(self) -> [u8; 4] {
@@ -109,6 +118,21 @@ pub(crate) const fn u32_from_str(bytes: [u8; U32_STR_LEN]) -> u32 {
     u32::from_le_bytes(bytes).to_le()
 }
 
+/// Converts a u32 back into the 4-byte string it was created from, reverse of [`u32_from_str`]
+pub(crate) const fn u32_to_str(value: u32) -> [u8; U32_STR_LEN] {
+    value.to_le_bytes()
+}
+
+/// Writes a 4CC value as text, non-printable bytes are written as `?`
+pub(crate) fn fmt_4cc(f: &mut core::fmt::Formatter<'_>, value: u32) -> core::fmt::Result {
+    use core::fmt::Write;
+
+    for byte in u32_to_str(value) {
+        f.write_char(if byte.is_ascii_graphic() { char::from(byte) } else { '?' })?;
+    }
+    Ok(())
+}
+
 /// Common unit test functions
 #[cfg(test)]
 pub(crate) mod test {
@@ -124,7 +148,10 @@ pub(crate) mod test {
     use tokio::time::sleep;
 
     use super::*;
-    use crate::command::{TFUD_ARGS_LEN, TfudArgs, TfuiArgs};
+    use crate::command::{
+        TFUD_ARGS_LEN, TFUQ_RETURN_BLOCK_STATUS_LEN, TFUQ_RETURN_LEN, TfudArgs, TfuiArgs, TfuqBlockStatus,
+        TfuqReturnValue,
+    };
     use crate::fw_update::{APP_IMAGE_SIZE_OFFSET, HEADER_BLOCK_LEN, HEADER_METADATA_OFFSET};
 
     pub const PORT0_ADDR0: u8 = ADDR0[0];
@@ -311,6 +338,22 @@ pub(crate) mod test {
         buffer
     }
 
+    /// Create a TFUq return value with the given written bitfield and block statuses
+    pub fn mock_tfuq_status(
+        blocks_written: u16,
+        block_status: [TfuqBlockStatus; TFUQ_RETURN_BLOCK_STATUS_LEN],
+    ) -> TfuqReturnValue {
+        let mut buf = [0u8; TFUQ_RETURN_LEN];
+        buf[5..7].copy_from_slice(&blocks_written.to_le_bytes());
+        for (byte, status) in buf[7..20].iter_mut().zip(block_status) {
+            *byte = status as u8;
+        }
+
+        bincode::decode_from_slice(&buf, bincode::config::standard().with_fixed_int_encoding())
+            .unwrap()
+            .0
+    }
+
     // The current USB PD code is focused on decoding PDOs, not creating them
     // TODO: https://github.com/OpenDevicePartnership/embedded-usb-pd/issues/30
     /// Create a test source PDO fixed raw value
@@ -433,4 +476,23 @@ mod tests {
         assert_eq!(Mode::try_from(0x52505457).unwrap(), Mode::Wtpr);
         assert_eq!(Mode::try_from(0u32), Err(PdError::InvalidParams));
     }
+
+    #[test]
+    fn test_mode_display() {
+        extern crate std;
+        use std::format;
+
+        for (mode, s) in [
+            (Mode::Boot, "BOOT"),
+            (Mode::F211, "F211"),
+            (Mode::App0, "APP0"),
+            (Mode::App1, "APP1"),
+            (Mode::Wtpr, "WTPR"),
+        ] {
+            assert_eq!(format!("{mode}"), s);
+            let bytes: [u8; U32_STR_LEN] = s.as_bytes().try_into().unwrap();
+            assert_eq!(Mode::try_from(u32_from_str(bytes)), Ok(mode));
+            assert_eq!(u32_to_str(mode as u32), bytes);
+        }
+    }
 }
@@ -1,5 +1,6 @@
 //! Types and functions related to register 0x2D, boot flags
 use bitfield::bitfield;
+use embedded_usb_pd::LocalPortId;
 
 /// The address of the boot flags register.
 pub const ADDR: u8 = 0x2D;
@@ -63,9 +64,75 @@ bitfield! {
 /// Provide this type alias for convenience
 pub type BootFlags = BootFlagsRaw<[u8; LEN]>;
 
+/// Number of firmware banks
+pub const NUM_BANKS: usize = 2;
+
+/// Firmware bank information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BankInfo {
+    /// Application firmware version in the bank
+    pub version: u32,
+    /// Bank contains valid application code
+    pub valid: bool,
+}
+
+/// Active bank and health of both firmware banks
+///
+/// If the bank the controller tried to boot from is corrupt, it remains in `F211`
+/// mode waiting for a new image. Use this to decide which bank to update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BankStatus {
+    /// Bank the device booted from
+    pub active_bank: u8,
+    /// Bank 0 contains valid application code
+    pub bank0_valid: bool,
+    /// Bank 1 contains valid application code
+    pub bank1_valid: bool,
+}
+
+impl<T: AsRef<[u8]>> BootFlagsRaw<T> {
+    /// Active bank and validity of both firmware banks
+    pub fn bank_status(&self) -> BankStatus {
+        BankStatus {
+            active_bank: self.active_bank(),
+            bank0_valid: self.bank0_valid() != 0,
+            bank1_valid: self.bank1_valid() != 0,
+        }
+    }
+
+    /// The controller booted in dead battery mode, powered through `port`
+    pub fn is_dead_battery(&self, port: LocalPortId) -> bool {
+        let provider = match port.0 {
+            0 => self.db_port_a_power_provider(),
+            1 => self.db_port_b_power_provider(),
+            _ => 0,
+        };
+
+        self.dead_battery_flag() != 0 && provider != 0
+    }
+
+    /// Information for each firmware bank, indexed by bank number
+    pub fn bank_info(&self) -> [BankInfo; NUM_BANKS] {
+        [
+            BankInfo {
+                version: self.bank0_fw_version(),
+                valid: self.bank0_valid() != 0,
+            },
+            BankInfo {
+                version: self.bank1_fw_version(),
+                valid: self.bank1_valid() != 0,
+            },
+        ]
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{BootFlags, BootFlagsRaw, LEN};
+    use embedded_usb_pd::LocalPortId;
+
+    use super::{BankInfo, BankStatus, BootFlags, BootFlagsRaw, LEN};
 
     #[test]
     fn test_boot_flags_nonzero_roundtrip() {
@@ -124,4 +191,68 @@ mod tests {
         assert_eq!(flags2.adc_in_value(), 0xABCD);
         assert_eq!(flags2.adc_in_index(), 0x1234);
     }
+
+    #[test]
+    fn test_boot_flags_is_dead_battery() {
+        let mut flags: BootFlags = BootFlagsRaw([0u8; LEN]);
+        flags.set_db_port_a_power_provider(1);
+        // Provider bits are ignored without the dead battery flag
+        assert!(!flags.is_dead_battery(LocalPortId(0)));
+
+        flags.set_dead_battery_flag(1);
+        assert!(flags.is_dead_battery(LocalPortId(0)));
+        assert!(!flags.is_dead_battery(LocalPortId(1)));
+        assert!(!flags.is_dead_battery(LocalPortId(2)));
+    }
+
+    #[test]
+    fn test_boot_flags_bank_info() {
+        let mut flags: BootFlags = BootFlagsRaw([0u8; LEN]);
+        flags.set_bank0_valid(1);
+        flags.set_bank0_fw_version(0x01020304);
+        flags.set_bank1_valid(0);
+        flags.set_bank1_fw_version(0x05060708);
+
+        assert_eq!(
+            flags.bank_info(),
+            [
+                BankInfo {
+                    version: 0x01020304,
+                    valid: true,
+                },
+                BankInfo {
+                    version: 0x05060708,
+                    valid: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_boot_flags_bank_status() {
+        let mut flags: BootFlags = BootFlagsRaw([0u8; LEN]);
+        flags.set_active_bank(1);
+        flags.set_bank0_valid(1);
+        flags.set_bank1_valid(1);
+        assert_eq!(
+            flags.bank_status(),
+            BankStatus {
+                active_bank: 1,
+                bank0_valid: true,
+                bank1_valid: true,
+            }
+        );
+
+        // Bank 1 corrupt, device falls back to bank 0
+        flags.set_active_bank(0);
+        flags.set_bank1_valid(0);
+        assert_eq!(
+            flags.bank_status(),
+            BankStatus {
+                active_bank: 0,
+                bank0_valid: true,
+                bank1_valid: false,
+            }
+        );
+    }
 }
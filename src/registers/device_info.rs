@@ -0,0 +1,84 @@
+//! Build description (`0x2E`) and device info (`0x2F`) registers.
+//!
+//! Both registers hold null-padded ASCII strings. The device info string includes the silicon revision, the build
+//! description identifies the firmware build.
+use embedded_usb_pd::PdError;
+
+/// The address of the `Build Description` register.
+pub const BUILD_DESCRIPTION_ADDR: u8 = 0x2E;
+
+/// The length of the `Build Description` register, in bytes.
+///
+/// This exceeds the maximum supported length by the [`device_driver`] crate.
+pub const BUILD_DESCRIPTION_LEN: usize = 49;
+
+/// The address of the `Device Info` register.
+pub const DEVICE_INFO_ADDR: u8 = 0x2F;
+
+/// The length of the `Device Info` register, in bytes.
+///
+/// This exceeds the maximum supported length by the [`device_driver`] crate.
+pub const DEVICE_INFO_LEN: usize = 40;
+
+/// Null-padded ASCII string register contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AsciiString<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> AsciiString<N> {
+    /// Returns the string up to the first null byte
+    ///
+    /// Returns [`PdError::InvalidResponse`] if the contents aren't valid UTF-8.
+    pub fn as_str(&self) -> Result<&str, PdError> {
+        let len = self.0.iter().position(|&b| b == 0).unwrap_or(N);
+        let bytes = self.0.get(..len).ok_or(PdError::InvalidResponse)?;
+        core::str::from_utf8(bytes).map_err(|_| PdError::InvalidResponse)
+    }
+}
+
+impl<const N: usize> Default for AsciiString<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+/// Contents of the `Build Description` register
+pub type BuildDescription = AsciiString<BUILD_DESCRIPTION_LEN>;
+
+/// Contents of the `Device Info` register
+pub type DeviceInfoString = AsciiString<DEVICE_INFO_LEN>;
+
+/// Controller identification, intended for diagnostics and telemetry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceInfo {
+    /// Device info string, includes the silicon revision
+    pub device_info: DeviceInfoString,
+    /// Firmware build identifier
+    pub build_description: BuildDescription,
+    /// Firmware version
+    pub fw_version: u32,
+    /// Customer use value, commonly used to track the configuration version
+    pub customer_use: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_str() {
+        let mut bytes = [0u8; DEVICE_INFO_LEN];
+        bytes[..4].copy_from_slice(b"TEST");
+        assert_eq!(AsciiString(bytes).as_str(), Ok("TEST"));
+
+        // Not null-terminated
+        assert_eq!(AsciiString(*b"TEST").as_str(), Ok("TEST"));
+
+        // Empty
+        assert_eq!(BuildDescription::default().as_str(), Ok(""));
+
+        // Invalid UTF-8
+        assert_eq!(AsciiString([0xff, 0x00]).as_str(), Err(PdError::InvalidResponse));
+    }
+}
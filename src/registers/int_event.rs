@@ -0,0 +1,410 @@
+//! Typed events decoded from the interrupt event register (`0x14`).
+//!
+//! [`PdEvent`] names each interrupt source in [`IntEventBus1`] so that consumers can match on events rather than
+//! calling the individual field getters.
+use super::field_sets::IntEventBus1;
+
+/// Number of events in [`IntEventBus1`]
+pub const NUM_EVENTS: usize = 65;
+
+/// Interrupt source in [`IntEventBus1`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PdEvent {
+    /// A PD hard reset has been performed
+    HardReset,
+    /// A plug has been inserted or removed
+    PlugEvent,
+    /// Power swap completed
+    PowerSwapCompleted,
+    /// Data swap completed
+    DataSwapCompleted,
+    /// Fast role swap completed
+    FastRoleSwapCompleted,
+    /// Source capabilities updated
+    SourceCapUpdated,
+    /// Asserts under an implicit contract or an explicit contract when PS_RDY has been received
+    SinkReady,
+    /// Overcurrent, see `Tps6699x::get_faults` for details
+    Overcurrent,
+    /// Attention received
+    AttentionReceived,
+    /// VDM received
+    VdmReceived,
+    /// New contract as consumer
+    NewConsumerContract,
+    /// New contract as provider
+    NewProviderContract,
+    /// Source capabilities received
+    SourceCapsReceived,
+    /// Sink capabilities received
+    SinkCapsReceived,
+    /// Power swap requested
+    PowerSwapRequested,
+    /// Data swap requested
+    DataSwapRequested,
+    /// USB host present
+    UsbHostPresent,
+    /// Set when USB host status transitions to anything other than present
+    UsbHostNotPresent,
+    /// Power path status register changed
+    PowerPathSwitchChanged,
+    /// Data status register changed
+    DataStatusUpdated,
+    /// Status register changed
+    StatusUpdated,
+    /// PD status register changed
+    PdStatusUpdated,
+    /// Command 1 completed
+    Cmd1Completed,
+    /// Command 2 completed
+    Cmd2Completed,
+    /// Device lacks PD or has incompatible PD version
+    DeviceIncompatible,
+    /// Source cannot supply requested voltage or current
+    CannotSource,
+    /// Source can supply requested voltage or current later
+    CanSourceLater,
+    /// Voltage or current exceeded
+    PowerEventError,
+    /// Device did not respond to get caps message
+    NoCapsResponse,
+    /// Unexpected message received from partner
+    ProtocolError,
+    /// Sink transition completed
+    SinkTransitionCompleted,
+    /// Plug connected but not debounced
+    PlugEarlyNotification,
+    /// Prochot asserted
+    ProchotNotification,
+    /// Source cannot produce negotiated voltage or current
+    SourceCannotProvide,
+    /// Alternate mode entry failed
+    AmEntryFail,
+    /// Alternate mode entered
+    AmEntered,
+    /// Discover modes process completed
+    DiscoverModeCompleted,
+    /// Exit mode process completed
+    ExitModeCompleted,
+    /// Data reset process started
+    DataResetStarted,
+    /// USB status updated
+    UsbStatusUpdated,
+    /// Connection manager updated
+    ConnectionManagerUpdated,
+    /// User VID alternate mode entered
+    UsvidModeEntered,
+    /// User VID alternate mode exited
+    UsvidModeExited,
+    /// User VID SVDM attention received
+    UsvidAttentionVdmReceived,
+    /// User VID SVDM non-attention or unstructured VDM received
+    UsvidOtherVdmReceived,
+    /// External DCDC event
+    ExternalDcDcEvent,
+    /// DP SID status register changed
+    DpSidStatusUpdated,
+    /// Intel VID status register changed
+    IntelVidStatusUpdated,
+    /// PD3 status register changed
+    Pd3StatusUpdated,
+    /// TX memory buffer empty
+    TxMemoryBufferEmpty,
+    /// Buffer for mbrd command received and ready
+    MbrdBufferReady,
+    /// SOC ack timeout
+    SocAckTimeout,
+    /// Not supported PD message received
+    NotSupportedReceived,
+    /// Error configuring the crossbar mux
+    CrossbarError,
+    /// Mailbox updated
+    MailboxUpdated,
+    /// I2C error communicating with external bus
+    BusError,
+    /// External DCDC status changed
+    ExternalDcDcStatusChanged,
+    /// Fast role swap signal received
+    FrsSignalReceived,
+    /// Chunk response received
+    ChunkResponseReceived,
+    /// Chunk request received
+    ChunkRequestReceived,
+    /// Alert message received
+    AlertMessageReceived,
+    /// Patch loaded to device
+    PatchLoaded,
+    /// Ready for F211 image
+    ReadyF211,
+    /// Boot error
+    BootError,
+    /// Ready for data block
+    ReadyForDataBlock,
+}
+
+impl PdEvent {
+    /// All events, in register bit order
+    pub const ALL: [PdEvent; NUM_EVENTS] = [
+        PdEvent::HardReset,
+        PdEvent::PlugEvent,
+        PdEvent::PowerSwapCompleted,
+        PdEvent::DataSwapCompleted,
+        PdEvent::FastRoleSwapCompleted,
+        PdEvent::SourceCapUpdated,
+        PdEvent::SinkReady,
+        PdEvent::Overcurrent,
+        PdEvent::AttentionReceived,
+        PdEvent::VdmReceived,
+        PdEvent::NewConsumerContract,
+        PdEvent::NewProviderContract,
+        PdEvent::SourceCapsReceived,
+        PdEvent::SinkCapsReceived,
+        PdEvent::PowerSwapRequested,
+        PdEvent::DataSwapRequested,
+        PdEvent::UsbHostPresent,
+        PdEvent::UsbHostNotPresent,
+        PdEvent::PowerPathSwitchChanged,
+        PdEvent::DataStatusUpdated,
+        PdEvent::StatusUpdated,
+        PdEvent::PdStatusUpdated,
+        PdEvent::Cmd1Completed,
+        PdEvent::Cmd2Completed,
+        PdEvent::DeviceIncompatible,
+        PdEvent::CannotSource,
+        PdEvent::CanSourceLater,
+        PdEvent::PowerEventError,
+        PdEvent::NoCapsResponse,
+        PdEvent::ProtocolError,
+        PdEvent::SinkTransitionCompleted,
+        PdEvent::PlugEarlyNotification,
+        PdEvent::ProchotNotification,
+        PdEvent::SourceCannotProvide,
+        PdEvent::AmEntryFail,
+        PdEvent::AmEntered,
+        PdEvent::DiscoverModeCompleted,
+        PdEvent::ExitModeCompleted,
+        PdEvent::DataResetStarted,
+        PdEvent::UsbStatusUpdated,
+        PdEvent::ConnectionManagerUpdated,
+        PdEvent::UsvidModeEntered,
+        PdEvent::UsvidModeExited,
+        PdEvent::UsvidAttentionVdmReceived,
+        PdEvent::UsvidOtherVdmReceived,
+        PdEvent::ExternalDcDcEvent,
+        PdEvent::DpSidStatusUpdated,
+        PdEvent::IntelVidStatusUpdated,
+        PdEvent::Pd3StatusUpdated,
+        PdEvent::TxMemoryBufferEmpty,
+        PdEvent::MbrdBufferReady,
+        PdEvent::SocAckTimeout,
+        PdEvent::NotSupportedReceived,
+        PdEvent::CrossbarError,
+        PdEvent::MailboxUpdated,
+        PdEvent::BusError,
+        PdEvent::ExternalDcDcStatusChanged,
+        PdEvent::FrsSignalReceived,
+        PdEvent::ChunkResponseReceived,
+        PdEvent::ChunkRequestReceived,
+        PdEvent::AlertMessageReceived,
+        PdEvent::PatchLoaded,
+        PdEvent::ReadyF211,
+        PdEvent::BootError,
+        PdEvent::ReadyForDataBlock,
+    ];
+}
+
+impl IntEventBus1 {
+    /// Returns true if the given event is set
+    pub fn event(&self, event: PdEvent) -> bool {
+        match event {
+            PdEvent::HardReset => self.hard_reset(),
+            PdEvent::PlugEvent => self.plug_event(),
+            PdEvent::PowerSwapCompleted => self.power_swap_completed(),
+            PdEvent::DataSwapCompleted => self.data_swap_completed(),
+            PdEvent::FastRoleSwapCompleted => self.fast_role_swap_completed(),
+            PdEvent::SourceCapUpdated => self.source_cap_updated(),
+            PdEvent::SinkReady => self.sink_ready(),
+            PdEvent::Overcurrent => self.overcurrent(),
+            PdEvent::AttentionReceived => self.attention_received(),
+            PdEvent::VdmReceived => self.vdm_received(),
+            PdEvent::NewConsumerContract => self.new_consumer_contract(),
+            PdEvent::NewProviderContract => self.new_provider_contract(),
+            PdEvent::SourceCapsReceived => self.source_caps_received(),
+            PdEvent::SinkCapsReceived => self.sink_caps_received(),
+            PdEvent::PowerSwapRequested => self.power_swap_requested(),
+            PdEvent::DataSwapRequested => self.data_swap_requested(),
+            PdEvent::UsbHostPresent => self.usb_host_present(),
+            PdEvent::UsbHostNotPresent => self.usb_host_not_present(),
+            PdEvent::PowerPathSwitchChanged => self.power_path_switch_changed(),
+            PdEvent::DataStatusUpdated => self.data_status_updated(),
+            PdEvent::StatusUpdated => self.status_updated(),
+            PdEvent::PdStatusUpdated => self.pd_status_updated(),
+            PdEvent::Cmd1Completed => self.cmd_1_completed(),
+            PdEvent::Cmd2Completed => self.cmd_2_completed(),
+            PdEvent::DeviceIncompatible => self.device_incompatible(),
+            PdEvent::CannotSource => self.cannot_source(),
+            PdEvent::CanSourceLater => self.can_source_later(),
+            PdEvent::PowerEventError => self.power_event_error(),
+            PdEvent::NoCapsResponse => self.no_caps_response(),
+            PdEvent::ProtocolError => self.protocol_error(),
+            PdEvent::SinkTransitionCompleted => self.sink_transition_completed(),
+            PdEvent::PlugEarlyNotification => self.plug_early_notification(),
+            PdEvent::ProchotNotification => self.prochot_notification(),
+            PdEvent::SourceCannotProvide => self.source_cannot_provide(),
+            PdEvent::AmEntryFail => self.am_entry_fail(),
+            PdEvent::AmEntered => self.am_entered(),
+            PdEvent::DiscoverModeCompleted => self.discover_mode_completed(),
+            PdEvent::ExitModeCompleted => self.exit_mode_completed(),
+            PdEvent::DataResetStarted => self.data_reset_started(),
+            PdEvent::UsbStatusUpdated => self.usb_status_updated(),
+            PdEvent::ConnectionManagerUpdated => self.connection_manager_updated(),
+            PdEvent::UsvidModeEntered => self.usvid_mode_entered(),
+            PdEvent::UsvidModeExited => self.usvid_mode_exited(),
+            PdEvent::UsvidAttentionVdmReceived => self.usvid_attention_vdm_received(),
+            PdEvent::UsvidOtherVdmReceived => self.usvid_other_vdm_received(),
+            PdEvent::ExternalDcDcEvent => self.external_dc_dc_event(),
+            PdEvent::DpSidStatusUpdated => self.dp_sid_status_updated(),
+            PdEvent::IntelVidStatusUpdated => self.intel_vid_status_updated(),
+            PdEvent::Pd3StatusUpdated => self.pd_3_status_updated(),
+            PdEvent::TxMemoryBufferEmpty => self.tx_memory_buffer_empty(),
+            PdEvent::MbrdBufferReady => self.mbrd_buffer_ready(),
+            PdEvent::SocAckTimeout => self.soc_ack_timeout(),
+            PdEvent::NotSupportedReceived => self.not_supported_received(),
+            PdEvent::CrossbarError => self.crossbar_error(),
+            PdEvent::MailboxUpdated => self.mailbox_updated(),
+            PdEvent::BusError => self.bus_error(),
+            PdEvent::ExternalDcDcStatusChanged => self.external_dc_dc_status_changed(),
+            PdEvent::FrsSignalReceived => self.frs_signal_received(),
+            PdEvent::ChunkResponseReceived => self.chunk_response_received(),
+            PdEvent::ChunkRequestReceived => self.chunk_request_received(),
+            PdEvent::AlertMessageReceived => self.alert_message_received(),
+            PdEvent::PatchLoaded => self.patch_loaded(),
+            PdEvent::ReadyF211 => self.ready_f_211(),
+            PdEvent::BootError => self.boot_error(),
+            PdEvent::ReadyForDataBlock => self.ready_for_data_block(),
+        }
+    }
+
+    /// Set or clear the given event
+    pub fn set_event(&mut self, event: PdEvent, value: bool) {
+        match event {
+            PdEvent::HardReset => self.set_hard_reset(value),
+            PdEvent::PlugEvent => self.set_plug_event(value),
+            PdEvent::PowerSwapCompleted => self.set_power_swap_completed(value),
+            PdEvent::DataSwapCompleted => self.set_data_swap_completed(value),
+            PdEvent::FastRoleSwapCompleted => self.set_fast_role_swap_completed(value),
+            PdEvent::SourceCapUpdated => self.set_source_cap_updated(value),
+            PdEvent::SinkReady => self.set_sink_ready(value),
+            PdEvent::Overcurrent => self.set_overcurrent(value),
+            PdEvent::AttentionReceived => self.set_attention_received(value),
+            PdEvent::VdmReceived => self.set_vdm_received(value),
+            PdEvent::NewConsumerContract => self.set_new_consumer_contract(value),
+            PdEvent::NewProviderContract => self.set_new_provider_contract(value),
+            PdEvent::SourceCapsReceived => self.set_source_caps_received(value),
+            PdEvent::SinkCapsReceived => self.set_sink_caps_received(value),
+            PdEvent::PowerSwapRequested => self.set_power_swap_requested(value),
+            PdEvent::DataSwapRequested => self.set_data_swap_requested(value),
+            PdEvent::UsbHostPresent => self.set_usb_host_present(value),
+            PdEvent::UsbHostNotPresent => self.set_usb_host_not_present(value),
+            PdEvent::PowerPathSwitchChanged => self.set_power_path_switch_changed(value),
+            PdEvent::DataStatusUpdated => self.set_data_status_updated(value),
+            PdEvent::StatusUpdated => self.set_status_updated(value),
+            PdEvent::PdStatusUpdated => self.set_pd_status_updated(value),
+            PdEvent::Cmd1Completed => self.set_cmd_1_completed(value),
+            PdEvent::Cmd2Completed => self.set_cmd_2_completed(value),
+            PdEvent::DeviceIncompatible => self.set_device_incompatible(value),
+            PdEvent::CannotSource => self.set_cannot_source(value),
+            PdEvent::CanSourceLater => self.set_can_source_later(value),
+            PdEvent::PowerEventError => self.set_power_event_error(value),
+            PdEvent::NoCapsResponse => self.set_no_caps_response(value),
+            PdEvent::ProtocolError => self.set_protocol_error(value),
+            PdEvent::SinkTransitionCompleted => self.set_sink_transition_completed(value),
+            PdEvent::PlugEarlyNotification => self.set_plug_early_notification(value),
+            PdEvent::ProchotNotification => self.set_prochot_notification(value),
+            PdEvent::SourceCannotProvide => self.set_source_cannot_provide(value),
+            PdEvent::AmEntryFail => self.set_am_entry_fail(value),
+            PdEvent::AmEntered => self.set_am_entered(value),
+            PdEvent::DiscoverModeCompleted => self.set_discover_mode_completed(value),
+            PdEvent::ExitModeCompleted => self.set_exit_mode_completed(value),
+            PdEvent::DataResetStarted => self.set_data_reset_started(value),
+            PdEvent::UsbStatusUpdated => self.set_usb_status_updated(value),
+            PdEvent::ConnectionManagerUpdated => self.set_connection_manager_updated(value),
+            PdEvent::UsvidModeEntered => self.set_usvid_mode_entered(value),
+            PdEvent::UsvidModeExited => self.set_usvid_mode_exited(value),
+            PdEvent::UsvidAttentionVdmReceived => self.set_usvid_attention_vdm_received(value),
+            PdEvent::UsvidOtherVdmReceived => self.set_usvid_other_vdm_received(value),
+            PdEvent::ExternalDcDcEvent => self.set_external_dc_dc_event(value),
+            PdEvent::DpSidStatusUpdated => self.set_dp_sid_status_updated(value),
+            PdEvent::IntelVidStatusUpdated => self.set_intel_vid_status_updated(value),
+            PdEvent::Pd3StatusUpdated => self.set_pd_3_status_updated(value),
+            PdEvent::TxMemoryBufferEmpty => self.set_tx_memory_buffer_empty(value),
+            PdEvent::MbrdBufferReady => self.set_mbrd_buffer_ready(value),
+            PdEvent::SocAckTimeout => self.set_soc_ack_timeout(value),
+            PdEvent::NotSupportedReceived => self.set_not_supported_received(value),
+            PdEvent::CrossbarError => self.set_crossbar_error(value),
+            PdEvent::MailboxUpdated => self.set_mailbox_updated(value),
+            PdEvent::BusError => self.set_bus_error(value),
+            PdEvent::ExternalDcDcStatusChanged => self.set_external_dc_dc_status_changed(value),
+            PdEvent::FrsSignalReceived => self.set_frs_signal_received(value),
+            PdEvent::ChunkResponseReceived => self.set_chunk_response_received(value),
+            PdEvent::ChunkRequestReceived => self.set_chunk_request_received(value),
+            PdEvent::AlertMessageReceived => self.set_alert_message_received(value),
+            PdEvent::PatchLoaded => self.set_patch_loaded(value),
+            PdEvent::ReadyF211 => self.set_ready_f_211(value),
+            PdEvent::BootError => self.set_boot_error(value),
+            PdEvent::ReadyForDataBlock => self.set_ready_for_data_block(value),
+        }
+    }
+
+    /// Returns an iterator over the events that are set, in register bit order
+    pub fn events(&self) -> impl Iterator<Item = PdEvent> + use<> {
+        let flags = *self;
+        PdEvent::ALL.into_iter().filter(move |&event| flags.event(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_events_empty() {
+        assert_eq!(IntEventBus1::new_zero().events().next(), None);
+    }
+
+    #[test]
+    fn test_events_all() {
+        let events: Vec<_> = IntEventBus1::all().events().collect();
+        assert_eq!(events, PdEvent::ALL);
+    }
+
+    #[test]
+    fn test_events_single() {
+        for event in PdEvent::ALL {
+            let mut flags = IntEventBus1::new_zero();
+            flags.set_event(event, true);
+            assert!(flags.event(event));
+
+            let events: Vec<_> = flags.events().collect();
+            assert_eq!(events, [event]);
+
+            flags.set_event(event, false);
+            assert_eq!(flags, IntEventBus1::new_zero());
+        }
+    }
+
+    #[test]
+    fn test_events_order() {
+        let mut flags = IntEventBus1::new_zero();
+        flags.set_cmd_1_completed(true);
+        flags.set_plug_event(true);
+        flags.set_hard_reset(true);
+
+        let events: Vec<_> = flags.events().collect();
+        assert_eq!(events, [PdEvent::HardReset, PdEvent::PlugEvent, PdEvent::Cmd1Completed]);
+    }
+}
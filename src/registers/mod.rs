@@ -1,12 +1,14 @@
 use embedded_usb_pd::type_c::ConnectionState;
 use embedded_usb_pd::{PdError, type_c};
 
-use crate::Mode;
+use crate::{Mode, error, warn};
 
 pub mod autonegotiate_sink;
 pub mod boot_flags;
+pub mod device_info;
 pub mod discovered_svids;
 pub mod dp_status;
+pub mod int_event;
 pub mod port_config;
 pub mod received_sop_identity_data;
 pub mod received_sop_prime_identity_data;
@@ -36,6 +38,128 @@ pub const REG_DATA1: u8 = 0x09;
 // Command data 1 register length
 pub const REG_DATA1_LEN: usize = 64;
 
+/// Maximum length of register data, the length is sent as a single byte
+pub(crate) const MAX_REGISTER_LEN: usize = 255;
+/// Size of a buffer that can hold a framed register write, +2 for the register address and length byte, +1 for the
+/// optional PEC byte
+pub(crate) const REGISTER_WRITE_BUF_LEN: usize = MAX_REGISTER_LEN + 3;
+/// Size of a buffer that can hold a register read response, +1 for the length byte, +1 for the optional PEC byte
+pub(crate) const REGISTER_READ_BUF_LEN: usize = MAX_REGISTER_LEN + 2;
+
+/// Compute the SMBus packet error code (CRC-8, polynomial x^8 + x^2 + x + 1) over `data`, continuing from `crc`
+pub(crate) fn pec(crc: u8, data: &[u8]) -> u8 {
+    data.iter().fold(crc, |crc, byte| {
+        (0..8).fold(
+            crc ^ byte,
+            |crc, _| {
+                if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 }
+            },
+        )
+    })
+}
+
+/// Frame a register write as the register address, data length and data, returns the framed bytes
+pub(crate) fn encode_register_write<'a>(
+    address: u8,
+    data: &[u8],
+    buf: &'a mut [u8; REGISTER_WRITE_BUF_LEN],
+) -> Result<&'a [u8], PdError> {
+    // Buffer length is sent as a byte
+    if data.len() > MAX_REGISTER_LEN {
+        return Err(PdError::InvalidParams);
+    }
+
+    buf[0] = address;
+    buf[1] = data.len() as u8;
+    buf.get_mut(2..data.len() + 2)
+        .ok_or(PdError::InvalidParams)?
+        .copy_from_slice(data);
+    buf.get(..data.len() + 2).ok_or(PdError::InvalidParams)
+}
+
+/// Frame a register write like [`encode_register_write`] followed by an SMBus PEC byte
+///
+/// `i2c_addr` is the 7-bit address of the controller, the PEC covers the address byte as sent on the bus.
+pub(crate) fn encode_register_write_pec<'a>(
+    i2c_addr: u8,
+    address: u8,
+    data: &[u8],
+    buf: &'a mut [u8; REGISTER_WRITE_BUF_LEN],
+) -> Result<&'a [u8], PdError> {
+    let len = encode_register_write(address, data, buf)?.len();
+    let crc = pec(pec(0, &[i2c_addr << 1]), buf.get(..len).ok_or(PdError::InvalidParams)?);
+    *buf.get_mut(len).ok_or(PdError::InvalidParams)? = crc;
+    buf.get(..len + 1).ok_or(PdError::InvalidParams)
+}
+
+/// Verify the SMBus PEC byte of a read response and extract the register data like [`decode_register_read`]
+///
+/// The response must have been read as `data.len() + 2` bytes so the PEC byte following the reported length is
+/// present. If the controller reports more bytes than `data` can hold the read was terminated before the PEC byte and
+/// can't be verified, this returns [`PdError::InvalidResponse`], as does a PEC mismatch.
+pub(crate) fn decode_register_read_pec(
+    i2c_addr: u8,
+    address: u8,
+    response: &[u8],
+    data: &mut [u8],
+) -> Result<(), PdError> {
+    let len = *response.first().ok_or(PdError::InvalidParams)? as usize;
+    // Busy responses are handled by decode_register_read
+    if len != 0 && len != 0xff {
+        if len > data.len() {
+            error!(
+                "Register {:#02x} reported {} bytes, PEC can't be verified on a {} byte read",
+                address,
+                len,
+                data.len()
+            );
+            return Err(PdError::InvalidResponse);
+        }
+
+        let frame = response.get(..len + 1).ok_or(PdError::InvalidParams)?;
+        let expected = pec(pec(0, &[i2c_addr << 1, address, (i2c_addr << 1) | 1]), frame);
+        let actual = *response.get(len + 1).ok_or(PdError::InvalidParams)?;
+        if actual != expected {
+            error!(
+                "PEC mismatch on register {:#02x}: expected {:#02x}, got {:#02x}",
+                address, expected, actual
+            );
+            return Err(PdError::InvalidResponse);
+        }
+    }
+
+    decode_register_read(address, response, data)
+}
+
+/// Extract register data from a read response, which starts with the number of valid bytes
+///
+/// If the controller reports fewer bytes than `data` can hold, only the reported bytes are copied and the rest of
+/// `data` is zeroed. Any bytes past the reported length are ignored. A length of 0 or 0xff means the controller
+/// is busy and returns [`PdError::Busy`].
+pub(crate) fn decode_register_read(address: u8, response: &[u8], data: &mut [u8]) -> Result<(), PdError> {
+    let len = *response.first().ok_or(PdError::InvalidParams)? as usize;
+    if len < data.len() {
+        // Just log a warning to workaround registers that might not match the TRM documentation
+        warn!(
+            "Under-read from TPS6699x register {:#02x}: expected {} bytes, got {}",
+            address,
+            data.len(),
+            len
+        );
+    }
+
+    if len == 0xff || len == 0 {
+        // Controller is busy and can't respond
+        Err(PdError::Busy)
+    } else {
+        // Only copy the bytes the controller actually returned, zero the rest rather than leaving stale data
+        let (valid, remaining) = data.split_at_mut(len.min(data.len()));
+        valid.copy_from_slice(response.get(1..valid.len() + 1).ok_or(PdError::InvalidParams)?);
+        remaining.fill(0);
+        Ok(())
+    }
+}
+
 impl TryFrom<TypecCurrent> for type_c::Current {
     type Error = PdError;
 
@@ -112,12 +236,123 @@ impl field_sets::IntEventBus1 {
     pub fn all() -> Self {
         field_sets::IntEventBus1::from([0xFF; 11])
     }
+
+    /// Create an IntEventBus1 with the events that indicate thermal stress set
+    ///
+    /// Only PROCHOT assertion is reported directly. An over-temperature shutdown shows up in the PD status error
+    /// recovery details, which are updated along with many unrelated events, so check those on `pd_status_updated`.
+    pub fn thermal() -> Self {
+        let mut events = field_sets::IntEventBus1::new_zero();
+        events.set_prochot_notification(true);
+        events
+    }
+
+    /// Returns true if any event that indicates thermal stress is set
+    pub fn thermal_event(&self) -> bool {
+        (*self & Self::thermal()) != field_sets::IntEventBus1::new_zero()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_pec() {
+        // Standard CRC-8/SMBUS check value
+        assert_eq!(pec(0, b"123456789"), 0xf4);
+        assert_eq!(pec(0, &[]), 0);
+        // Continuing a CRC is the same as computing it in one go
+        assert_eq!(pec(pec(0, b"1234"), b"56789"), 0xf4);
+    }
+
+    #[test]
+    fn test_encode_register_write_pec() {
+        let mut buf = [0u8; REGISTER_WRITE_BUF_LEN];
+        let frame = encode_register_write_pec(0x20, 0x08, &[0x12, 0x34, 0x56, 0x78], &mut buf).unwrap();
+        assert_eq!(frame, &[0x08, 4, 0x12, 0x34, 0x56, 0x78, 0x99]);
+    }
+
+    #[test]
+    fn test_decode_register_read_pec() {
+        let mut data = [0xaa; 4];
+        decode_register_read_pec(0x20, 0x03, &[4, b'A', b'P', b'P', b' ', 0x2f], &mut data).unwrap();
+        assert_eq!(&data, b"APP ");
+
+        // Short response, the PEC follows the reported length
+        let response = [2, 1, 2, pec(0, &[0x40, 0x03, 0x41, 2, 1, 2]), 0xff];
+        let mut data = [0xaa; 3];
+        decode_register_read_pec(0x20, 0x03, &response, &mut data).unwrap();
+        assert_eq!(data, [1, 2, 0]);
+
+        // PEC mismatch
+        let mut data = [0xaa; 4];
+        assert_eq!(
+            decode_register_read_pec(0x20, 0x03, &[4, b'A', b'P', b'P', b' ', 0x30], &mut data),
+            Err(PdError::InvalidResponse)
+        );
+
+        // Reported length longer than the read, PEC wasn't received
+        let mut data = [0xaa; 2];
+        assert_eq!(
+            decode_register_read_pec(0x20, 0x03, &[4, 1, 2, 0x00], &mut data),
+            Err(PdError::InvalidResponse)
+        );
+
+        // Busy is reported before the PEC is checked
+        let mut data = [0xaa; 2];
+        assert_eq!(
+            decode_register_read_pec(0x20, 0x03, &[0, 0, 0, 0], &mut data),
+            Err(PdError::Busy)
+        );
+    }
+
+    #[test]
+    fn test_decode_register_read() {
+        let mut data = [0xaa; 4];
+        decode_register_read(0x00, &[4, 1, 2, 3, 4], &mut data).unwrap();
+        assert_eq!(data, [1, 2, 3, 4]);
+
+        // Short response, garbage past the reported length is ignored
+        let mut data = [0xaa; 4];
+        decode_register_read(0x00, &[2, 1, 2, 0xff, 0xff], &mut data).unwrap();
+        assert_eq!(data, [1, 2, 0, 0]);
+
+        // Long response, only the requested bytes are copied
+        let mut data = [0xaa; 2];
+        decode_register_read(0x00, &[4, 1, 2, 3, 4], &mut data).unwrap();
+        assert_eq!(data, [1, 2]);
+
+        // Busy
+        let mut data = [0xaa; 2];
+        assert_eq!(decode_register_read(0x00, &[0, 1, 2], &mut data), Err(PdError::Busy));
+        assert_eq!(decode_register_read(0x00, &[0xff, 1, 2], &mut data), Err(PdError::Busy));
+
+        // Response too short to hold the requested data
+        assert_eq!(
+            decode_register_read(0x00, &[2, 1], &mut data),
+            Err(PdError::InvalidParams)
+        );
+        assert_eq!(decode_register_read(0x00, &[], &mut data), Err(PdError::InvalidParams));
+    }
+
+    #[test]
+    fn test_int_event_thermal() {
+        assert!(!field_sets::IntEventBus1::new_zero().thermal_event());
+        assert!(field_sets::IntEventBus1::all().thermal_event());
+
+        let mut events = field_sets::IntEventBus1::new_zero();
+        events.set_plug_event(true);
+        assert!(!events.thermal_event());
+
+        // A plain PD status update isn't a thermal event
+        events.set_pd_status_updated(true);
+        assert!(!events.thermal_event());
+
+        events.set_prochot_notification(true);
+        assert!(events.thermal_event());
+    }
+
     #[test]
     fn test_convert_type_c_current() {
         let current: type_c::Current = TypecCurrent::UsbDefault.try_into().unwrap();
@@ -6,6 +6,7 @@
 //! - Use a units crate for safely expressing values like 50 mV per LSB, etc.
 
 use bitfield::bitfield;
+use embedded_usb_pd::PdError;
 use embedded_usb_pd::pdo::MV50_UNIT;
 
 /// The address of the `Port Configuration` register.
@@ -282,6 +283,39 @@ impl From<VbusSinkUvpTripHv> for u8 {
     }
 }
 
+impl VbusSinkUvpTripHv {
+    /// Supported UVP trip points, as a percentage below the contract voltage
+    pub const SUPPORTED_PERCENT: [u8; 7] = [5, 10, 15, 20, 25, 30, 40];
+
+    /// UVP trip point as a percentage below the contract voltage, `None` if reserved
+    pub fn percent(self) -> Option<u8> {
+        match self {
+            VbusSinkUvpTripHv::Pct5 => Some(5),
+            VbusSinkUvpTripHv::Pct10 => Some(10),
+            VbusSinkUvpTripHv::Pct15 => Some(15),
+            VbusSinkUvpTripHv::Pct20 => Some(20),
+            VbusSinkUvpTripHv::Pct25 => Some(25),
+            VbusSinkUvpTripHv::Pct30 => Some(30),
+            VbusSinkUvpTripHv::Pct40 => Some(40),
+            VbusSinkUvpTripHv::Reserved(_) => None,
+        }
+    }
+
+    /// Create from a percentage below the contract voltage, `None` if not supported
+    pub fn from_percent(percent: u8) -> Option<Self> {
+        match percent {
+            5 => Some(VbusSinkUvpTripHv::Pct5),
+            10 => Some(VbusSinkUvpTripHv::Pct10),
+            15 => Some(VbusSinkUvpTripHv::Pct15),
+            20 => Some(VbusSinkUvpTripHv::Pct20),
+            25 => Some(VbusSinkUvpTripHv::Pct25),
+            30 => Some(VbusSinkUvpTripHv::Pct30),
+            40 => Some(VbusSinkUvpTripHv::Pct40),
+            _ => None,
+        }
+    }
+}
+
 /// OVP for PP5V
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -354,6 +388,32 @@ impl From<VbusOvpUsage> for u8 {
     }
 }
 
+impl VbusOvpUsage {
+    /// Supported OVP trip points, as a percentage of the contract voltage
+    pub const SUPPORTED_PERCENT: [u8; 4] = [100, 105, 111, 114];
+
+    /// OVP trip point as a percentage of the contract voltage
+    pub fn percent(self) -> u8 {
+        match self {
+            VbusOvpUsage::Pct100 => 100,
+            VbusOvpUsage::Pct105 => 105,
+            VbusOvpUsage::Pct111 => 111,
+            VbusOvpUsage::Pct114 => 114,
+        }
+    }
+
+    /// Create from a percentage of the contract voltage, `None` if not supported
+    pub fn from_percent(percent: u8) -> Option<Self> {
+        match percent {
+            100 => Some(VbusOvpUsage::Pct100),
+            105 => Some(VbusOvpUsage::Pct105),
+            111 => Some(VbusOvpUsage::Pct111),
+            114 => Some(VbusOvpUsage::Pct114),
+            _ => None,
+        }
+    }
+}
+
 /// USB3 Rate configuration.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -422,6 +482,39 @@ impl From<TypeCSupportOptions> for u8 {
     }
 }
 
+/// Role a DRP port prefers when attaching to another DRP, selected through [`TypeCSupportOptions`]
+///
+/// The preference only applies while [`PortConfig::typec_state_machine`] is [`TypeCStateMachine::Drp`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DrpPreference {
+    /// No preference, the role is decided by the normal DRP toggle
+    None,
+    /// Prefer the source role through the Try.SRC state
+    TrySrc,
+}
+
+impl From<DrpPreference> for TypeCSupportOptions {
+    fn from(value: DrpPreference) -> Self {
+        match value {
+            DrpPreference::None => TypeCSupportOptions::None,
+            DrpPreference::TrySrc => TypeCSupportOptions::TrySrcDrp,
+        }
+    }
+}
+
+impl TryFrom<TypeCSupportOptions> for DrpPreference {
+    type Error = PdError;
+
+    fn try_from(value: TypeCSupportOptions) -> Result<Self, Self::Error> {
+        match value {
+            TypeCSupportOptions::None => Ok(DrpPreference::None),
+            TypeCSupportOptions::TrySrcDrp => Ok(DrpPreference::TrySrc),
+            TypeCSupportOptions::Reserved(_) => Err(PdError::InvalidParams),
+        }
+    }
+}
+
 /// Crossbar Type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -840,6 +933,17 @@ impl PortConfig {
         self
     }
 
+    /// DRP role preference, decoded from [`Self::typec_support_options`].
+    ///
+    /// Returns [`PdError::InvalidParams`] if the register contains a reserved value.
+    pub fn drp_preference(&self) -> Result<DrpPreference, PdError> {
+        self.typec_support_options().try_into()
+    }
+    /// Set [`Self::drp_preference`] and return `self` to chain.
+    pub fn set_drp_preference(&mut self, value: DrpPreference) -> &mut Self {
+        self.set_typec_support_options(value.into())
+    }
+
     /// PP_EXT ActiveLow.
     pub fn pp_ext_active_low(&self) -> bool {
         self.0.pp_ext_active_low()
@@ -1078,6 +1182,30 @@ mod tests {
         assert_eq!(u8::from(VbusOvpUsage::Pct114), 0x3);
     }
 
+    #[test]
+    fn test_vbus_ovp_usage_percent() {
+        for percent in VbusOvpUsage::SUPPORTED_PERCENT {
+            assert_eq!(VbusOvpUsage::from_percent(percent).unwrap().percent(), percent);
+        }
+        assert_eq!(VbusOvpUsage::from_percent(0), None);
+        assert_eq!(VbusOvpUsage::from_percent(110), None);
+        assert_eq!(VbusOvpUsage::from_percent(120), None);
+    }
+
+    #[test]
+    fn test_vbus_sink_uvp_trip_hv_percent() {
+        for percent in VbusSinkUvpTripHv::SUPPORTED_PERCENT {
+            assert_eq!(
+                VbusSinkUvpTripHv::from_percent(percent).unwrap().percent(),
+                Some(percent)
+            );
+        }
+        assert_eq!(VbusSinkUvpTripHv::from_percent(0), None);
+        assert_eq!(VbusSinkUvpTripHv::from_percent(35), None);
+        assert_eq!(VbusSinkUvpTripHv::from_percent(50), None);
+        assert_eq!(VbusSinkUvpTripHv::Reserved(0x7).percent(), None);
+    }
+
     // Usb3Rate tests
     #[test]
     fn test_usb3_rate_from_u8() {
@@ -1113,6 +1241,23 @@ mod tests {
         assert_eq!(u8::from(TypeCSupportOptions::Reserved(0x2)), 0x2);
     }
 
+    #[test]
+    fn test_drp_preference() {
+        // TypeC Support Options is bits 8-9, in the second byte
+        for (pref, raw) in [(DrpPreference::None, 0x0), (DrpPreference::TrySrc, 0x1)] {
+            let mut config = PortConfig::default();
+            config.set_drp_preference(pref);
+            assert_eq!(config.as_bytes()[1], raw);
+            assert_eq!(config.drp_preference(), Ok(pref));
+        }
+
+        for reserved in [0x2, 0x3] {
+            let mut config = PortConfig::default();
+            config.set_typec_support_options(TypeCSupportOptions::Reserved(reserved));
+            assert_eq!(config.drp_preference(), Err(PdError::InvalidParams));
+        }
+    }
+
     // CrossbarType tests
     #[test]
     fn test_crossbar_type_from_bool() {
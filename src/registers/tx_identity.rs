@@ -462,6 +462,39 @@ mod tests {
         assert_eq!(u8::from(ProductTypeUfp::Reserved(0x4)), 0x4);
     }
 
+    /// Set each field to all ones and confirm it doesn't share bits with any other field
+    #[test]
+    fn test_fields_disjoint() {
+        type Setter = fn(&mut TxIdentityRaw<[u8; LEN]>);
+        let fields: [(Setter, u32); 12] = [
+            (|r| r.set_number_valid_vdos(0x7), 3),
+            (|r| r.set_vendor_id(u16::MAX), 16),
+            (|r| r.set_product_type_dfp(0x7), 3),
+            (|r| r.set_modal_operation_supported(true), 1),
+            (|r| r.set_product_type_ufp(0x7), 3),
+            (|r| r.set_usb_communication_capable_as_device(true), 1),
+            (|r| r.set_usb_communication_capable_as_host(true), 1),
+            (|r| r.set_certification_test_id(u32::MAX), 32),
+            (|r| r.set_bcd_device(u16::MAX), 16),
+            (|r| r.set_usb_product_id(u16::MAX), 16),
+            (|r| r.set_ufp1_vdo(u32::MAX), 32),
+            (|r| r.set_dfp1_vdo(u32::MAX), 32),
+        ];
+
+        let mut used = [0u8; LEN];
+        for (set, width) in fields {
+            let mut raw = TxIdentityRaw([0u8; LEN]);
+            set(&mut raw);
+
+            // Field only sets its own bits
+            assert_eq!(raw.0.iter().map(|b| b.count_ones()).sum::<u32>(), width);
+            for (used, bits) in used.iter_mut().zip(raw.0.iter()) {
+                assert_eq!(*used & bits, 0);
+                *used |= bits;
+            }
+        }
+    }
+
     #[test]
     fn test_tx_identity_nonzero_roundtrip() {
         const EXPECTED: [u8; LEN] = [
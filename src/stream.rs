@@ -60,6 +60,16 @@ impl SeekingStream {
         }
     }
 
+    /// Current offset within the overall stream
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Jump directly to the target, for sources that can seek instead of supplying the skipped bytes
+    pub fn skip_to_target(&mut self) {
+        self.position = self.target;
+    }
+
     /// Supply a byte slice to the stream
     /// Returns the seeked slice
     pub fn seek_bytes<'a>(&mut self, data: &'a [u8]) -> &'a [u8] {
@@ -137,6 +147,11 @@ impl ReadingStream {
         }
     }
 
+    /// Current offset within the overall stream
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+
     /// Supply a byte slice to the stream
     pub fn read_bytes<'a>(&mut self, data: &'a [u8]) -> ReadResult<'a> {
         trace!("Starting read at {:#x}", self.position);
@@ -207,6 +222,17 @@ mod test {
         assert_eq!(stream.position, 2);
     }
 
+    /// Test skipping directly to the seek target
+    #[test]
+    fn test_skip_to_target() {
+        let mut stream = SeekingStream::new(0, 4);
+        stream.skip_to_target();
+        assert_eq!(stream.position(), 4);
+        let remaining_data = stream.seek_bytes(&[4, 5]);
+        assert_eq!(remaining_data, &[4, 5]);
+        assert_eq!(stream.position(), 4);
+    }
+
     /// Test that a seek backwards fails
     #[test]
     fn test_seek_backwards() {